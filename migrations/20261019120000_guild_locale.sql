@@ -0,0 +1 @@
+ALTER TABLE guilds ADD COLUMN locale TEXT;
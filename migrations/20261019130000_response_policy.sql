@@ -0,0 +1,10 @@
+ALTER TABLE guilds ADD COLUMN quiet BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE guilds ADD COLUMN announce_channel_id TEXT;
+
+CREATE TABLE IF NOT EXISTS command_visibility
+(
+    guild_id TEXT NOT NULL,
+    command TEXT NOT NULL,
+    public BOOLEAN NOT NULL,
+    PRIMARY KEY (guild_id, command)
+);
@@ -0,0 +1,13 @@
+ALTER TABLE guilds ADD COLUMN cleanup_ttl_secs INTEGER;
+
+CREATE TABLE IF NOT EXISTS bot_messages
+(
+    id TEXT PRIMARY KEY,
+    channel_id TEXT NOT NULL,
+    guild_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    disabled BOOLEAN NOT NULL DEFAULT FALSE,
+    created DATETIME NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS bot_messages_created ON bot_messages (created);
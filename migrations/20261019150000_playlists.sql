@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS playlists
+(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    owner_id TEXT NOT NULL,
+    guild_id TEXT NOT NULL,
+    shared BOOLEAN NOT NULL DEFAULT FALSE,
+    updated DATETIME NOT NULL
+);
+
+-- personal playlists are unique per user, shared ones per guild
+CREATE UNIQUE INDEX IF NOT EXISTS playlists_personal_name ON playlists (owner_id, name) WHERE NOT shared;
+CREATE UNIQUE INDEX IF NOT EXISTS playlists_shared_name ON playlists (guild_id, name) WHERE shared;
+
+CREATE TABLE IF NOT EXISTS playlist_tracks
+(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    playlist_id INTEGER NOT NULL REFERENCES playlists (id) ON DELETE CASCADE,
+    track_id INTEGER NOT NULL REFERENCES tracks (id),
+    position INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS playlist_tracks_playlist ON playlist_tracks (playlist_id, position);
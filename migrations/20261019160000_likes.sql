@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS likes
+(
+    user_id TEXT NOT NULL REFERENCES users (id),
+    track_id INTEGER NOT NULL REFERENCES tracks (id),
+    created DATETIME NOT NULL,
+    PRIMARY KEY (user_id, track_id)
+);
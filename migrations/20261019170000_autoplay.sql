@@ -0,0 +1 @@
+ALTER TABLE guilds ADD COLUMN autoplay TEXT;
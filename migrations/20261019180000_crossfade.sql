@@ -0,0 +1 @@
+ALTER TABLE guilds ADD COLUMN crossfade_secs INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE tracks ADD COLUMN gain_db REAL;
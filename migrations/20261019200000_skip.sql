@@ -0,0 +1,9 @@
+ALTER TABLE guilds ADD COLUMN skip_silence BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE guilds ADD COLUMN skip_segments BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS segment_cache
+(
+    url TEXT PRIMARY KEY,
+    segments TEXT NOT NULL,
+    updated DATETIME NOT NULL
+);
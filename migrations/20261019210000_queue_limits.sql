@@ -0,0 +1,4 @@
+ALTER TABLE guilds ADD COLUMN max_tracks_per_user INTEGER;
+ALTER TABLE guilds ADD COLUMN max_playlist_size INTEGER;
+ALTER TABLE guilds ADD COLUMN max_track_duration_secs INTEGER;
+ALTER TABLE guilds ADD COLUMN fair_queue BOOLEAN NOT NULL DEFAULT FALSE;
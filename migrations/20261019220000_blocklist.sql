@@ -0,0 +1,25 @@
+CREATE TABLE IF NOT EXISTS blocklist
+(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id TEXT NOT NULL,
+    -- what the pattern is matched against: url, channel or keyword
+    kind TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    created_by TEXT NOT NULL,
+    created DATETIME NOT NULL,
+    UNIQUE (guild_id, kind, pattern)
+);
+
+CREATE TABLE IF NOT EXISTS blocked_attempts
+(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    url TEXT NOT NULL,
+    title TEXT NOT NULL,
+    created DATETIME NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS blocked_attempts_guild ON blocked_attempts (guild_id, kind, pattern);
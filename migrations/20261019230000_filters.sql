@@ -0,0 +1,4 @@
+ALTER TABLE guilds ADD COLUMN filter_equalizer TEXT NOT NULL DEFAULT 'flat';
+ALTER TABLE guilds ADD COLUMN filter_speed REAL NOT NULL DEFAULT 1.0;
+ALTER TABLE guilds ADD COLUMN filter_pitch REAL NOT NULL DEFAULT 1.0;
+ALTER TABLE guilds ADD COLUMN filter_normalize BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,255 @@
+use crate::commands::CommandError;
+use crate::db::{
+    self,
+    blocklist::{BlockRule, BlockedAttempt},
+    track::SavedTrack,
+};
+use crate::state::State;
+use crate::ytdlp::YouTubeTrack;
+use regex::{Regex, RegexBuilder};
+use std::error::Error;
+use twilight_model::id::{
+    marker::{GuildMarker, UserMarker},
+    Id,
+};
+
+/// Limit on the compiled size of patterns, so they cannot slow down adding tracks.
+const REGEX_SIZE_LIMIT: usize = 1 << 16;
+
+/// What a rule of the blocklist is matched against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BlockKind {
+    /// The URL of a track, matched by a regular expression.
+    Url,
+    /// The name of the channel that uploaded a track, ignoring case.
+    Channel,
+    /// The title of a track, matched by a regular expression.
+    Keyword,
+}
+
+impl BlockKind {
+    pub(crate) const ALL: [Self; 3] = [Self::Url, Self::Channel, Self::Keyword];
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Url => "url",
+            Self::Channel => "channel",
+            Self::Keyword => "keyword",
+        }
+    }
+
+    pub(crate) fn parse(kind: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == kind)
+    }
+
+    /// Compiles `pattern` into the expression that values of this kind are matched with.
+    pub(crate) fn compile(self, pattern: &str) -> Result<Regex, regex::Error> {
+        let pattern = match self {
+            Self::Url | Self::Keyword => pattern.to_string(),
+            Self::Channel => format!("^{}$", regex::escape(pattern.trim())),
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(true)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .build()
+    }
+}
+
+/// A track that rules of the blocklist are matched against.
+pub(crate) trait Blockable {
+    fn url(&self) -> Option<&str>;
+    fn channel(&self) -> &str;
+    fn title(&self) -> &str;
+}
+
+impl Blockable for YouTubeTrack {
+    fn url(&self) -> Option<&str> {
+        self.original_url.as_deref().or(self.url.as_deref())
+    }
+
+    fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+}
+
+impl Blockable for SavedTrack {
+    fn url(&self) -> Option<&str> {
+        Some(&self.url)
+    }
+
+    fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+}
+
+/// The rules that tracks are blocked by in a guild.
+pub(crate) struct Blocklist {
+    rules: Vec<(BlockKind, String, Regex)>,
+}
+
+impl Blocklist {
+    /// Compiles `rules`, skipping those that are no longer valid.
+    pub(crate) fn new(rules: &[BlockRule]) -> Self {
+        let rules = rules
+            .iter()
+            .filter_map(|rule| {
+                let kind = BlockKind::parse(&rule.kind)?;
+                match kind.compile(&rule.pattern) {
+                    Ok(regex) => Some((kind, rule.pattern.clone(), regex)),
+                    Err(e) => {
+                        tracing::warn!(?e, "invalid pattern on the blocklist: {}", rule.pattern);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// The first rule that blocks `track`, if any.
+    pub(crate) fn check(&self, track: &impl Blockable) -> Option<(BlockKind, &str)> {
+        self.check_parts(track.url(), Some(track.channel()), Some(track.title()))
+    }
+
+    /// The first rule that blocks a track with `url`, `channel` and `title`, if any.
+    /// Rules on parts that are unknown do not match.
+    pub(crate) fn check_parts(
+        &self,
+        url: Option<&str>,
+        channel: Option<&str>,
+        title: Option<&str>,
+    ) -> Option<(BlockKind, &str)> {
+        self.rules
+            .iter()
+            .find(|(kind, _, regex)| match kind {
+                BlockKind::Url => url.is_some_and(|url| regex.is_match(url)),
+                BlockKind::Channel => channel.is_some_and(|channel| regex.is_match(channel.trim())),
+                BlockKind::Keyword => title.is_some_and(|title| regex.is_match(title)),
+            })
+            .map(|(kind, pattern, _)| (*kind, pattern.as_str()))
+    }
+}
+
+/// Removes the `tracks` that are blocked in `guild_id` and logs that `user_id` tried to
+/// add them. Returns the other tracks and how many were blocked.
+///
+/// Fails if all of the tracks are blocked.
+pub(crate) async fn remove_blocked<T: Blockable>(
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    user_id: Id<UserMarker>,
+    tracks: Vec<T>,
+) -> Result<(Vec<T>, usize), Box<dyn Error + Send + Sync + 'static>> {
+    let rules = db::blocklist::block_rules(&state.pool, guild_id.to_string()).await?;
+    if rules.is_empty() {
+        return Ok((tracks, 0));
+    }
+    let blocklist = Blocklist::new(&rules);
+    let count = tracks.len();
+    let mut allowed = Vec::with_capacity(count);
+    for track in tracks {
+        let Some((kind, pattern)) = blocklist.check(&track) else {
+            allowed.push(track);
+            continue;
+        };
+        tracing::info!(
+            "blocked {} for {user_id} in {guild_id} by {} {pattern}",
+            track.title(),
+            kind.as_str()
+        );
+        let attempt = BlockedAttempt {
+            guild_id: guild_id.to_string(),
+            user_id: user_id.to_string(),
+            kind: kind.as_str().to_string(),
+            pattern: pattern.to_string(),
+            url: track.url().unwrap_or_default().to_string(),
+            title: track.title().to_string(),
+        };
+        if let Err(e) = db::blocklist::log_blocked_attempt(&state.pool, attempt).await {
+            tracing::warn!(?e, "could not log a blocked track");
+        }
+    }
+    if allowed.is_empty() && count > 0 {
+        return Err(CommandError::Blocked.into());
+    }
+    let blocked = count - allowed.len();
+    Ok((allowed, blocked))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ytdlp::tests::fake_ytdlp;
+
+    fn rule(kind: BlockKind, pattern: &str) -> BlockRule {
+        BlockRule {
+            kind: kind.as_str().to_string(),
+            pattern: pattern.to_string(),
+            blocked: 0,
+        }
+    }
+
+    #[test]
+    fn test_compile() {
+        assert!(BlockKind::Keyword.compile("night(core").is_err());
+        assert!(BlockKind::Channel.compile("night(core").is_ok());
+        assert!(BlockKind::Url.compile(&"a{1000}".repeat(100)).is_err());
+        assert_eq!(BlockKind::parse("channel"), Some(BlockKind::Channel));
+        assert_eq!(BlockKind::parse("user"), None);
+    }
+
+    #[tokio::test]
+    async fn test_check() {
+        let tracks = fake_ytdlp()
+            .get_tracks(
+                "https://www.youtube.com/playlist?list=PLFxxhcEeloYa1OlnWD6UgxlVQKJH5i_0p"
+                    .to_string(),
+            )
+            .await
+            .unwrap();
+        let blocked = |rules: &[BlockRule]| {
+            let blocklist = Blocklist::new(rules);
+            tracks
+                .iter()
+                .filter(|&track| blocklist.check(track).is_some())
+                .count()
+        };
+        assert_eq!(blocked(&[]), 0);
+        assert_eq!(blocked(&[rule(BlockKind::Keyword, r"\bcomputer")]), 1);
+        assert_eq!(blocked(&[rule(BlockKind::Url, "v=34CZjsEI1yU")]), 1);
+        assert_eq!(blocked(&[rule(BlockKind::Channel, " kraftwerk ")]), 3);
+        // channels only match as a whole
+        assert_eq!(blocked(&[rule(BlockKind::Channel, "kraft")]), 0);
+        // invalid rules are skipped
+        let rules = [
+            rule(BlockKind::Keyword, "auto(bahn"),
+            rule(BlockKind::Keyword, "autobahn|model"),
+        ];
+        assert_eq!(blocked(&rules), 2);
+        let blocklist = Blocklist::new(&rules);
+        assert_eq!(
+            blocklist.check(&tracks[0]),
+            Some((BlockKind::Keyword, "autobahn|model"))
+        );
+
+        let saved = SavedTrack {
+            url: "https://www.youtube.com/watch?v=34CZjsEI1yU".to_string(),
+            title: "Das Model".to_string(),
+            channel: "Kraftwerk".to_string(),
+            duration: "3:42".to_string(),
+        };
+        let blocklist = Blocklist::new(&[rule(BlockKind::Channel, "kraftwerk")]);
+        assert_eq!(
+            blocklist.check(&saved),
+            Some((BlockKind::Channel, "kraftwerk"))
+        );
+    }
+}
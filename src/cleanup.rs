@@ -0,0 +1,160 @@
+use crate::db;
+use crate::state::State;
+use chrono::{TimeDelta, Utc};
+use std::{error::Error, time::Duration};
+use twilight_model::{
+    channel::{
+        message::{
+            component::{ActionRow, Button},
+            Component, MessageFlags,
+        },
+        Message,
+    },
+    id::{
+        marker::{ChannelMarker, GuildMarker, MessageMarker},
+        Id,
+    },
+};
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Why the bot remembers one of its messages.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum MessageKind {
+    /// Deleted once it is older than the TTL of its guild.
+    Confirmation,
+    /// A queue with pagination buttons, which are disabled once it is stale.
+    Queue,
+}
+
+impl MessageKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Confirmation => "confirmation",
+            Self::Queue => "queue",
+        }
+    }
+}
+
+/// Remembers `message` so that it can be cleaned up later.
+///
+/// Ephemeral messages cannot be touched by the bot later on and are skipped.
+pub(crate) async fn remember(
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    message: &Message,
+    kind: MessageKind,
+) {
+    if message
+        .flags
+        .is_some_and(|flags| flags.contains(MessageFlags::EPHEMERAL))
+    {
+        return;
+    }
+    let message = db::message::BotMessage::new(
+        message.id.to_string(),
+        message.channel_id.to_string(),
+        guild_id.to_string(),
+        kind.as_str().to_string(),
+    );
+    if let Err(e) = db::message::insert_bot_message(&state.pool, message).await {
+        tracing::warn!(?e, "could not remember message");
+    }
+}
+
+/// Deletes expired messages and disables stale pagination buttons, forever.
+pub(crate) async fn run(state: State) {
+    let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = sweep(&state).await {
+            tracing::warn!(?e, "could not clean up messages");
+        }
+    }
+}
+
+async fn sweep(state: &State) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let now = Utc::now();
+    for message in db::message::expired_bot_messages(&state.pool, now).await? {
+        if let (Ok(channel_id), Ok(message_id)) = (message.channel_id.parse(), message.id.parse()) {
+            if let Err(e) = state.http.delete_message(channel_id, message_id).await {
+                tracing::debug!(?e, "could not delete message {message_id}");
+            }
+        }
+        db::message::delete_bot_message(&state.pool, message.id).await?;
+    }
+
+    let timeout = TimeDelta::from_std(state.config.queue.pagination_timeout())?;
+    let kind = MessageKind::Queue.as_str().to_string();
+    for message in db::message::stale_bot_messages(&state.pool, kind.clone(), now - timeout).await?
+    {
+        if let (Ok(channel_id), Ok(message_id)) = (message.channel_id.parse(), message.id.parse()) {
+            if let Err(e) = disable_buttons(state, channel_id, message_id).await {
+                tracing::debug!(?e, "could not disable buttons of message {message_id}");
+            }
+        }
+        db::message::disable_bot_message(&state.pool, message.id).await?;
+    }
+
+    db::message::forget_bot_messages(&state.pool, kind).await?;
+    Ok(())
+}
+
+async fn disable_buttons(
+    state: &State,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let message = state
+        .http
+        .message(channel_id, message_id)
+        .await?
+        .model()
+        .await?;
+    let components = disable_components(message.components);
+    state
+        .http
+        .update_message(channel_id, message_id)
+        .components(Some(&components))
+        .await?;
+    Ok(())
+}
+
+/// Returns `components` with all buttons disabled.
+pub(crate) fn disable_components(components: Vec<Component>) -> Vec<Component> {
+    components
+        .into_iter()
+        .map(|component| match component {
+            Component::ActionRow(row) => Component::ActionRow(ActionRow {
+                components: disable_components(row.components),
+            }),
+            Component::Button(button) => Component::Button(Button {
+                disabled: true,
+                ..button
+            }),
+            component => component,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::queue::build_action_row;
+    use crate::i18n::Locale;
+
+    #[test]
+    fn test_disable_components() {
+        let components = disable_components(build_action_row(1, 3, Locale::En));
+        let Component::ActionRow(row) = &components[0] else {
+            panic!("expected an action row");
+        };
+        assert_eq!(row.components.len(), 4);
+        for component in &row.components {
+            assert!(matches!(
+                component,
+                Component::Button(Button { disabled: true, .. })
+            ));
+        }
+    }
+}
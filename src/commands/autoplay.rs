@@ -0,0 +1,465 @@
+use crate::blocklist::Blocklist;
+use crate::commands::framework::{
+    announce, CommandResult, FromOptions, OptionError, Options, SlashCommand,
+};
+use crate::commands::play::enqueue;
+use crate::commands::CommandError;
+use crate::db;
+use crate::i18n::Locale;
+use crate::metadata::Metadata;
+use crate::resolve::ResolveError;
+use crate::section::Section;
+use crate::state::{State, StateRef};
+use crate::utils::{parse_duration, spawn};
+use async_trait::async_trait;
+use chrono::Utc;
+use songbird::tracks::{PlayMode, TrackHandle};
+use songbird::{Call, Event, EventContext, EventHandler, TrackEvent};
+use std::{error::Error, sync::Arc, time::Duration};
+use twilight_model::{
+    application::command::{Command, CommandType},
+    gateway::payload::incoming::InteractionCreate,
+    id::{marker::GuildMarker, Id},
+};
+use twilight_util::builder::command::{CommandBuilder, StringBuilder};
+use url::Url;
+use uuid::Uuid;
+
+/// Autoplayed tracks that are not picked again.
+const RECENT_AUTOPLAYS: usize = 50;
+
+/// Requests in the guild whose tracks are not picked from its history.
+const RECENT_REQUESTS: i64 = 10;
+
+/// Most popular tracks of a guild that autoplay picks from.
+const HISTORY_CANDIDATES: i64 = 50;
+
+/// Where autoplay takes follow-up tracks from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Mode {
+    /// Tracks requested in the guild before, popular and recent ones more likely.
+    History,
+    /// The YouTube mix of the track that ended.
+    Related,
+}
+
+impl Mode {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::History => "history",
+            Self::Related => "related",
+        }
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "history" => Some(Self::History),
+            "related" => Some(Self::Related),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) struct Autoplay;
+
+pub(crate) struct AutoplayOptions {
+    /// `None` to turn autoplay off.
+    mode: Option<Mode>,
+}
+
+impl FromOptions for AutoplayOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        let mode = match options.string("mode")?.as_str() {
+            "off" => None,
+            mode => Some(Mode::from_str(mode).ok_or(OptionError::InvalidType("mode".to_string()))?),
+        };
+        Ok(Self { mode })
+    }
+}
+
+#[async_trait]
+impl SlashCommand for Autoplay {
+    type Options = AutoplayOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "autoplay",
+            "Keep playing when the queue runs out",
+            CommandType::ChatInput,
+        )
+        .option(
+            StringBuilder::new("mode", "Where the next tracks come from")
+                .required(true)
+                .choices([
+                    ("Tracks played in this server", "history"),
+                    ("Tracks related to the last one", "related"),
+                    ("Off", "off"),
+                ]),
+        )
+        .build()
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: AutoplayOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "autoplay command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        db::guild::set_guild_autoplay(
+            &state.pool,
+            db::track::Guild::new(guild_id.to_string()),
+            options.mode.map(|mode| mode.as_str().to_string()),
+        )
+        .await?;
+
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        let message = match options.mode {
+            Some(Mode::History) => locale.text("autoplay.history"),
+            Some(Mode::Related) => locale.text("autoplay.related"),
+            None => locale.text("autoplay.off"),
+        };
+        announce(&interaction, &state, message).await
+    }
+}
+
+/// Lets autoplay continue the queue of `call` when it runs out.
+pub(crate) fn register(call: &mut Call, guild_id: Id<GuildMarker>, state: State) {
+    call.add_global_event(
+        Event::Track(TrackEvent::End),
+        QueueEndNotifier { guild_id, state },
+    );
+}
+
+struct QueueEndNotifier {
+    guild_id: Id<GuildMarker>,
+    state: Arc<StateRef>,
+}
+
+#[async_trait]
+impl EventHandler for QueueEndNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(track_list) = ctx else {
+            return None;
+        };
+        let (track_state, track_handle) = track_list.first()?;
+        // stopped and skipped tracks end the queue on purpose
+        if !matches!(track_state.playing, PlayMode::End) {
+            return None;
+        }
+        spawn(continue_queue(
+            Arc::clone(&self.state),
+            self.guild_id,
+            (*track_handle).clone(),
+        ));
+        None
+    }
+}
+
+/// A track that autoplay may pick.
+#[derive(Debug, PartialEq)]
+struct Candidate {
+    url: String,
+    title: Option<String>,
+    channel: Option<String>,
+    duration: Option<Duration>,
+}
+
+impl Candidate {
+    /// Whether a rule of the blocklist of the guild blocks the track.
+    fn is_blocked(&self, blocklist: &Blocklist) -> bool {
+        blocklist
+            .check_parts(
+                Some(&self.url),
+                self.channel.as_deref(),
+                self.title.as_deref(),
+            )
+            .is_some()
+    }
+}
+
+/// Enqueues a follow-up track if `ended` was the last one in the queue of `guild_id`.
+async fn continue_queue(
+    state: State,
+    guild_id: Id<GuildMarker>,
+    ended: TrackHandle,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    if state
+        .guild_settings
+        .get(&guild_id)
+        .is_some_and(|settings| settings.loop_queue)
+    {
+        return Ok(());
+    }
+    let Some(mode) = db::guild::guild_autoplay(&state.pool, guild_id.to_string())
+        .await?
+        .as_deref()
+        .and_then(Mode::from_str)
+    else {
+        return Ok(());
+    };
+    let Some(call_lock) = state.songbird.get(guild_id) else {
+        return Ok(());
+    };
+    // the queue may not have removed the ended track yet
+    if call_lock
+        .lock()
+        .await
+        .queue()
+        .current_queue()
+        .iter()
+        .any(|track| track.uuid() != ended.uuid())
+    {
+        return Ok(());
+    }
+
+    let ended = ended.data::<Metadata>();
+    let mut recent: Vec<String> = state
+        .guild_settings
+        .get(&guild_id)
+        .map(|settings| settings.autoplayed.iter().cloned().collect())
+        .unwrap_or_default();
+    recent.push(ended.url.clone());
+    // nobody tried to add autoplayed tracks, so blocked ones are not logged
+    let rules = db::blocklist::block_rules(&state.pool, guild_id.to_string()).await?;
+    let blocklist = Blocklist::new(&rules);
+
+    let next = match mode {
+        Mode::History => match from_history(&state, guild_id, &recent, &blocklist).await? {
+            Some(next) => Some(next),
+            None => related(&state, &ended.url, &recent, &blocklist)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::debug!(?e, "could not find related tracks");
+                    None
+                }),
+        },
+        Mode::Related => match related(&state, &ended.url, &recent, &blocklist).await {
+            Ok(Some(next)) => Some(next),
+            Ok(None) => from_history(&state, guild_id, &recent, &blocklist).await?,
+            Err(e) => {
+                tracing::debug!(?e, "could not find related tracks");
+                from_history(&state, guild_id, &recent, &blocklist).await?
+            }
+        },
+    };
+    let Some(next) = next else {
+        tracing::debug!("nothing to autoplay in guild {guild_id}");
+        return Ok(());
+    };
+    tracing::debug!("autoplaying {} in guild {guild_id}", next.url);
+
+    if let Some(mut settings) = state.guild_settings.get_mut(&guild_id) {
+        settings.autoplayed.push_back(next.url.clone());
+        if settings.autoplayed.len() > RECENT_AUTOPLAYS {
+            settings.autoplayed.pop_front();
+        }
+    }
+    let metadata = Metadata {
+        title: next.title,
+        channel: next.channel,
+        duration: next.duration,
+        src: state.ytdlp.source(state.client.clone(), next.url.clone()),
+        url: next.url,
+        autoplay: true,
+        requester: None,
+        gain: 1.0,
+        section: Section::default(),
+        chapters: Vec::new(),
+    };
+    enqueue(&state, guild_id, metadata).await;
+    Ok(())
+}
+
+/// Picks a track from the history of `guild_id` that is not blocked, weighted by its
+/// popularity.
+async fn from_history(
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    recent: &[String],
+    blocklist: &Blocklist,
+) -> Result<Option<Candidate>, sqlx::Error> {
+    let tracks = db::track::popular_tracks(
+        &state.pool,
+        guild_id.to_string(),
+        Utc::now(),
+        RECENT_REQUESTS,
+        HISTORY_CANDIDATES,
+    )
+    .await?;
+    let candidates = tracks
+        .into_iter()
+        .filter(|track| !recent.contains(&track.url))
+        .map(|track| {
+            let candidate = Candidate {
+                duration: parse_duration(&track.duration),
+                title: Some(track.title).filter(|title| !title.is_empty()),
+                channel: Some(track.channel).filter(|channel| !channel.is_empty()),
+                url: track.url,
+            };
+            (candidate, track.score)
+        })
+        .filter(|(candidate, _)| !candidate.is_blocked(blocklist))
+        .collect();
+    Ok(pick_weighted(candidates, random()))
+}
+
+/// Picks the first track of the YouTube mix of `url` that was not played recently and
+/// is not blocked.
+async fn related(
+    state: &State,
+    url: &str,
+    recent: &[String],
+    blocklist: &Blocklist,
+) -> Result<Option<Candidate>, ResolveError> {
+    let Some(mix) = mix_url(url) else {
+        return Ok(None);
+    };
+    let recent: Vec<_> = recent.iter().map(|url| track_key(url)).collect();
+    let tracks = state.ytdlp.get_tracks(mix).await?;
+    Ok(tracks.into_iter().find_map(|track| {
+        let url = track.original_url.or(track.url)?;
+        if recent.contains(&track_key(&url)) {
+            return None;
+        }
+        let candidate = Candidate {
+            url,
+            title: Some(track.title),
+            channel: Some(track.channel),
+            duration: parse_duration(&track.duration_string),
+        };
+        (!candidate.is_blocked(blocklist)).then_some(candidate)
+    }))
+}
+
+/// Picks one of `candidates` with a chance proportional to its weight.
+///
+/// `r` is a random number in `[0, 1)`.
+fn pick_weighted<T>(candidates: Vec<(T, f64)>, r: f64) -> Option<T> {
+    let total: f64 = candidates.iter().map(|(_, weight)| weight).sum();
+    let mut target = r * total;
+    let mut last = None;
+    for (candidate, weight) in candidates {
+        if target < weight {
+            return Some(candidate);
+        }
+        target -= weight;
+        last = Some(candidate);
+    }
+    // rounding errors can leave a bit of the target
+    last
+}
+
+/// A random number in `[0, 1)`.
+fn random() -> f64 {
+    // the lowest 53 bits of a v4 uuid are random, and fit into the mantissa
+    let bits = Uuid::new_v4().as_u128() & ((1 << 53) - 1);
+    bits as f64 / (1u64 << 53) as f64
+}
+
+/// Id of the YouTube video at `url`.
+fn video_id(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?.trim_start_matches("www.");
+    match host {
+        "youtu.be" => url.path_segments()?.next().map(str::to_string),
+        "youtube.com" | "music.youtube.com" | "m.youtube.com" => {
+            let mut segments = url.path_segments()?;
+            match segments.next()? {
+                "watch" => url
+                    .query_pairs()
+                    .find(|(key, _)| key == "v")
+                    .map(|(_, id)| id.into_owned()),
+                "shorts" => segments.next().map(str::to_string),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+    .filter(|id| !id.is_empty())
+}
+
+/// URL of the mix YouTube generates for the video at `url`.
+fn mix_url(url: &str) -> Option<String> {
+    let id = video_id(url)?;
+    Some(format!("https://www.youtube.com/watch?v={id}&list=RD{id}"))
+}
+
+/// Identifies a track across the different URLs of a YouTube video.
+fn track_key(url: &str) -> String {
+    video_id(url).unwrap_or_else(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_weighted() {
+        let candidates = || vec![("a", 1.0), ("b", 3.0)];
+        assert_eq!(pick_weighted(candidates(), 0.0), Some("a"));
+        assert_eq!(pick_weighted(candidates(), 0.24), Some("a"));
+        assert_eq!(pick_weighted(candidates(), 0.26), Some("b"));
+        assert_eq!(pick_weighted(candidates(), 0.999), Some("b"));
+        assert_eq!(pick_weighted(Vec::<(&str, f64)>::new(), 0.5), None);
+        let r = random();
+        assert!((0.0..1.0).contains(&r));
+    }
+
+    #[test]
+    fn test_mix_url() {
+        for url in [
+            "https://www.youtube.com/watch?v=34CZjsEI1yU",
+            "https://music.youtube.com/watch?v=34CZjsEI1yU&list=PL123",
+            "https://youtu.be/34CZjsEI1yU?t=42",
+            "https://www.youtube.com/shorts/34CZjsEI1yU",
+        ] {
+            assert_eq!(
+                mix_url(url).as_deref(),
+                Some("https://www.youtube.com/watch?v=34CZjsEI1yU&list=RD34CZjsEI1yU"),
+                "{url}"
+            );
+        }
+        assert_eq!(mix_url("https://example.com/watch?v=34CZjsEI1yU"), None);
+        assert_eq!(mix_url("https://www.youtube.com/playlist?list=PL123"), None);
+        assert_eq!(track_key("https://example.com/a"), "https://example.com/a");
+    }
+
+    #[test]
+    fn test_is_blocked() {
+        let rule = |kind: &str, pattern: &str| db::blocklist::BlockRule {
+            kind: kind.to_string(),
+            pattern: pattern.to_string(),
+            blocked: 0,
+        };
+        let blocklist =
+            Blocklist::new(&[rule("channel", "Kraftwerk"), rule("keyword", "nightcore")]);
+        let candidate = |title: Option<&str>, channel: Option<&str>| Candidate {
+            url: "https://www.youtube.com/watch?v=34CZjsEI1yU".to_string(),
+            title: title.map(str::to_string),
+            channel: channel.map(str::to_string),
+            duration: None,
+        };
+        assert!(candidate(Some("The Model"), Some("Kraftwerk")).is_blocked(&blocklist));
+        assert!(candidate(Some("The Model (Nightcore)"), None).is_blocked(&blocklist));
+        assert!(!candidate(Some("The Model"), None).is_blocked(&blocklist));
+        assert!(!candidate(None, Some("Daft Punk")).is_blocked(&blocklist));
+    }
+
+    #[test]
+    fn test_mode() {
+        for mode in [Mode::History, Mode::Related] {
+            assert_eq!(Mode::from_str(mode.as_str()), Some(mode));
+        }
+        assert_eq!(Mode::from_str("off"), None);
+    }
+}
@@ -0,0 +1,258 @@
+use crate::blocklist::BlockKind;
+use crate::commands::framework::{
+    confirm, CommandResult, FromOptions, OptionError, Options, SlashCommand,
+};
+use crate::commands::CommandError;
+use crate::db::{self, blocklist::BlockRule};
+use crate::i18n::Locale;
+use crate::state::State;
+use async_trait::async_trait;
+use twilight_model::{
+    application::command::{Command, CommandType},
+    gateway::payload::incoming::InteractionCreate,
+    guild::Permissions,
+};
+use twilight_util::builder::command::{CommandBuilder, StringBuilder, SubCommandBuilder};
+
+/// Longest pattern that can be added to the blocklist.
+const MAX_PATTERN_LENGTH: u16 = 200;
+
+/// Rules that are listed at most.
+const SHOWN_RULES: usize = 30;
+
+pub(crate) struct Blocklist;
+
+pub(crate) enum BlocklistOptions {
+    Add { kind: BlockKind, pattern: String },
+    Remove { kind: BlockKind, pattern: String },
+    List,
+}
+
+/// Reads the kind and the pattern of a rule, which must compile if it is `added`.
+fn rule(options: &Options<'_>, added: bool) -> Result<(BlockKind, String), OptionError> {
+    let kind = BlockKind::parse(&options.string("type")?)
+        .ok_or(OptionError::InvalidType("type".to_string()))?;
+    let pattern = options.string("pattern")?.trim().to_string();
+    if pattern.is_empty()
+        || pattern.chars().count() > MAX_PATTERN_LENGTH as usize
+        || (added && kind.compile(&pattern).is_err())
+    {
+        return Err(OptionError::InvalidType("pattern".to_string()));
+    }
+    Ok((kind, pattern))
+}
+
+impl FromOptions for BlocklistOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        match options.subcommand()? {
+            ("add", options) => {
+                let (kind, pattern) = rule(&options, true)?;
+                Ok(Self::Add { kind, pattern })
+            }
+            ("remove", options) => {
+                // rules that no longer compile can still be removed
+                let (kind, pattern) = rule(&options, false)?;
+                Ok(Self::Remove { kind, pattern })
+            }
+            ("list", _) => Ok(Self::List),
+            (name, _) => Err(OptionError::InvalidType(name.to_string())),
+        }
+    }
+}
+
+fn describe_kind(kind: BlockKind, locale: Locale) -> &'static str {
+    match kind {
+        BlockKind::Url => locale.text("blocklist.url"),
+        BlockKind::Channel => locale.text("blocklist.channel"),
+        BlockKind::Keyword => locale.text("blocklist.keyword"),
+    }
+}
+
+/// Lists the `rules` of a guild with how often they blocked a track.
+fn list_rules(rules: &[BlockRule], locale: Locale) -> String {
+    if rules.is_empty() {
+        return locale.text("blocklist.empty").to_string();
+    }
+    let mut message = locale.text("blocklist.list").to_string();
+    for rule in rules.iter().take(SHOWN_RULES) {
+        let kind = BlockKind::parse(&rule.kind)
+            .map_or(rule.kind.as_str(), |kind| describe_kind(kind, locale));
+        let line = locale.format(
+            "blocklist.rule",
+            &[
+                ("kind", &kind),
+                ("pattern", &rule.pattern),
+                ("count", &rule.blocked),
+            ],
+        );
+        message.push_str(&format!("\n- {line}"));
+    }
+    if rules.len() > SHOWN_RULES {
+        let more = rules.len() - SHOWN_RULES;
+        message.push_str(&format!(
+            "\n{}",
+            locale.format("blocklist.more", &[("count", &more)])
+        ));
+    }
+    message
+}
+
+/// Option of a subcommand for the kind of a rule.
+fn kind_option() -> StringBuilder {
+    StringBuilder::new("type", "What the pattern is matched against")
+        .required(true)
+        .choices([
+            ("URL", BlockKind::Url.as_str()),
+            ("Channel", BlockKind::Channel.as_str()),
+            ("Title", BlockKind::Keyword.as_str()),
+        ])
+}
+
+#[async_trait]
+impl SlashCommand for Blocklist {
+    type Options = BlocklistOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "blocklist",
+            "Block tracks by their URL, channel or title",
+            CommandType::ChatInput,
+        )
+        .option(
+            SubCommandBuilder::new("add", "Block tracks that match a pattern")
+                .option(kind_option())
+                .option(
+                    StringBuilder::new(
+                        "pattern",
+                        "Regular expression for URLs and titles, name of a channel",
+                    )
+                    .required(true)
+                    .max_length(MAX_PATTERN_LENGTH),
+                ),
+        )
+        .option(
+            SubCommandBuilder::new("remove", "Stop blocking tracks that match a pattern")
+                .option(kind_option())
+                .option(
+                    StringBuilder::new("pattern", "Pattern to remove")
+                        .required(true)
+                        .max_length(MAX_PATTERN_LENGTH),
+                ),
+        )
+        .option(SubCommandBuilder::new(
+            "list",
+            "List what is blocked in this server",
+        ))
+        .build()
+    }
+
+    fn required_permissions(&self) -> Option<Permissions> {
+        Some(Permissions::MANAGE_GUILD)
+    }
+
+    fn public_by_default(&self) -> bool {
+        false
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: BlocklistOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "blocklist command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let (Some(user_id), Some(guild_id)) = (interaction.author_id(), interaction.guild_id)
+        else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        let locale = Locale::for_interaction(&interaction, &state).await;
+
+        let message = match options {
+            BlocklistOptions::Add { kind, pattern } => {
+                let added = db::blocklist::add_block_rule(
+                    &state.pool,
+                    guild_id.to_string(),
+                    kind.as_str().to_string(),
+                    pattern.clone(),
+                    user_id.to_string(),
+                )
+                .await?;
+                let key = if added {
+                    "blocklist.added"
+                } else {
+                    "blocklist.exists"
+                };
+                locale.format(
+                    key,
+                    &[
+                        ("kind", &describe_kind(kind, locale)),
+                        ("pattern", &pattern),
+                    ],
+                )
+            }
+            BlocklistOptions::Remove { kind, pattern } => {
+                let removed = db::blocklist::remove_block_rule(
+                    &state.pool,
+                    guild_id.to_string(),
+                    kind.as_str().to_string(),
+                    pattern.clone(),
+                )
+                .await?;
+                let key = if removed {
+                    "blocklist.removed"
+                } else {
+                    "blocklist.not_found"
+                };
+                locale.format(
+                    key,
+                    &[
+                        ("kind", &describe_kind(kind, locale)),
+                        ("pattern", &pattern),
+                    ],
+                )
+            }
+            BlocklistOptions::List => {
+                let rules = db::blocklist::block_rules(&state.pool, guild_id.to_string()).await?;
+                list_rules(&rules, locale)
+            }
+        };
+        confirm(&interaction, &state, message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_rules() {
+        assert_eq!(
+            list_rules(&[], Locale::En),
+            "Nothing is blocked in this server."
+        );
+        let rules = [
+            BlockRule {
+                kind: "keyword".to_string(),
+                pattern: "nightcore".to_string(),
+                blocked: 3,
+            },
+            BlockRule {
+                kind: "channel".to_string(),
+                pattern: "Kraftwerk".to_string(),
+                blocked: 0,
+            },
+        ];
+        assert_eq!(
+            list_rules(&rules, Locale::En),
+            "Blocked in this server:\n\
+             - title matching `nightcore`, blocked 3 times\n\
+             - channel matching `Kraftwerk`, blocked 0 times"
+        );
+    }
+}
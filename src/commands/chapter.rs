@@ -0,0 +1,272 @@
+use crate::commands::framework::{
+    announce, CommandResult, FromOptions, OptionError, Options, SlashCommand,
+};
+use crate::commands::CommandError;
+use crate::filter::Filters;
+use crate::i18n::Locale;
+use crate::metadata::Metadata;
+use crate::section::Section;
+use crate::state::State;
+use crate::utils::format_duration;
+use crate::ytdlp::Chapter;
+use async_trait::async_trait;
+use songbird::tracks::TrackHandle;
+use std::time::Duration;
+use twilight_model::{
+    application::command::{Command, CommandType},
+    gateway::payload::incoming::InteractionCreate,
+};
+use twilight_util::builder::command::{CommandBuilder, IntegerBuilder, SubCommandBuilder};
+
+/// Chapters that are listed at most.
+const SHOWN_CHAPTERS: usize = 30;
+
+pub(crate) struct Chapters;
+
+pub(crate) enum ChapterOptions {
+    Next,
+    Previous,
+    List,
+    /// 1-based number of the chapter.
+    Goto(i64),
+}
+
+impl FromOptions for ChapterOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        match options.subcommand()? {
+            ("next", _) => Ok(Self::Next),
+            ("prev", _) => Ok(Self::Previous),
+            ("list", _) => Ok(Self::List),
+            ("goto", options) => {
+                let number = options.integer("number")?;
+                if number < 1 {
+                    return Err(OptionError::InvalidType("number".to_string()));
+                }
+                Ok(Self::Goto(number))
+            }
+            (name, _) => Err(OptionError::InvalidType(name.to_string())),
+        }
+    }
+}
+
+/// Time in the track that plays at `position`.
+pub(crate) fn track_time(position: Duration, filters: Filters, section: Section) -> Duration {
+    section.start + Filters::new().convert_position(filters, position)
+}
+
+/// Position at which `time` in the track plays.
+fn track_position(time: Duration, filters: Filters, section: Section) -> Duration {
+    filters.convert_position(Filters::new(), time.saturating_sub(section.start))
+}
+
+/// Index of the chapter that plays at `time`.
+fn chapter_at(chapters: &[Chapter], time: Duration) -> Option<usize> {
+    let secs = time.as_secs_f64();
+    chapters
+        .iter()
+        .rposition(|chapter| chapter.start_time <= secs)
+}
+
+/// Index of the chapter that plays on `handle`, `None` for tracks without chapters.
+pub(crate) async fn current_chapter(handle: &TrackHandle, filters: Filters) -> Option<usize> {
+    let metadata = handle.data::<Metadata>();
+    if metadata.chapters.is_empty() {
+        return None;
+    }
+    let position = handle.get_info().await.ok()?.position;
+    chapter_at(
+        &metadata.chapters,
+        track_time(position, filters, metadata.section),
+    )
+}
+
+/// Describes the chapter at `index` of `chapters`.
+pub(crate) fn describe_chapter(chapters: &[Chapter], index: usize, locale: Locale) -> String {
+    locale.format(
+        "chapter.chapter",
+        &[
+            ("number", &(index + 1)),
+            ("count", &chapters.len()),
+            ("title", &chapters[index].title),
+        ],
+    )
+}
+
+/// Lists `chapters`, marking the one at `current`.
+fn list_chapters(chapters: &[Chapter], current: Option<usize>, locale: Locale) -> String {
+    let mut message = locale.text("chapter.list").to_string();
+    message.push('\n');
+    for (index, chapter) in chapters.iter().enumerate().take(SHOWN_CHAPTERS) {
+        let start = format_duration(Duration::from_secs_f64(chapter.start_time));
+        let line = format!("{}. `{start}` {}", index + 1, chapter.title);
+        if Some(index) == current {
+            message.push_str(&format!("**{line}** ▶️\n"));
+        } else {
+            message.push_str(&format!("{line}\n"));
+        }
+    }
+    if chapters.len() > SHOWN_CHAPTERS {
+        let more = chapters.len() - SHOWN_CHAPTERS;
+        message.push_str(&locale.format("chapter.more", &[("count", &more)]));
+    }
+    message
+}
+
+#[async_trait]
+impl SlashCommand for Chapters {
+    type Options = ChapterOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "chapter",
+            "Jump between the chapters of the current track",
+            CommandType::ChatInput,
+        )
+        .option(SubCommandBuilder::new("next", "Play the next chapter"))
+        .option(SubCommandBuilder::new("prev", "Play the previous chapter"))
+        .option(SubCommandBuilder::new(
+            "list",
+            "List the chapters of the current track",
+        ))
+        .option(
+            SubCommandBuilder::new("goto", "Play a chapter").option(
+                IntegerBuilder::new("number", "Number of the chapter")
+                    .required(true)
+                    .min_value(1),
+            ),
+        )
+        .build()
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: ChapterOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "chapter command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        let call_lock = state
+            .songbird
+            .get(guild_id)
+            .ok_or(CommandError::NothingPlaying)?;
+        let handle = call_lock
+            .lock()
+            .await
+            .queue()
+            .current()
+            .ok_or(CommandError::NothingPlaying)?;
+
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        let metadata = handle.data::<Metadata>();
+        if metadata.chapters.is_empty() {
+            return Err(CommandError::NoChapters.into());
+        }
+        let filters = state
+            .guild_settings
+            .get(&guild_id)
+            .map(|settings| settings.filters)
+            .unwrap_or_default();
+        let current = current_chapter(&handle, filters).await;
+        let chapters = &metadata.chapters;
+
+        let target = match options {
+            ChapterOptions::List => {
+                let message = list_chapters(chapters, current, locale);
+                return announce(&interaction, &state, message).await;
+            }
+            ChapterOptions::Next => match current {
+                Some(index) if index + 1 >= chapters.len() => {
+                    return Err(CommandError::LastChapter.into());
+                }
+                Some(index) => index + 1,
+                None => 0,
+            },
+            // the first chapter starts over
+            ChapterOptions::Previous => current.unwrap_or_default().saturating_sub(1),
+            ChapterOptions::Goto(number) => {
+                let index = number as usize - 1;
+                if index >= chapters.len() {
+                    return Err(CommandError::UnknownChapter {
+                        number,
+                        count: chapters.len(),
+                    }
+                    .into());
+                }
+                index
+            }
+        };
+
+        let start = Duration::from_secs_f64(chapters[target].start_time);
+        handle
+            .seek_async(track_position(start, filters, metadata.section))
+            .await?;
+        let message = locale.format(
+            "chapter.playing",
+            &[("chapter", &describe_chapter(chapters, target, locale))],
+        );
+        announce(&interaction, &state, message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapters() -> Vec<Chapter> {
+        [(0.0, 372.0, "The Robots"), (372.0, 800.0, "Computer Love")]
+            .into_iter()
+            .map(|(start_time, end_time, title)| Chapter {
+                start_time,
+                end_time,
+                title: title.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chapter_at() {
+        let chapters = chapters();
+        assert_eq!(chapter_at(&chapters, Duration::ZERO), Some(0));
+        assert_eq!(chapter_at(&chapters, Duration::from_secs(372)), Some(1));
+        assert_eq!(chapter_at(&chapters, Duration::from_secs(900)), Some(1));
+        assert_eq!(chapter_at(&[], Duration::ZERO), None);
+    }
+
+    #[test]
+    fn test_track_time() {
+        let section = Section {
+            start: Duration::from_secs(60),
+            end: None,
+        };
+        let position = Duration::from_secs(80);
+        let time = track_time(position, Filters::nightcore(), section);
+        assert_eq!(time, Duration::from_secs(160));
+        assert_eq!(
+            track_position(time, Filters::nightcore(), section),
+            position
+        );
+    }
+
+    #[test]
+    fn test_list_chapters() {
+        assert_eq!(
+            list_chapters(&chapters(), Some(1), Locale::En),
+            "Chapters:\n\
+             1. `00:00` The Robots\n\
+             **2. `06:12` Computer Love** ▶️\n"
+        );
+        assert_eq!(
+            describe_chapter(&chapters(), 1, Locale::En),
+            "chapter 2/2, Computer Love"
+        );
+    }
+}
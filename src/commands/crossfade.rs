@@ -0,0 +1,350 @@
+use crate::commands::framework::{
+    announce, CommandResult, FromOptions, OptionError, Options, SlashCommand,
+};
+use crate::commands::volume;
+use crate::commands::CommandError;
+use crate::db;
+use crate::i18n::Locale;
+use crate::metadata::Metadata;
+use crate::state::State;
+use crate::utils::spawn;
+use async_trait::async_trait;
+use songbird::tracks::{PlayMode, TrackHandle};
+use songbird::{Event, EventContext, EventHandler};
+use std::{error::Error, f32::consts::FRAC_PI_2, time::Duration};
+use tokio::time::{self, Instant};
+use twilight_model::{
+    application::command::{Command, CommandType},
+    gateway::payload::incoming::InteractionCreate,
+    id::{marker::GuildMarker, Id},
+};
+use twilight_util::builder::command::{CommandBuilder, IntegerBuilder};
+
+/// Longest crossfade that can be set, in seconds.
+const MAX_CROSSFADE_SECS: i64 = 12;
+
+/// How long before a crossfade the next track is prepared, so it can start right away.
+const PREPARE_AHEAD: Duration = Duration::from_secs(5);
+
+/// How often the volumes change during a crossfade.
+const FADE_STEP: Duration = Duration::from_millis(50);
+
+pub(crate) struct Crossfade;
+
+pub(crate) struct CrossfadeOptions {
+    seconds: i64,
+}
+
+impl FromOptions for CrossfadeOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        Ok(Self {
+            seconds: options.integer("seconds")?.clamp(0, MAX_CROSSFADE_SECS),
+        })
+    }
+}
+
+#[async_trait]
+impl SlashCommand for Crossfade {
+    type Options = CrossfadeOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "crossfade",
+            "Blend the end of a track into the next one",
+            CommandType::ChatInput,
+        )
+        .option(
+            IntegerBuilder::new(
+                "seconds",
+                "How long tracks overlap, 0 to play them without gaps",
+            )
+            .required(true)
+            .min_value(0)
+            .max_value(MAX_CROSSFADE_SECS),
+        )
+        .build()
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: CrossfadeOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "crossfade command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        db::guild::set_guild_crossfade(
+            &state.pool,
+            db::track::Guild::new(guild_id.to_string()),
+            options.seconds,
+        )
+        .await?;
+
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        let message = if options.seconds == 0 {
+            locale.text("crossfade.off").to_string()
+        } else {
+            locale.format("crossfade.set", &[("seconds", &options.seconds)])
+        };
+        announce(&interaction, &state, message).await
+    }
+}
+
+/// Prepares the transition from the track of `handle` to the one after it.
+///
+/// `duration` is how long the track plays, which the speed filter changes. Nothing is
+/// scheduled for tracks of unknown length.
+pub(crate) fn schedule(
+    handle: &TrackHandle,
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    duration: Option<Duration>,
+) {
+    let Some(duration) = duration else {
+        return;
+    };
+    let speed = state
+        .guild_settings
+        .get(&guild_id)
+        .map_or(1.0, |settings| settings.filters.speed);
+    let duration = duration.div_f64(speed);
+    let lead = Duration::from_secs(MAX_CROSSFADE_SECS as u64) + PREPARE_AHEAD;
+    let result = handle.add_event(
+        Event::Delayed(duration.saturating_sub(lead)),
+        TailNotifier {
+            state: State::clone(state),
+            guild_id,
+            duration,
+        },
+    );
+    if let Err(e) = result {
+        tracing::debug!(?e, "could not schedule the end of a track");
+    }
+}
+
+/// Length of a crossfade of `setting` seconds into a track that plays for `duration`.
+///
+/// Short tracks are not faded for more than half of their length.
+fn crossfade_length(setting: i64, duration: Duration) -> Duration {
+    Duration::from_secs(setting.clamp(0, MAX_CROSSFADE_SECS) as u64).min(duration / 2)
+}
+
+/// Volumes of the ending and the starting track at `progress` through a crossfade.
+///
+/// The volumes follow a quarter sine, so the sum of their power stays the same.
+fn fade_volumes(progress: f32) -> (f32, f32) {
+    let angle = progress.clamp(0.0, 1.0) * FRAC_PI_2;
+    (angle.cos(), angle.sin())
+}
+
+/// What a crossfade does next.
+#[derive(Debug, PartialEq)]
+enum FadeStep {
+    /// Turns the ending and the starting track to these volumes.
+    Fade(f32, f32),
+    /// The ending track was paused, so the starting one waits for its turn again.
+    Paused,
+    /// The crossfade is over or the ending track stopped, so the starting one plays on.
+    Done,
+}
+
+/// The step of a crossfade at `progress`, while the ending track is in mode `playing`,
+/// `None` if it is gone.
+fn fade_step(progress: f32, playing: Option<&PlayMode>) -> FadeStep {
+    match playing {
+        Some(PlayMode::Pause) => FadeStep::Paused,
+        Some(PlayMode::Play) if progress < 1.0 => {
+            let (out, into) = fade_volumes(progress);
+            FadeStep::Fade(out, into)
+        }
+        _ => FadeStep::Done,
+    }
+}
+
+/// Fires a while before the end of a track, to prepare the next one.
+struct TailNotifier {
+    state: State,
+    guild_id: Id<GuildMarker>,
+    /// How long the track plays.
+    duration: Duration,
+}
+
+#[async_trait]
+impl EventHandler for TailNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(track_list) = ctx else {
+            return None;
+        };
+        let (track_state, handle) = track_list.first()?;
+        let handle = (*handle).clone();
+        let position = track_state.position;
+        let state = State::clone(&self.state);
+        let (guild_id, duration) = (self.guild_id, self.duration);
+        spawn(async move {
+            let Some(next) = next_track(&state, guild_id, &handle).await else {
+                return Ok(());
+            };
+            // the next track is ready to start when the current one ends, without a gap
+            drop(next.make_playable());
+
+            let setting = db::guild::guild_crossfade(&state.pool, guild_id.to_string()).await?;
+            let length = crossfade_length(setting, duration);
+            if length.is_zero() {
+                return Ok(());
+            }
+            handle.add_event(
+                Event::Delayed(duration.saturating_sub(length).saturating_sub(position)),
+                FadeNotifier {
+                    state: State::clone(&state),
+                    guild_id,
+                    length,
+                },
+            )?;
+            Ok::<(), Box<dyn Error + Send + Sync + 'static>>(())
+        });
+        None
+    }
+}
+
+/// Fires when the crossfade from a track into the next one starts.
+struct FadeNotifier {
+    state: State,
+    guild_id: Id<GuildMarker>,
+    length: Duration,
+}
+
+#[async_trait]
+impl EventHandler for FadeNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(track_list) = ctx else {
+            return None;
+        };
+        let (track_state, handle) = track_list.first()?;
+        if !matches!(track_state.playing, PlayMode::Play) {
+            return None;
+        }
+        let handle = (*handle).clone();
+        let state = State::clone(&self.state);
+        let (guild_id, length) = (self.guild_id, self.length);
+        spawn(async move {
+            // the queue may have changed since the next track was prepared
+            let Some(next) = next_track(&state, guild_id, &handle).await else {
+                return Ok(());
+            };
+            fade(&state, guild_id, &handle, &next, length).await
+        });
+        None
+    }
+}
+
+/// The track after the one of `handle` in the queue of `guild_id`.
+async fn next_track(
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    handle: &TrackHandle,
+) -> Option<TrackHandle> {
+    let call_lock = state.songbird.get(guild_id)?;
+    let queue = call_lock.lock().await.queue().current_queue();
+    let index = queue
+        .iter()
+        .position(|track| track.uuid() == handle.uuid())?;
+    queue.get(index + 1).cloned()
+}
+
+/// Starts `next` silently and turns it up while `current` is turned down.
+async fn fade(
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    current: &TrackHandle,
+    next: &TrackHandle,
+    length: Duration,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let current_volume = volume::track_volume(state, guild_id, &current.data::<Metadata>());
+    let next_volume = volume::track_volume(state, guild_id, &next.data::<Metadata>());
+    next.set_volume(0.0)?;
+    next.play()?;
+    let start = Instant::now();
+    let mut interval = time::interval(FADE_STEP);
+    loop {
+        interval.tick().await;
+        let progress = start.elapsed().as_secs_f32() / length.as_secs_f32();
+        // skipping or pausing the current track ends the crossfade early
+        let playing = current.get_info().await.ok().map(|info| info.playing);
+        match fade_step(progress, playing.as_ref()) {
+            FadeStep::Fade(out, into) => {
+                current.set_volume(out * current_volume)?;
+                next.set_volume(into * next_volume)?;
+            }
+            FadeStep::Paused => {
+                // `/pause` only pauses the current track of the queue
+                next.pause()?;
+                next.seek_async(Duration::ZERO).await?;
+                next.set_volume(next_volume)?;
+                current.set_volume(current_volume)?;
+                return Ok(());
+            }
+            FadeStep::Done => {
+                next.set_volume(next_volume)?;
+                if playing != Some(PlayMode::Play) {
+                    current.set_volume(current_volume)?;
+                }
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crossfade_length() {
+        let minutes = Duration::from_secs(180);
+        assert_eq!(crossfade_length(6, minutes), Duration::from_secs(6));
+        assert_eq!(crossfade_length(0, minutes), Duration::ZERO);
+        assert_eq!(crossfade_length(60, minutes), Duration::from_secs(12));
+        assert_eq!(
+            crossfade_length(6, Duration::from_secs(8)),
+            Duration::from_secs(4)
+        );
+    }
+
+    #[test]
+    fn test_fade_volumes() {
+        assert_eq!(fade_volumes(0.0), (1.0, 0.0));
+        let (out, into) = fade_volumes(1.0);
+        assert!(out.abs() < 1e-6 && (into - 1.0).abs() < 1e-6);
+        for progress in [0.1, 0.5, 0.9] {
+            let (out, into) = fade_volumes(progress);
+            assert!((out * out + into * into - 1.0).abs() < 1e-6);
+            assert!(out > 0.0 && into > 0.0);
+        }
+        assert!(fade_volumes(0.25).0 > fade_volumes(0.75).0);
+    }
+
+    #[test]
+    fn test_fade_step() {
+        let (out, into) = fade_volumes(0.5);
+        assert_eq!(
+            fade_step(0.5, Some(&PlayMode::Play)),
+            FadeStep::Fade(out, into)
+        );
+        assert_eq!(fade_step(1.0, Some(&PlayMode::Play)), FadeStep::Done);
+        // pausing during the crossfade pauses the next track as well
+        assert_eq!(fade_step(0.5, Some(&PlayMode::Pause)), FadeStep::Paused);
+        assert_eq!(fade_step(1.0, Some(&PlayMode::Pause)), FadeStep::Paused);
+        // skipped and ended tracks leave the next one playing
+        assert_eq!(fade_step(0.5, Some(&PlayMode::Stop)), FadeStep::Done);
+        assert_eq!(fade_step(0.5, Some(&PlayMode::End)), FadeStep::Done);
+        assert_eq!(fade_step(0.5, None), FadeStep::Done);
+    }
+}
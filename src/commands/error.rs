@@ -0,0 +1,153 @@
+use crate::commands::framework::OptionError;
+use crate::i18n::Locale;
+use crate::resolve::ResolveError;
+use crate::utils::format_duration;
+use songbird::error::JoinError;
+use std::{error::Error, fmt, time::Duration};
+use twilight_http::{api_error::ApiError, error::ErrorType};
+
+/// Failures of commands that are caused by the user or the bot's situation.
+#[derive(Debug)]
+pub(crate) enum CommandError {
+    NotInGuild,
+    NotInVoice,
+    NothingPlaying,
+    BotMissingPermissions,
+    UnknownPlaylist,
+    PlaylistExists,
+    NotPlaylistOwner,
+    InvalidImportFile,
+    ImportTooLarge,
+    /// The member has as many tracks in the queue as they may.
+    QueueLimitReached(i64),
+    /// The tracks are longer than the guild allows.
+    TrackTooLong(Duration),
+    /// The tracks are on the blocklist of the guild.
+    Blocked,
+    /// The current track has no chapters.
+    NoChapters,
+    /// The current chapter is the last one of the track.
+    LastChapter,
+    /// The chapter with the number does not exist, the track has the count of chapters.
+    UnknownChapter {
+        number: i64,
+        count: usize,
+    },
+}
+
+impl CommandError {
+    /// Explains the failure to a user in `locale`.
+    pub(crate) fn message(&self, locale: Locale) -> String {
+        let key = match self {
+            Self::NotInGuild => "errors.not_in_guild",
+            Self::NotInVoice => "errors.not_in_voice",
+            Self::NothingPlaying => "errors.nothing_playing",
+            Self::BotMissingPermissions => "errors.bot_missing_permissions",
+            Self::UnknownPlaylist => "errors.unknown_playlist",
+            Self::PlaylistExists => "errors.playlist_exists",
+            Self::NotPlaylistOwner => "errors.not_playlist_owner",
+            Self::InvalidImportFile => "errors.invalid_import_file",
+            Self::ImportTooLarge => "errors.import_too_large",
+            Self::Blocked => "errors.blocked",
+            Self::NoChapters => "errors.no_chapters",
+            Self::LastChapter => "errors.last_chapter",
+            Self::QueueLimitReached(limit) => {
+                return locale.format("errors.queue_limit_reached", &[("limit", limit)]);
+            }
+            Self::TrackTooLong(limit) => {
+                let limit = format_duration(*limit);
+                return locale.format("errors.track_too_long", &[("limit", &limit)]);
+            }
+            Self::UnknownChapter { number, count } => {
+                return locale.format(
+                    "errors.unknown_chapter",
+                    &[("number", number), ("count", count)],
+                );
+            }
+        };
+        locale.text(key).to_string()
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(Locale::En))
+    }
+}
+
+impl Error for CommandError {}
+
+/// Discord's error code for "Missing Permissions".
+const MISSING_PERMISSIONS: u64 = 50013;
+
+/// Explains `error` in `locale` to the user who invoked the failed command.
+pub(crate) fn user_message(error: &(dyn Error + 'static), locale: Locale) -> String {
+    let chain = std::iter::successors(Some(error), |&e| e.source());
+    for e in chain {
+        if let Some(e) = e.downcast_ref::<CommandError>() {
+            return e.message(locale);
+        }
+        if let Some(e) = e.downcast_ref::<ResolveError>() {
+            return e.message(locale);
+        }
+        if let Some(e) = e.downcast_ref::<OptionError>() {
+            return e.message(locale);
+        }
+        if let Some(JoinError::TimedOut) = e.downcast_ref::<JoinError>() {
+            return locale.text("errors.join_timed_out").to_string();
+        }
+        if let Some(e) = e.downcast_ref::<twilight_http::Error>() {
+            if let ErrorType::Response {
+                error: ApiError::General(error),
+                ..
+            } = e.kind()
+            {
+                if error.code == MISSING_PERMISSIONS {
+                    return CommandError::BotMissingPermissions.message(locale);
+                }
+            }
+        }
+    }
+    locale.text("errors.unexpected").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_user_message() {
+        let e: Box<dyn Error + Send + Sync> = CommandError::NotInVoice.into();
+        assert_eq!(
+            user_message(e.as_ref(), Locale::En),
+            "You need to be in a voice channel to do that."
+        );
+        assert_eq!(
+            user_message(e.as_ref(), Locale::De),
+            "Dafür musst du in einem Sprachkanal sein."
+        );
+
+        let e: Box<dyn Error + Send + Sync> = ResolveError::PremiumOnly.into();
+        assert_eq!(
+            user_message(e.as_ref(), Locale::En),
+            ResolveError::PremiumOnly.to_string()
+        );
+
+        let e: Box<dyn Error + Send + Sync> = "unexpected".into();
+        assert_eq!(
+            user_message(e.as_ref(), Locale::En),
+            "Something went wrong, please try again later."
+        );
+    }
+
+    #[test]
+    fn test_user_message_with_context() {
+        let result: Result<(), JoinError> = Err(JoinError::TimedOut);
+        let e: Box<dyn Error + Send + Sync> = result
+            .context("Could not join voice channel")
+            .unwrap_err()
+            .into();
+        assert!(user_message(e.as_ref(), Locale::En).contains("could not join your voice channel"));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::commands::framework::{
+    defer, optional, CommandResult, FromOptions, OptionError, Options, SlashCommand,
+};
+use crate::commands::playlist::find_playlist;
+use crate::commands::CommandError;
+use crate::db;
+use crate::i18n::Locale;
+use crate::metadata::Metadata;
+use crate::playlist_file::{self, Entry, Format};
+use crate::state::State;
+use crate::utils::parse_duration;
+use async_trait::async_trait;
+use twilight_model::{
+    application::command::{Command, CommandType},
+    gateway::payload::incoming::InteractionCreate,
+    http::attachment::Attachment,
+};
+use twilight_util::builder::command::{CommandBuilder, StringBuilder};
+
+pub(crate) struct Export;
+
+pub(crate) struct ExportOptions {
+    /// `None` to export the current queue.
+    playlist: Option<String>,
+    format: Format,
+}
+
+impl FromOptions for ExportOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        let format = match optional(options.string("format"))?.as_deref() {
+            Some("json") | None => Format::Json,
+            Some("m3u") => Format::M3u,
+            Some(_) => return Err(OptionError::InvalidType("format".to_string())),
+        };
+        Ok(Self {
+            playlist: optional(options.string("playlist"))?,
+            format,
+        })
+    }
+}
+
+/// Name of the exported file, from the name of what is exported.
+fn filename(name: &str, format: Format) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{name}.{}", format.extension())
+}
+
+#[async_trait]
+impl SlashCommand for Export {
+    type Options = ExportOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "export",
+            "Download the queue or a playlist as a file",
+            CommandType::ChatInput,
+        )
+        .option(StringBuilder::new(
+            "playlist",
+            "Playlist to export, none for the current queue",
+        ))
+        .option(
+            StringBuilder::new("format", "Format of the file, JSON by default")
+                .choices([("JSON", "json"), ("M3U", "m3u")]),
+        )
+        .build()
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: ExportOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "export command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let (Some(guild_id), Some(user_id)) = (interaction.guild_id, interaction.author_id())
+        else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        let locale = Locale::for_interaction(&interaction, &state).await;
+
+        let (name, mut entries) = match options.playlist {
+            Some(name) => {
+                let playlist = find_playlist(&state, guild_id, user_id, name).await?;
+                let entries = db::playlist::playlist_tracks(&state.pool, playlist.id)
+                    .await?
+                    .into_iter()
+                    .map(|track| {
+                        let title = Some(track.title).filter(|title| !title.is_empty());
+                        Entry::new(track.url, title, parse_duration(&track.duration))
+                    })
+                    .collect();
+                (Some(playlist.name), entries)
+            }
+            None => {
+                let Some(call_lock) = state.songbird.get(guild_id) else {
+                    return Err(CommandError::NothingPlaying.into());
+                };
+                let queue = call_lock.lock().await.queue().current_queue();
+                if queue.is_empty() {
+                    return Err(CommandError::NothingPlaying.into());
+                }
+                let entries: Vec<_> = queue
+                    .iter()
+                    .map(|handle| {
+                        let metadata = handle.data::<Metadata>();
+                        Entry::new(
+                            metadata.url.clone(),
+                            metadata.title.clone(),
+                            metadata.duration,
+                        )
+                    })
+                    .collect();
+                (None, entries)
+            }
+        };
+        defer(&interaction, &state, locale.text("export.exporting")).await?;
+
+        // fill in what is known about the tracks from when they were played
+        for entry in &mut entries {
+            if let Some(track) =
+                db::track::find_track_by_url(&state.pool, entry.url.clone()).await?
+            {
+                let known = |s: String| Some(s).filter(|s| !s.is_empty());
+                entry.title = entry.title.take().or(known(track.title));
+                entry.channel = known(track.channel);
+                entry.thumbnail = known(track.thumbnail);
+            }
+        }
+
+        let file = playlist_file::write(name.as_deref(), &entries, options.format);
+        let attachments = [Attachment::from_bytes(
+            filename(name.as_deref().unwrap_or("queue"), options.format),
+            file.into_bytes(),
+            0,
+        )];
+        let content = locale.format("export.exported", &[("count", &entries.len())]);
+        state
+            .http
+            .interaction(interaction.application_id)
+            .update_response(&interaction.token)
+            .content(Some(&content))
+            .embeds(Some(&[]))
+            .attachments(&attachments)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filename() {
+        assert_eq!(filename("queue", Format::Json), "queue.json");
+        assert_eq!(filename("Chill / Lo-Fi", Format::M3u), "Chill___Lo-Fi.m3u");
+    }
+}
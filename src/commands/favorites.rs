@@ -0,0 +1,209 @@
+use crate::colors;
+use crate::commands::framework::{
+    announce_embeds, confirm, defer, defer_announcement, reply, update_response, CommandResult,
+    FromOptions, OptionError, Options, SlashCommand,
+};
+use crate::commands::play::with_notes;
+use crate::commands::playlist::{list_tracks, play_tracks};
+use crate::commands::CommandError;
+use crate::db;
+use crate::i18n::Locale;
+use crate::metadata::Metadata;
+use crate::state::State;
+use crate::utils::format_duration;
+use async_trait::async_trait;
+use twilight_model::{
+    application::command::{Command, CommandType},
+    channel::message::{
+        component::{Button, ButtonStyle},
+        Component, EmojiReactionType,
+    },
+    gateway::payload::incoming::InteractionCreate,
+};
+use twilight_util::builder::{
+    command::{CommandBuilder, SubCommandBuilder},
+    embed::EmbedBuilder,
+};
+
+/// Prefix of the custom id of like buttons, followed by a track id or [`CURRENT_TRACK`].
+pub(crate) const LIKE_PREFIX: &str = "like:";
+
+/// Liking this likes whatever is playing when the button is clicked.
+const CURRENT_TRACK: &str = "current";
+
+/// A button that likes the track with `track_id`, or the current track if it is `None`.
+pub(crate) fn like_button(track_id: Option<i64>, locale: Locale) -> Component {
+    let target = match track_id {
+        Some(track_id) => track_id.to_string(),
+        None => CURRENT_TRACK.to_string(),
+    };
+    Component::Button(Button {
+        custom_id: Some(format!("{LIKE_PREFIX}{target}")),
+        style: ButtonStyle::Secondary,
+        label: Some(locale.text("favorites.like").to_string()),
+        emoji: Some(EmojiReactionType::Unicode {
+            name: "❤️".to_string(),
+        }),
+        url: None,
+        disabled: false,
+        sku_id: None,
+    })
+}
+
+/// Handles a click on a like button with the custom id `LIKE_PREFIX` + `target`.
+///
+/// Clicking it again takes the like back.
+pub(crate) async fn like(
+    interaction: &InteractionCreate,
+    state: &State,
+    target: &str,
+) -> CommandResult {
+    let locale = Locale::for_interaction(interaction, state).await;
+    let Some(user) = interaction.author() else {
+        return Err(CommandError::NotInGuild.into());
+    };
+    let track_id = if target == CURRENT_TRACK {
+        match current_track(interaction, state).await? {
+            Some(track_id) => track_id,
+            None => {
+                let message = CommandError::NothingPlaying.message(locale);
+                return reply(interaction, state, message, true).await;
+            }
+        }
+    } else {
+        target.parse()?
+    };
+
+    db::track::insert_user(
+        &state.pool,
+        db::track::User::new(
+            user.id.to_string(),
+            user.name.clone(),
+            user.global_name.clone(),
+        ),
+    )
+    .await?;
+    let liked = db::like::toggle_like(&state.pool, user.id.to_string(), track_id).await?;
+    let title = db::track::find_track(&state.pool, track_id)
+        .await?
+        .map(|track| track.title)
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| locale.text("unknown").to_string());
+    let key = if liked {
+        "favorites.liked"
+    } else {
+        "favorites.unliked"
+    };
+    let message = locale.format(key, &[("title", &title)]);
+    reply(interaction, state, message, true).await
+}
+
+/// Returns the id of the track that is playing in the guild of `interaction`.
+async fn current_track(
+    interaction: &InteractionCreate,
+    state: &State,
+) -> Result<Option<i64>, sqlx::Error> {
+    let Some(call_lock) = interaction.guild_id.and_then(|id| state.songbird.get(id)) else {
+        return Ok(None);
+    };
+    let Some(handle) = call_lock.lock().await.queue().current() else {
+        return Ok(None);
+    };
+    let metadata = handle.data::<Metadata>();
+    let track = db::track::Track::new(
+        metadata.url.clone(),
+        metadata.title.clone().unwrap_or_default(),
+        String::new(),
+        metadata.duration.map(format_duration).unwrap_or_default(),
+        String::new(),
+    );
+    db::track::insert_track_if_missing(&state.pool, track)
+        .await
+        .map(Some)
+}
+
+pub(crate) struct Favorites;
+
+pub(crate) enum FavoritesOptions {
+    Show,
+    Play,
+}
+
+impl FromOptions for FavoritesOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        match options.subcommand()? {
+            ("show", _) => Ok(Self::Show),
+            ("play", _) => Ok(Self::Play),
+            (name, _) => Err(OptionError::InvalidType(name.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl SlashCommand for Favorites {
+    type Options = FavoritesOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "favorites",
+            "Tracks you liked with the ❤️ button",
+            CommandType::ChatInput,
+        )
+        .option(SubCommandBuilder::new("show", "List the tracks you liked"))
+        .option(SubCommandBuilder::new(
+            "play",
+            "Add the tracks you liked to the queue",
+        ))
+        .build()
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: FavoritesOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "favorites command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let (Some(guild_id), Some(user_id)) = (interaction.guild_id, interaction.author_id())
+        else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        let locale = Locale::for_interaction(&interaction, &state).await;
+
+        match options {
+            FavoritesOptions::Show => {
+                defer(&interaction, &state, locale.text("favorites.loading")).await?;
+                let tracks = db::like::liked_tracks(&state.pool, user_id.to_string()).await?;
+                let message = if tracks.is_empty() {
+                    locale.text("favorites.none").to_string()
+                } else {
+                    list_tracks(&tracks, locale)
+                };
+                update_response(&interaction, &state, message).await
+            }
+            FavoritesOptions::Play => {
+                let tracks = db::like::liked_tracks(&state.pool, user_id.to_string()).await?;
+                if tracks.is_empty() {
+                    return confirm(&interaction, &state, locale.text("favorites.none")).await;
+                }
+                defer_announcement(&interaction, &state, locale.text("favorites.loading")).await?;
+                let (admitted, blocked) = play_tracks(&state, guild_id, user_id, tracks).await?;
+
+                let count = admitted.tracks.len();
+                let message = locale.format("favorites.playing", &[("count", &count)]);
+                let message = with_notes(message, blocked, admitted.left_out, locale);
+                let embeds = vec![EmbedBuilder::new()
+                    .description(message)
+                    .color(colors::BLURPLE)
+                    .build()];
+                announce_embeds(&interaction, &state, &embeds, &[]).await
+            }
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use crate::commands::framework::{
+    announce, optional, CommandResult, FromOptions, OptionError, Options, SlashCommand,
+};
+use crate::commands::{volume, CommandError};
+use crate::db;
+use crate::filter::{self, Equalizer, Filters};
+use crate::i18n::Locale;
+use crate::state::State;
+use async_trait::async_trait;
+use songbird::tracks::TrackHandle;
+use std::{error::Error, time::Duration};
+use twilight_model::{
+    application::command::{Command, CommandType},
+    gateway::payload::incoming::InteractionCreate,
+};
+use twilight_util::builder::command::{
+    BooleanBuilder, CommandBuilder, IntegerBuilder, StringBuilder,
+};
+
+const MAX_SEMITONES: i64 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Preset {
+    Off,
+    Equalizer(Equalizer),
+    Nightcore,
+    Vaporwave,
+}
+
+impl Preset {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "off" => Some(Self::Off),
+            "bassboost" => Some(Self::Equalizer(Equalizer::BassBoost)),
+            "treble" => Some(Self::Equalizer(Equalizer::Treble)),
+            "vocals" => Some(Self::Equalizer(Equalizer::Vocals)),
+            "nightcore" => Some(Self::Nightcore),
+            "vaporwave" => Some(Self::Vaporwave),
+            _ => None,
+        }
+    }
+
+    /// Equalizer presets keep the speed and the other way around.
+    fn apply(self, filters: Filters) -> Filters {
+        match self {
+            Self::Off => Filters::new(),
+            Self::Equalizer(equalizer) => Filters {
+                equalizer,
+                ..filters
+            },
+            Self::Nightcore => Filters {
+                equalizer: filters.equalizer,
+                normalize: filters.normalize,
+                ..Filters::nightcore()
+            },
+            Self::Vaporwave => Filters {
+                equalizer: filters.equalizer,
+                normalize: filters.normalize,
+                ..Filters::vaporwave()
+            },
+        }
+    }
+}
+
+pub(crate) struct Filter;
+
+pub(crate) struct FilterOptions {
+    preset: Option<Preset>,
+    /// In percent of the normal speed.
+    speed: Option<i64>,
+    /// In semitones.
+    pitch: Option<i64>,
+    normalize: Option<bool>,
+}
+
+impl FromOptions for FilterOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        let preset = optional(options.string("preset"))?
+            .map(|preset| {
+                Preset::from_str(&preset).ok_or(OptionError::InvalidType("preset".to_string()))
+            })
+            .transpose()?;
+        Ok(Self {
+            preset,
+            speed: optional(options.integer("speed"))?,
+            pitch: optional(options.integer("pitch"))?,
+            normalize: optional(options.boolean("normalize"))?,
+        })
+    }
+}
+
+impl FilterOptions {
+    fn apply(&self, filters: Filters) -> Filters {
+        let mut filters = match self.preset {
+            Some(preset) => preset.apply(filters),
+            None => filters,
+        };
+        if let Some(speed) = self.speed {
+            filters.speed = (speed as f64 / 100.0).clamp(Filters::MIN_SPEED, Filters::MAX_SPEED);
+        }
+        if let Some(semitones) = self.pitch {
+            filters.pitch =
+                Filters::pitch_from_semitones(semitones.clamp(-MAX_SEMITONES, MAX_SEMITONES));
+        }
+        if let Some(normalize) = self.normalize {
+            filters.normalize = normalize;
+        }
+        filters
+    }
+}
+
+/// Lists the active filters.
+fn describe(filters: Filters, locale: Locale) -> String {
+    if filters == Filters::new() {
+        return locale.text("filter.none").to_string();
+    }
+    let mut message = locale.text("filter.active").to_string();
+    message.push('\n');
+    let equalizer = match filters.equalizer {
+        Equalizer::Flat => None,
+        Equalizer::BassBoost => Some("filter.bass_boost"),
+        Equalizer::Treble => Some("filter.treble"),
+        Equalizer::Vocals => Some("filter.vocals"),
+    };
+    if let Some(key) = equalizer {
+        message.push_str(&format!("* {}\n", locale.text(key)));
+    }
+    if filters.speed != 1.0 {
+        let percent = (filters.speed * 100.0).round();
+        let line = locale.format("filter.speed", &[("percent", &percent)]);
+        message.push_str(&format!("* {line}\n"));
+    }
+    if filters.pitch != 1.0 {
+        let semitones = format!("{:+.1}", filters.semitones());
+        let line = locale.format("filter.pitch", &[("semitones", &semitones)]);
+        message.push_str(&format!("* {line}\n"));
+    }
+    if filters.normalize {
+        message.push_str(&format!("* {}\n", locale.text("filter.normalize")));
+    }
+    message
+}
+
+/// Restarts `handle` at the same place in the track, to play it with `filters` instead of
+/// `previous`.
+async fn restart(
+    handle: &TrackHandle,
+    previous: Filters,
+    filters: Filters,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let position = handle.get_info().await?.position;
+    // a track that did not play yet picks up the filters when it starts
+    if position.is_zero() {
+        return Ok(());
+    }
+    // filtered inputs cannot seek back, so songbird recreates the input with the new filters
+    handle.seek_async(Duration::ZERO).await?;
+    handle
+        .seek_async(filters.convert_position(previous, position))
+        .await?;
+    Ok(())
+}
+
+#[async_trait]
+impl SlashCommand for Filter {
+    type Options = FilterOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "filter",
+            "Change how tracks sound, or show the active filters",
+            CommandType::ChatInput,
+        )
+        .option(
+            StringBuilder::new("preset", "Equalizer or speed preset").choices([
+                ("Off", "off"),
+                ("Bass boost", "bassboost"),
+                ("Treble", "treble"),
+                ("Vocals", "vocals"),
+                ("Nightcore", "nightcore"),
+                ("Vaporwave", "vaporwave"),
+            ]),
+        )
+        .option(
+            IntegerBuilder::new("speed", "Playback speed in percent")
+                .min_value((Filters::MIN_SPEED * 100.0) as i64)
+                .max_value((Filters::MAX_SPEED * 100.0) as i64),
+        )
+        .option(
+            IntegerBuilder::new("pitch", "Pitch shift in semitones")
+                .min_value(-MAX_SEMITONES)
+                .max_value(MAX_SEMITONES),
+        )
+        .option(BooleanBuilder::new(
+            "normalize",
+            "Play all tracks at the same loudness",
+        ))
+        .build()
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: FilterOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "filter command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        let previous = filter::saved_filters(&state, guild_id).await;
+        let filters = options.apply(previous);
+        db::guild::set_filter_settings(
+            &state.pool,
+            db::track::Guild::new(guild_id.to_string()),
+            filters.into(),
+        )
+        .await?;
+        state.guild_settings.entry(guild_id).or_default().filters = filters;
+
+        if filters != previous {
+            let current = match state.songbird.get(guild_id) {
+                Some(call_lock) => call_lock.lock().await.queue().current(),
+                None => None,
+            };
+            if let Some(handle) = current {
+                restart(&handle, previous, filters).await?;
+            }
+        }
+        if filters.normalize != previous.normalize {
+            volume::update_volumes(&state, guild_id).await?;
+        }
+
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        announce(&interaction, &state, describe(filters, locale)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let options = FilterOptions {
+            preset: Some(Preset::Nightcore),
+            speed: None,
+            pitch: None,
+            normalize: Some(true),
+        };
+        let bass_boost = Filters {
+            equalizer: Equalizer::BassBoost,
+            ..Filters::new()
+        };
+        assert_eq!(
+            options.apply(bass_boost),
+            Filters {
+                equalizer: Equalizer::BassBoost,
+                normalize: true,
+                ..Filters::nightcore()
+            }
+        );
+
+        let options = FilterOptions {
+            preset: Some(Preset::Off),
+            speed: Some(500),
+            pitch: Some(-12),
+            normalize: None,
+        };
+        let filters = options.apply(bass_boost);
+        assert_eq!(filters.equalizer, Equalizer::Flat);
+        assert_eq!(filters.speed, Filters::MAX_SPEED);
+        assert_eq!(filters.pitch, 0.5);
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(
+            describe(Filters::new(), Locale::En),
+            "Tracks play without filters."
+        );
+        let filters = Filters {
+            equalizer: Equalizer::BassBoost,
+            normalize: true,
+            ..Filters::nightcore()
+        };
+        assert_eq!(
+            describe(filters, Locale::En),
+            "Tracks play with these filters:\n\
+             * Bass boost\n\
+             * Speed: 125%\n\
+             * Pitch: +3.9 semitones\n\
+             * Loudness normalization\n"
+        );
+    }
+}
@@ -0,0 +1,770 @@
+use crate::cleanup::{self, MessageKind};
+use crate::colors;
+use crate::commands::error::user_message;
+use crate::db::{self, guild::ResponseSettings};
+use crate::i18n::{localize_command, Locale};
+use crate::state::State;
+use async_trait::async_trait;
+use std::{cell::Cell, error::Error, fmt, future::Future, sync::Arc};
+use twilight_model::{
+    application::{
+        command::Command,
+        interaction::{
+            application_command::{CommandDataOption, CommandOptionValue},
+            InteractionData,
+        },
+    },
+    channel::{
+        message::{Component, Embed, MessageFlags},
+        Attachment, Message,
+    },
+    gateway::payload::incoming::InteractionCreate,
+    guild::Permissions,
+    http::interaction::{InteractionResponse, InteractionResponseType},
+    id::{
+        marker::{AttachmentMarker, ChannelMarker, UserMarker},
+        Id,
+    },
+};
+use twilight_util::builder::{
+    embed::{EmbedBuilder, EmbedFooterBuilder},
+    InteractionResponseDataBuilder,
+};
+use uuid::Uuid;
+
+pub(crate) type CommandResult = Result<(), Box<dyn Error + Send + Sync + 'static>>;
+
+/// How the interaction of the running command has been responded to so far.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Acknowledgement {
+    None,
+    /// Only the loading message was sent.
+    Deferred,
+    /// A message was sent or the loading message was replaced.
+    Replied,
+}
+
+/// How the running command responds, following the settings of its guild.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Response {
+    /// Whether confirmations are only shown to the user who ran the command.
+    ephemeral: bool,
+    /// Channel that changes of the queue are posted in instead.
+    announce_channel: Option<Id<ChannelMarker>>,
+    /// Whether confirmations are deleted after a while.
+    cleanup: bool,
+}
+
+impl Response {
+    fn new(
+        settings: ResponseSettings,
+        public_by_default: bool,
+        channel_id: Option<Id<ChannelMarker>>,
+    ) -> Self {
+        let announce_channel = settings
+            .announce_channel_id
+            .and_then(|id| id.parse().ok())
+            .filter(|&id| Some(id) != channel_id);
+        Self {
+            ephemeral: settings.quiet || !settings.public.unwrap_or(public_by_default),
+            announce_channel,
+            cleanup: settings.cleanup,
+        }
+    }
+
+    async fn for_interaction(
+        interaction: &InteractionCreate,
+        state: &State,
+        command: String,
+        public_by_default: bool,
+    ) -> Self {
+        let mut settings = ResponseSettings::default();
+        if let Some(guild_id) = interaction.guild_id {
+            match db::guild::response_settings(&state.pool, guild_id.to_string(), command).await {
+                Ok(s) => settings = s,
+                Err(e) => tracing::warn!(?e, "could not load response settings of {guild_id}"),
+            }
+        }
+        let channel_id = interaction.channel.as_ref().map(|channel| channel.id);
+        Self::new(settings, public_by_default, channel_id)
+    }
+}
+
+tokio::task_local! {
+    static ACKNOWLEDGEMENT: Cell<Acknowledgement>;
+    static RESPONSE: Response;
+}
+
+fn acknowledge(acknowledgement: Acknowledgement) {
+    // outside of a dispatched command there is no error to report later
+    let _ = ACKNOWLEDGEMENT.try_with(|cell| cell.set(acknowledgement));
+}
+
+fn response() -> Response {
+    RESPONSE.try_with(|response| *response).unwrap_or_default()
+}
+
+/// A slash command that can be registered with Discord and executed.
+#[async_trait]
+pub(crate) trait SlashCommand: Send + Sync + 'static {
+    /// Options of the command, extracted from the interaction before [`Self::execute`] runs.
+    type Options: FromOptions + Send;
+
+    fn definition(&self) -> Command;
+
+    /// Permissions a member needs to use the command.
+    fn required_permissions(&self) -> Option<Permissions> {
+        None
+    }
+
+    /// Whether confirmations are public unless the guild configured otherwise.
+    fn public_by_default(&self) -> bool {
+        true
+    }
+
+    /// Whether guilds can make the confirmations of the command public or private.
+    fn configurable_visibility(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: Self::Options,
+    ) -> CommandResult;
+}
+
+/// Typed options of a [`SlashCommand`].
+pub(crate) trait FromOptions: Sized {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError>;
+}
+
+impl FromOptions for () {
+    fn from_options(_: &Options<'_>) -> Result<Self, OptionError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum OptionError {
+    Missing(String),
+    InvalidType(String),
+}
+
+impl OptionError {
+    /// Explains the failure to a user in `locale`.
+    pub(crate) fn message(&self, locale: Locale) -> String {
+        match self {
+            Self::Missing(name) => locale.format("errors.missing_option", &[("name", name)]),
+            Self::InvalidType(name) => locale.format("errors.invalid_option", &[("name", name)]),
+        }
+    }
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(Locale::En))
+    }
+}
+
+impl Error for OptionError {}
+
+/// Options as sent by Discord for an invocation of a command.
+pub(crate) struct Options<'a> {
+    options: &'a [CommandDataOption],
+}
+
+impl<'a> Options<'a> {
+    pub(crate) fn new(options: &'a [CommandDataOption]) -> Self {
+        Self { options }
+    }
+
+    fn get(&self, name: &str) -> Option<&'a CommandOptionValue> {
+        self.options
+            .iter()
+            .find(|option| option.name == name)
+            .map(|option| &option.value)
+    }
+
+    pub(crate) fn string(&self, name: &str) -> Result<String, OptionError> {
+        match self.get(name) {
+            Some(CommandOptionValue::String(value)) => Ok(value.clone()),
+            Some(_) => Err(OptionError::InvalidType(name.to_string())),
+            None => Err(OptionError::Missing(name.to_string())),
+        }
+    }
+
+    pub(crate) fn boolean(&self, name: &str) -> Result<bool, OptionError> {
+        match self.get(name) {
+            Some(CommandOptionValue::Boolean(value)) => Ok(*value),
+            Some(_) => Err(OptionError::InvalidType(name.to_string())),
+            None => Err(OptionError::Missing(name.to_string())),
+        }
+    }
+
+    pub(crate) fn integer(&self, name: &str) -> Result<i64, OptionError> {
+        match self.get(name) {
+            Some(CommandOptionValue::Integer(value)) => Ok(*value),
+            Some(_) => Err(OptionError::InvalidType(name.to_string())),
+            None => Err(OptionError::Missing(name.to_string())),
+        }
+    }
+
+    pub(crate) fn user(&self, name: &str) -> Result<Id<UserMarker>, OptionError> {
+        match self.get(name) {
+            Some(CommandOptionValue::User(value)) => Ok(*value),
+            Some(_) => Err(OptionError::InvalidType(name.to_string())),
+            None => Err(OptionError::Missing(name.to_string())),
+        }
+    }
+
+    pub(crate) fn channel(&self, name: &str) -> Result<Id<ChannelMarker>, OptionError> {
+        match self.get(name) {
+            Some(CommandOptionValue::Channel(value)) => Ok(*value),
+            Some(_) => Err(OptionError::InvalidType(name.to_string())),
+            None => Err(OptionError::Missing(name.to_string())),
+        }
+    }
+
+    /// Id of an uploaded file, see [`resolved_attachment`].
+    pub(crate) fn attachment(&self, name: &str) -> Result<Id<AttachmentMarker>, OptionError> {
+        match self.get(name) {
+            Some(CommandOptionValue::Attachment(value)) => Ok(*value),
+            Some(_) => Err(OptionError::InvalidType(name.to_string())),
+            None => Err(OptionError::Missing(name.to_string())),
+        }
+    }
+
+    /// Name and options of the invoked subcommand.
+    pub(crate) fn subcommand(&self) -> Result<(&'a str, Options<'a>), OptionError> {
+        self.options
+            .iter()
+            .find_map(|option| match &option.value {
+                CommandOptionValue::SubCommand(options) => {
+                    Some((option.name.as_str(), Options::new(options)))
+                }
+                _ => None,
+            })
+            .ok_or_else(|| OptionError::Missing("subcommand".to_string()))
+    }
+}
+
+/// The file with `id` that was uploaded with `interaction`.
+pub(crate) fn resolved_attachment(
+    interaction: &InteractionCreate,
+    id: Id<AttachmentMarker>,
+) -> Option<&Attachment> {
+    match &interaction.data {
+        Some(InteractionData::ApplicationCommand(data)) => {
+            data.resolved.as_ref()?.attachments.get(&id)
+        }
+        _ => None,
+    }
+}
+
+/// Turns a missing option into `None`.
+pub(crate) fn optional<T>(option: Result<T, OptionError>) -> Result<Option<T>, OptionError> {
+    match option {
+        Ok(value) => Ok(Some(value)),
+        Err(OptionError::Missing(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Object-safe form of [`SlashCommand`], used by the [`Registry`].
+#[async_trait]
+trait DynCommand: Send + Sync {
+    fn definition(&self) -> Command;
+    fn configurable_visibility(&self) -> bool;
+    async fn dispatch(&self, interaction: Box<InteractionCreate>, state: State) -> CommandResult;
+}
+
+#[async_trait]
+impl<T: SlashCommand> DynCommand for T {
+    fn definition(&self) -> Command {
+        let mut command = SlashCommand::definition(self);
+        command.default_member_permissions = self.required_permissions();
+        localize_command(&mut command);
+        command
+    }
+
+    fn configurable_visibility(&self) -> bool {
+        SlashCommand::configurable_visibility(self)
+    }
+
+    async fn dispatch(&self, interaction: Box<InteractionCreate>, state: State) -> CommandResult {
+        if let Some(required) = self.required_permissions() {
+            let permissions = interaction
+                .member
+                .as_ref()
+                .and_then(|member| member.permissions)
+                .unwrap_or_else(Permissions::empty);
+            if !permissions.contains(required) {
+                let locale = Locale::for_interaction(&interaction, &state).await;
+                let content = locale.format(
+                    "errors.missing_permissions",
+                    &[("permissions", &format!("{required:?}"))],
+                );
+                return reply(&interaction, &state, content, true).await;
+            }
+        }
+
+        let data_options = match &interaction.data {
+            Some(InteractionData::ApplicationCommand(data)) => data.options.as_slice(),
+            _ => &[],
+        };
+        let options = match T::Options::from_options(&Options::new(data_options)) {
+            Ok(options) => options,
+            Err(e) => {
+                let locale = Locale::for_interaction(&interaction, &state).await;
+                return reply(&interaction, &state, e.message(locale), true).await;
+            }
+        };
+        let name = SlashCommand::definition(self).name;
+        let response =
+            Response::for_interaction(&interaction, &state, name, self.public_by_default()).await;
+        RESPONSE
+            .scope(response, self.execute(interaction, state, options))
+            .await
+    }
+}
+
+/// All commands of the bot, in the order they are registered.
+pub(crate) struct Registry {
+    commands: Vec<(String, Box<dyn DynCommand>)>,
+}
+
+impl Registry {
+    pub(crate) fn new() -> Self {
+        Self {
+            commands: Vec::new(),
+        }
+    }
+
+    pub(crate) fn command(mut self, command: impl SlashCommand) -> Self {
+        let name = SlashCommand::definition(&command).name;
+        self.commands.push((name, Box::new(command)));
+        self
+    }
+
+    pub(crate) fn definitions(&self) -> Vec<Command> {
+        self.commands
+            .iter()
+            .map(|(_, command)| command.definition())
+            .collect()
+    }
+
+    /// Names of the commands whose confirmations guilds can make public or private.
+    pub(crate) fn configurable_commands(&self) -> Vec<String> {
+        self.commands
+            .iter()
+            .filter(|(_, command)| command.configurable_visibility())
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Runs the command named in `interaction`.
+    pub(crate) async fn dispatch(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+    ) -> CommandResult {
+        let name = match &interaction.data {
+            Some(InteractionData::ApplicationCommand(data)) => data.name.clone(),
+            _ => return Ok(()),
+        };
+        match self.commands.iter().find(|(n, _)| *n == name) {
+            Some((_, command)) => {
+                let handler = command.dispatch(interaction.clone(), Arc::clone(&state));
+                run_handler(&interaction, &state, handler).await
+            }
+            None => {
+                let locale = Locale::for_interaction(&interaction, &state).await;
+                let content = locale.format("errors.unknown_command", &[("name", &name)]);
+                reply(&interaction, &state, content, true).await
+            }
+        }
+    }
+}
+
+/// Responds to `interaction` with a plain message.
+pub(crate) async fn reply(
+    interaction: &InteractionCreate,
+    state: &State,
+    content: impl Into<String>,
+    ephemeral: bool,
+) -> CommandResult {
+    let mut interaction_response_data = InteractionResponseDataBuilder::new().content(content);
+    if ephemeral {
+        interaction_response_data = interaction_response_data.flags(MessageFlags::EPHEMERAL);
+    }
+    let response = InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(interaction_response_data.build()),
+    };
+    let client = state.http.interaction(interaction.application_id);
+    client
+        .create_response(interaction.id, &interaction.token, &response)
+        .await?;
+    acknowledge(Acknowledgement::Replied);
+
+    if !ephemeral && self::response().cleanup {
+        let message = client.response(&interaction.token).await?.model().await?;
+        remember(interaction, state, &message, MessageKind::Confirmation).await;
+    }
+    Ok(())
+}
+
+/// Remembers a message posted for `interaction`, to clean it up later.
+///
+/// Confirmations are only remembered in guilds with auto-cleanup.
+pub(crate) async fn remember(
+    interaction: &InteractionCreate,
+    state: &State,
+    message: &Message,
+    kind: MessageKind,
+) {
+    if kind == MessageKind::Confirmation && !response().cleanup {
+        return;
+    }
+    if let Some(guild_id) = interaction.guild_id {
+        cleanup::remember(state, guild_id, message, kind).await;
+    }
+}
+
+/// Responds to `interaction` as configured for the guild.
+pub(crate) async fn confirm(
+    interaction: &InteractionCreate,
+    state: &State,
+    content: impl Into<String>,
+) -> CommandResult {
+    reply(interaction, state, content, response().ephemeral).await
+}
+
+/// Tells the guild about a change of its queue, in its announce channel if it has one.
+///
+/// The user who ran the command then gets the same message privately.
+pub(crate) async fn announce(
+    interaction: &InteractionCreate,
+    state: &State,
+    content: impl Into<String>,
+) -> CommandResult {
+    let content = content.into();
+    let response = response();
+    match response.announce_channel {
+        Some(channel_id) => {
+            let message = state
+                .http
+                .create_message(channel_id)
+                .content(&content)
+                .await?
+                .model()
+                .await?;
+            remember(interaction, state, &message, MessageKind::Confirmation).await;
+            reply(interaction, state, content, true).await
+        }
+        None => reply(interaction, state, content, response.ephemeral).await,
+    }
+}
+
+/// Acknowledges `interaction` with a loading message, to be replaced with `update_response`.
+pub(crate) async fn defer(
+    interaction: &InteractionCreate,
+    state: &State,
+    content: impl Into<String>,
+) -> CommandResult {
+    defer_with(interaction, state, content, response().ephemeral).await
+}
+
+/// Replaces the loading message of [`defer`] with an embed showing `description`.
+pub(crate) async fn update_response(
+    interaction: &InteractionCreate,
+    state: &State,
+    description: impl Into<String>,
+) -> CommandResult {
+    let embeds = vec![EmbedBuilder::new()
+        .description(description)
+        .color(colors::BLURPLE)
+        .build()];
+    state
+        .http
+        .interaction(interaction.application_id)
+        .update_response(&interaction.token)
+        .embeds(Some(&embeds))
+        .await?;
+    acknowledge(Acknowledgement::Replied);
+    Ok(())
+}
+
+/// Replaces the loading message of [`defer`] with the first of `messages`, each a list of
+/// embeds, and posts the others as follow-ups.
+pub(crate) async fn respond_embeds(
+    interaction: &InteractionCreate,
+    state: &State,
+    messages: &[Vec<Embed>],
+) -> CommandResult {
+    let ephemeral = response().ephemeral;
+    let client = state.http.interaction(interaction.application_id);
+    for (index, embeds) in messages.iter().enumerate() {
+        let message = if index == 0 {
+            let message = client
+                .update_response(&interaction.token)
+                .embeds(Some(embeds))
+                .await?;
+            acknowledge(Acknowledgement::Replied);
+            message
+        } else {
+            let mut followup = client.create_followup(&interaction.token).embeds(embeds);
+            if ephemeral {
+                followup = followup.flags(MessageFlags::EPHEMERAL);
+            }
+            followup.await?
+        };
+        if !ephemeral {
+            let message = message.model().await?;
+            remember(interaction, state, &message, MessageKind::Confirmation).await;
+        }
+    }
+    Ok(())
+}
+
+/// Like [`defer`], for a command that ends with [`announce_embeds`].
+pub(crate) async fn defer_announcement(
+    interaction: &InteractionCreate,
+    state: &State,
+    content: impl Into<String>,
+) -> CommandResult {
+    let response = response();
+    let ephemeral = response.ephemeral || response.announce_channel.is_some();
+    defer_with(interaction, state, content, ephemeral).await
+}
+
+/// Replaces the loading message of [`defer_announcement`] with `embeds` and
+/// posts them in the announce channel of the guild, both with `components`.
+pub(crate) async fn announce_embeds(
+    interaction: &InteractionCreate,
+    state: &State,
+    embeds: &[Embed],
+    components: &[Component],
+) -> CommandResult {
+    let message = state
+        .http
+        .interaction(interaction.application_id)
+        .update_response(&interaction.token)
+        .embeds(Some(embeds))
+        .components(Some(components))
+        .await?
+        .model()
+        .await?;
+    acknowledge(Acknowledgement::Replied);
+    remember(interaction, state, &message, MessageKind::Confirmation).await;
+    if let Some(channel_id) = response().announce_channel {
+        let message = state
+            .http
+            .create_message(channel_id)
+            .embeds(embeds)
+            .components(components)
+            .await?
+            .model()
+            .await?;
+        remember(interaction, state, &message, MessageKind::Confirmation).await;
+    }
+    Ok(())
+}
+
+async fn defer_with(
+    interaction: &InteractionCreate,
+    state: &State,
+    content: impl Into<String>,
+    ephemeral: bool,
+) -> CommandResult {
+    let embeds = vec![EmbedBuilder::new()
+        .description(content)
+        .color(colors::YELLOW)
+        .build()];
+    let mut flags = MessageFlags::LOADING;
+    if ephemeral {
+        flags |= MessageFlags::EPHEMERAL;
+    }
+    let interaction_response_data = InteractionResponseDataBuilder::new()
+        .flags(flags)
+        .embeds(embeds)
+        .build();
+    let response = InteractionResponse {
+        kind: InteractionResponseType::DeferredChannelMessageWithSource,
+        data: Some(interaction_response_data),
+    };
+    state
+        .http
+        .interaction(interaction.application_id)
+        .create_response(interaction.id, &interaction.token, &response)
+        .await?;
+    acknowledge(Acknowledgement::Deferred);
+    Ok(())
+}
+
+/// Runs `handler` for `interaction`, and tells the user if it fails.
+pub(crate) async fn run_handler(
+    interaction: &InteractionCreate,
+    state: &State,
+    handler: impl Future<Output = CommandResult>,
+) -> CommandResult {
+    let (result, acknowledgement) = ACKNOWLEDGEMENT
+        .scope(Cell::new(Acknowledgement::None), async {
+            let result = handler.await;
+            (result, ACKNOWLEDGEMENT.with(Cell::get))
+        })
+        .await;
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => report_error(interaction, state, acknowledgement, e.as_ref()).await,
+    }
+}
+
+/// Tells the user that their command failed, in the way that still fits the interaction.
+async fn report_error(
+    interaction: &InteractionCreate,
+    state: &State,
+    acknowledgement: Acknowledgement,
+    error: &(dyn Error + Send + Sync + 'static),
+) -> CommandResult {
+    let error_id = Uuid::new_v4().simple().to_string()[..8].to_string();
+    tracing::error!(%error_id, ?error, "command failed");
+
+    let locale = Locale::for_interaction(interaction, state).await;
+    let embeds = vec![EmbedBuilder::new()
+        .description(user_message(error, locale))
+        .color(colors::RED)
+        .footer(EmbedFooterBuilder::new(
+            locale.format("errors.error_id", &[("id", &error_id)]),
+        ))
+        .build()];
+    let client = state.http.interaction(interaction.application_id);
+    match acknowledgement {
+        Acknowledgement::None => {
+            let interaction_response_data = InteractionResponseDataBuilder::new()
+                .embeds(embeds)
+                .flags(MessageFlags::EPHEMERAL)
+                .build();
+            let response = InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(interaction_response_data),
+            };
+            client
+                .create_response(interaction.id, &interaction.token, &response)
+                .await?;
+        }
+        Acknowledgement::Deferred => {
+            // the loading message may be public, so it is replaced with a private follow-up
+            client.delete_response(&interaction.token).await?;
+            client
+                .create_followup(&interaction.token)
+                .embeds(&embeds)
+                .flags(MessageFlags::EPHEMERAL)
+                .await?;
+        }
+        Acknowledgement::Replied => {
+            client
+                .create_followup(&interaction.token)
+                .embeds(&embeds)
+                .flags(MessageFlags::EPHEMERAL)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::registry;
+    use std::collections::HashSet;
+
+    fn option(name: &str, value: CommandOptionValue) -> CommandDataOption {
+        CommandDataOption {
+            name: name.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_string_option() {
+        let data = [
+            option("query", CommandOptionValue::String("kraftwerk".to_string())),
+            option("count", CommandOptionValue::Integer(3)),
+        ];
+        let options = Options::new(&data);
+        assert_eq!(options.string("query").unwrap(), "kraftwerk");
+        assert!(matches!(
+            options.string("count"),
+            Err(OptionError::InvalidType(name)) if name == "count"
+        ));
+        assert!(matches!(
+            options.string("url"),
+            Err(OptionError::Missing(name)) if name == "url"
+        ));
+    }
+
+    #[test]
+    fn test_subcommand_options() {
+        let channel = Id::new(42);
+        let data = [option(
+            "announce",
+            CommandOptionValue::SubCommand(vec![option(
+                "channel",
+                CommandOptionValue::Channel(channel),
+            )]),
+        )];
+        let (name, options) = Options::new(&data).subcommand().unwrap();
+        assert_eq!(name, "announce");
+        assert_eq!(options.channel("channel").unwrap(), channel);
+        assert_eq!(optional(options.boolean("enabled")).unwrap(), None);
+        assert!(optional(options.boolean("channel")).is_err());
+    }
+
+    #[test]
+    fn test_response() {
+        let settings = |quiet, public, announce: Option<&str>| ResponseSettings {
+            quiet,
+            public,
+            announce_channel_id: announce.map(str::to_string),
+            cleanup: false,
+        };
+        let here = Some(Id::new(1));
+
+        let response = Response::new(settings(false, None, None), true, here);
+        assert_eq!(response, Response::default());
+        assert!(Response::new(settings(false, None, None), false, here).ephemeral);
+        assert!(!Response::new(settings(false, Some(true), None), false, here).ephemeral);
+        assert!(Response::new(settings(false, Some(false), None), true, here).ephemeral);
+        assert!(Response::new(settings(true, Some(true), None), true, here).ephemeral);
+
+        let response = Response::new(settings(false, None, Some("2")), true, here);
+        assert_eq!(response.announce_channel, Some(Id::new(2)));
+        let response = Response::new(settings(false, None, Some("1")), true, here);
+        assert_eq!(response.announce_channel, None);
+    }
+
+    #[test]
+    fn test_registry_definitions() {
+        let definitions = registry().definitions();
+        let names: HashSet<_> = definitions.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names.len(), definitions.len());
+        assert!(names.contains("play"));
+        let play = definitions.iter().find(|c| c.name == "play").unwrap();
+        assert_eq!(play.options[0].name, "query");
+        assert_eq!(play.options[0].required, Some(true));
+    }
+
+    #[test]
+    fn test_configurable_commands() {
+        let commands = registry().configurable_commands();
+        // Discord allows up to 25 choices for an option
+        assert!(commands.len() <= 25);
+        for name in ["play", "playlist", "lyrics", "blocklist"] {
+            assert!(commands.iter().any(|command| command == name));
+        }
+        assert!(!commands.iter().any(|command| command == "settings"));
+    }
+}
@@ -0,0 +1,183 @@
+use crate::colors;
+use crate::commands::error::user_message;
+use crate::commands::framework::{
+    announce_embeds, defer_announcement, resolved_attachment, CommandResult, FromOptions,
+    OptionError, Options, SlashCommand,
+};
+use crate::commands::join::join_channel;
+use crate::commands::play::enqueue_query;
+use crate::commands::CommandError;
+use crate::i18n::Locale;
+use crate::playlist_file::{self, Entry, Format};
+use crate::resolve::ResolveError;
+use crate::state::State;
+use anyhow::Context;
+use async_trait::async_trait;
+use twilight_model::{
+    application::command::{Command, CommandType},
+    gateway::payload::incoming::InteractionCreate,
+    id::{marker::AttachmentMarker, Id},
+};
+use twilight_util::builder::{
+    command::{AttachmentBuilder, CommandBuilder},
+    embed::EmbedBuilder,
+};
+
+/// Largest file that is imported, in bytes.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Most entries of a file that are imported.
+const MAX_ENTRIES: usize = 500;
+
+/// Failed entries that are listed in the summary.
+const SHOWN_FAILURES: usize = 10;
+
+pub(crate) struct Import;
+
+pub(crate) struct ImportOptions {
+    file: Id<AttachmentMarker>,
+}
+
+impl FromOptions for ImportOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        Ok(Self {
+            file: options.attachment("file")?,
+        })
+    }
+}
+
+/// Summarizes an import for the guild, listing the first failed entries.
+fn summary(added: usize, failures: &[(Entry, String)], locale: Locale) -> String {
+    let mut message = locale.format("import.added", &[("count", &added)]);
+    if failures.is_empty() {
+        return message;
+    }
+    message.push_str("\n\n");
+    message.push_str(&locale.format("import.failed", &[("count", &failures.len())]));
+    message.push('\n');
+    for (entry, reason) in failures.iter().take(SHOWN_FAILURES) {
+        let title = entry.title.as_deref().unwrap_or(&entry.url);
+        message.push_str(&format!("* [{}]({}): {}\n", title, entry.url, reason));
+    }
+    if failures.len() > SHOWN_FAILURES {
+        message.push_str(&locale.format(
+            "import.more_failures",
+            &[("count", &(failures.len() - SHOWN_FAILURES))],
+        ));
+    }
+    message
+}
+
+#[async_trait]
+impl SlashCommand for Import {
+    type Options = ImportOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "import",
+            "Add the tracks of an exported queue or playlist to the queue",
+            CommandType::ChatInput,
+        )
+        .option(
+            AttachmentBuilder::new("file", "JSON or M3U file, e.g. from /export").required(true),
+        )
+        .build()
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: ImportOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "import command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let (Some(guild_id), Some(user_id)) = (interaction.guild_id, interaction.author_id())
+        else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        let Some(attachment) = resolved_attachment(&interaction, options.file) else {
+            return Err(OptionError::Missing("file".to_string()).into());
+        };
+        if attachment.size > MAX_FILE_SIZE {
+            return Err(CommandError::ImportTooLarge.into());
+        }
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        let content = locale.format("import.importing", &[("file", &attachment.filename)]);
+        defer_announcement(&interaction, &state, content).await?;
+
+        let content = state
+            .client
+            .get(&attachment.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let content = String::from_utf8_lossy(&content);
+        let format = Format::detect(&attachment.filename, &content);
+        let entries = playlist_file::parse(&content, format).map_err(|e| {
+            tracing::debug!(?e, "could not parse {}", attachment.filename);
+            CommandError::InvalidImportFile
+        })?;
+        if entries.len() > MAX_ENTRIES {
+            return Err(CommandError::ImportTooLarge.into());
+        }
+
+        join_channel(state.clone(), guild_id, user_id).await?;
+        if let Some(call_lock) = state.songbird.get(guild_id) {
+            let call = call_lock.lock().await;
+            call.queue().resume().context("Could not resume playing")?;
+        }
+
+        let mut added = 0;
+        let mut failures = Vec::new();
+        for entry in entries {
+            match enqueue_query(&interaction, &state, guild_id, entry.url.clone()).await {
+                Ok(count) => added += count,
+                Err(e) => {
+                    if e.downcast_ref::<ResolveError>()
+                        .is_some_and(ResolveError::is_fatal)
+                    {
+                        return Err(e);
+                    }
+                    tracing::debug!(?e, "could not import {}", entry.url);
+                    failures.push((entry, user_message(e.as_ref(), locale)));
+                }
+            }
+        }
+
+        let embeds = vec![EmbedBuilder::new()
+            .description(summary(added, &failures, locale))
+            .color(colors::BLURPLE)
+            .build()];
+        announce_embeds(&interaction, &state, &embeds, &[]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(summary(3, &[], Locale::En), "Added 3 tracks to the queue.");
+
+        let failures: Vec<_> = (0..SHOWN_FAILURES + 1)
+            .map(|i| {
+                let entry = Entry::new(format!("https://example.com/{i}"), None, None);
+                (entry, "not found".to_string())
+            })
+            .collect();
+        let message = summary(0, &failures, Locale::En);
+        assert!(message.contains("Could not add 11 entries:"));
+        assert!(message.contains("* [https://example.com/0](https://example.com/0): not found\n"));
+        assert!(!message.contains("https://example.com/10"));
+        assert!(message.ends_with("…and 1 more."));
+    }
+}
@@ -1,17 +1,22 @@
+use crate::commands::autoplay;
+use crate::commands::framework::{confirm, CommandResult, SlashCommand};
+use crate::commands::CommandError;
+use crate::filter;
+use crate::i18n::Locale;
 use crate::state::State;
 use anyhow::Context;
+use async_trait::async_trait;
 use std::error::Error;
 use tracing::debug;
 use twilight_model::{
-    channel::message::MessageFlags,
+    application::command::{Command, CommandType},
     gateway::payload::incoming::InteractionCreate,
-    http::interaction::{InteractionResponse, InteractionResponseType},
     id::{
         marker::{GuildMarker, UserMarker},
         Id,
     },
 };
-use twilight_util::builder::InteractionResponseDataBuilder;
+use twilight_util::builder::command::CommandBuilder;
 
 pub(crate) async fn join_channel(
     state: State,
@@ -23,9 +28,11 @@ pub(crate) async fn join_channel(
     let channel_id = state
         .cache
         .voice_state(user_id, guild_id)
-        .context("Could not get voice state for user")?
+        .ok_or(CommandError::NotInVoice)?
         .channel_id();
 
+    let new_call = state.songbird.get(guild_id.cast()).is_none();
+
     // join the voice channel
     state
         .songbird
@@ -37,49 +44,56 @@ pub(crate) async fn join_channel(
     if let Some(call_lock) = state.songbird.get(guild_id.cast()) {
         let mut call = call_lock.lock().await;
         call.deafen(true).await.context("Could not deafen")?;
+        // calls outlive leaving the channel, so their events are only added once
+        if new_call {
+            autoplay::register(&mut call, guild_id, state.clone());
+        }
     }
 
-    // create guild config
-    state.guild_settings.entry(guild_id).or_default();
+    // create guild config, with the filters the guild chose before
+    let filters = filter::saved_filters(&state, guild_id).await;
+    state.guild_settings.entry(guild_id).or_default().filters = filters;
 
     Ok(())
 }
 
-pub(crate) async fn join(
-    interaction: Box<InteractionCreate>,
-    state: State,
-) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-    debug!(
-        "join command in guild {:?} in channel {:?} by {:?}",
-        interaction.guild_id,
-        interaction.channel,
-        interaction.author(),
-    );
+pub(crate) struct Join;
 
-    let Some(guild_id) = interaction.guild_id else {
-        return Ok(());
-    };
-    let Some(author_id) = interaction.author_id() else {
-        return Ok(());
-    };
+#[async_trait]
+impl SlashCommand for Join {
+    type Options = ();
 
-    join_channel(state.clone(), guild_id, author_id).await?;
+    fn definition(&self) -> Command {
+        CommandBuilder::new("join", "Join the channel", CommandType::ChatInput).build()
+    }
 
-    let interaction_response_data = InteractionResponseDataBuilder::new()
-        .content("Bin da Brudi!")
-        .flags(MessageFlags::EPHEMERAL)
-        .build();
+    fn public_by_default(&self) -> bool {
+        false
+    }
 
-    let response = InteractionResponse {
-        kind: InteractionResponseType::ChannelMessageWithSource,
-        data: Some(interaction_response_data),
-    };
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        _options: (),
+    ) -> CommandResult {
+        debug!(
+            "join command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
 
-    state
-        .http
-        .interaction(interaction.application_id)
-        .create_response(interaction.id, &interaction.token, &response)
-        .await?;
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        let Some(author_id) = interaction.author_id() else {
+            return Err(CommandError::NotInGuild.into());
+        };
 
-    Ok(())
+        join_channel(state.clone(), guild_id, author_id).await?;
+
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        confirm(&interaction, &state, locale.text("join.joined")).await
+    }
 }
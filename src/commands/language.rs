@@ -0,0 +1,104 @@
+use crate::commands::framework::{
+    confirm, CommandResult, FromOptions, OptionError, Options, SlashCommand,
+};
+use crate::commands::CommandError;
+use crate::db;
+use crate::i18n::Locale;
+use crate::state::State;
+use async_trait::async_trait;
+use twilight_model::{
+    application::command::{Command, CommandType},
+    gateway::payload::incoming::InteractionCreate,
+    guild::Permissions,
+};
+use twilight_util::builder::command::{CommandBuilder, StringBuilder};
+
+const AUTOMATIC: &str = "auto";
+
+pub(crate) struct Language;
+
+pub(crate) struct LanguageOptions {
+    /// `None` to follow the language of the server and the user again.
+    locale: Option<Locale>,
+}
+
+impl FromOptions for LanguageOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        let code = options.string("language")?;
+        if code == AUTOMATIC {
+            return Ok(Self { locale: None });
+        }
+        match Locale::from_code(&code) {
+            Some(locale) => Ok(Self {
+                locale: Some(locale),
+            }),
+            None => Err(OptionError::InvalidType("language".to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl SlashCommand for Language {
+    type Options = LanguageOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "language",
+            "Set the language of the bot in this server",
+            CommandType::ChatInput,
+        )
+        .option(
+            StringBuilder::new(
+                "language",
+                "Language, or automatic by server and user language",
+            )
+            .required(true)
+            .choices([
+                ("Automatic", AUTOMATIC),
+                ("English", Locale::En.code()),
+                ("Deutsch", Locale::De.code()),
+            ]),
+        )
+        .build()
+    }
+
+    fn required_permissions(&self) -> Option<Permissions> {
+        Some(Permissions::MANAGE_GUILD)
+    }
+
+    fn public_by_default(&self) -> bool {
+        false
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: LanguageOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "language command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
+
+        db::guild::set_guild_locale(
+            &state.pool,
+            db::track::Guild::new(guild_id.to_string()),
+            options.locale.map(|locale| locale.code().to_string()),
+        )
+        .await?;
+
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        let message = match options.locale {
+            Some(_) => locale.text("language.set"),
+            None => locale.text("language.reset"),
+        };
+        confirm(&interaction, &state, message).await
+    }
+}
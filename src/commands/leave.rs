@@ -1,10 +1,16 @@
+use crate::commands::framework::{announce, CommandResult, SlashCommand};
+use crate::commands::CommandError;
+use crate::i18n::Locale;
 use crate::state::{State, StateRef};
 use anyhow::Context;
+use async_trait::async_trait;
 use std::{error::Error, sync::Arc};
 use twilight_model::{
+    application::command::{Command, CommandType},
     gateway::payload::incoming::InteractionCreate,
     id::{marker::GuildMarker, Id},
 };
+use twilight_util::builder::command::CommandBuilder;
 
 pub(crate) async fn leave_if_alone(
     guild_id: Id<GuildMarker>,
@@ -52,22 +58,36 @@ pub(crate) async fn leave_channel(
     Ok(())
 }
 
-pub(crate) async fn leave(
-    interaction: Box<InteractionCreate>,
-    state: State,
-) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-    tracing::debug!(
-        "leave command n guild {:?} in channel {:?} by {:?}",
-        interaction.guild_id,
-        interaction.channel,
-        interaction.author(),
-    );
+pub(crate) struct Leave;
 
-    let Some(guild_id) = interaction.guild_id else {
-        return Ok(());
-    };
+#[async_trait]
+impl SlashCommand for Leave {
+    type Options = ();
 
-    leave_channel(guild_id, Arc::clone(&state)).await?;
+    fn definition(&self) -> Command {
+        CommandBuilder::new("leave", "Leave the channel", CommandType::ChatInput).build()
+    }
 
-    Ok(())
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        _options: (),
+    ) -> CommandResult {
+        tracing::debug!(
+            "leave command n guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
+
+        leave_channel(guild_id, Arc::clone(&state)).await?;
+
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        announce(&interaction, &state, locale.text("leave.left")).await
+    }
 }
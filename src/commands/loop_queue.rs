@@ -1,78 +1,78 @@
+use crate::commands::crossfade;
+use crate::commands::framework::{announce, CommandResult, SlashCommand};
+use crate::commands::play;
+use crate::commands::CommandError;
+use crate::i18n::Locale;
 use crate::metadata::Metadata;
+use crate::section;
 use crate::state::{State, StateRef};
 use async_trait::async_trait;
-use songbird::tracks::Track;
 use songbird::{Event, EventContext, EventHandler, TrackEvent};
-use std::ops::Sub;
-use std::time::Duration;
-use std::{error::Error, sync::Arc};
+use std::sync::Arc;
 use twilight_model::{
+    application::command::{Command, CommandType},
     gateway::payload::incoming::InteractionCreate,
-    http::interaction::{InteractionResponse, InteractionResponseType},
     id::{marker::GuildMarker, Id},
 };
-use twilight_util::builder::InteractionResponseDataBuilder;
+use twilight_util::builder::command::CommandBuilder;
 
-pub(crate) async fn loop_queue(
-    interaction: Box<InteractionCreate>,
-    state: State,
-) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-    tracing::debug!(
-        "loop command in guild {:?} in channel {:?} by {:?}",
-        interaction.guild_id,
-        interaction.channel,
-        interaction.author(),
-    );
+pub(crate) struct Loop;
 
-    let guild_id: Id<GuildMarker> = if let Some(guild_id) = interaction.guild_id {
-        guild_id
-    } else {
-        return Ok(());
-    };
-
-    state.guild_settings.entry(guild_id).and_modify(|settings| {
-        settings.loop_queue = !settings.loop_queue;
-    });
+#[async_trait]
+impl SlashCommand for Loop {
+    type Options = ();
 
-    let looping = state
-        .guild_settings
-        .get(&guild_id)
-        .expect("Cannot get loop state")
-        .loop_queue;
+    fn definition(&self) -> Command {
+        CommandBuilder::new("loop", "Loop queue", CommandType::ChatInput).build()
+    }
 
-    if let Some(call_lock) = state.songbird.get(guild_id) {
-        let mut call = call_lock.lock().await;
-        call.add_global_event(
-            Event::Track(TrackEvent::End),
-            TrackEndNotifier {
-                guild_id,
-                state: Arc::clone(&state),
-            },
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        _options: (),
+    ) -> CommandResult {
+        tracing::debug!(
+            "loop command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
         );
-    }
 
-    let message = if looping {
-        "I'm now looping the current queue!".to_string()
-    } else {
-        "I'm not looping anymore!".to_string()
-    };
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
 
-    let interaction_response_data = InteractionResponseDataBuilder::new()
-        .content(message)
-        .build();
+        state.guild_settings.entry(guild_id).and_modify(|settings| {
+            settings.loop_queue = !settings.loop_queue;
+        });
 
-    let response = InteractionResponse {
-        kind: InteractionResponseType::ChannelMessageWithSource,
-        data: Some(interaction_response_data),
-    };
+        let looping = state
+            .guild_settings
+            .get(&guild_id)
+            .expect("Cannot get loop state")
+            .loop_queue;
 
-    state
-        .http
-        .interaction(interaction.application_id)
-        .create_response(interaction.id, &interaction.token, &response)
-        .await?;
+        if let Some(call_lock) = state.songbird.get(guild_id) {
+            let mut call = call_lock.lock().await;
+            call.add_global_event(
+                Event::Track(TrackEvent::End),
+                TrackEndNotifier {
+                    guild_id,
+                    state: Arc::clone(&state),
+                },
+            );
+        }
+
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        let message = if looping {
+            locale.text("loop.enabled")
+        } else {
+            locale.text("loop.disabled")
+        };
 
-    Ok(())
+        announce(&interaction, &state, message).await
+    }
 }
 
 struct TrackEndNotifier {
@@ -97,22 +97,20 @@ impl EventHandler for TrackEndNotifier {
         };
         let (_, track_handle) = track_list.first()?;
         if let Some(call_lock) = self.state.songbird.get(self.guild_id) {
-            let mut call = call_lock.lock().await;
-
             // get metadata from finished track
             let old_metadata = track_handle.data::<Metadata>();
             // enqueue track
-            let track = Track::new_with_data(old_metadata.src.clone().into(), old_metadata.clone());
-            let _handle = call.enqueue_with_preload(
+            let track = play::track(&self.state, self.guild_id, (*old_metadata).clone()).await;
+            let mut call = call_lock.lock().await;
+            let handle = call.enqueue_with_preload(
                 track,
-                old_metadata.duration.map(|duration| -> Duration {
-                    if duration.as_secs() > 5 {
-                        duration.sub(Duration::from_secs(5))
-                    } else {
-                        duration
-                    }
-                }),
+                self.state
+                    .config
+                    .queue
+                    .preload_position(old_metadata.duration),
             );
+            crossfade::schedule(&handle, &self.state, self.guild_id, old_metadata.duration);
+            section::schedule_end(&handle, &self.state, self.guild_id, old_metadata.section);
         }
         None
     }
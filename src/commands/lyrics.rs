@@ -0,0 +1,334 @@
+use crate::colors;
+use crate::commands::chapter::track_time;
+use crate::commands::framework::{
+    defer, optional, respond_embeds, update_response, CommandResult, FromOptions, OptionError,
+    Options, SlashCommand,
+};
+use crate::commands::CommandError;
+use crate::i18n::Locale;
+use crate::lyrics::lrc::SyncedLyrics;
+use crate::lyrics::{Lyrics, LyricsQuery};
+use crate::metadata::Metadata;
+use crate::state::State;
+use async_trait::async_trait;
+use songbird::tracks::TrackHandle;
+use std::time::{Duration, Instant};
+use twilight_model::{
+    application::command::{Command, CommandType},
+    channel::message::Embed,
+    gateway::payload::incoming::InteractionCreate,
+};
+use twilight_util::builder::command::{BooleanBuilder, CommandBuilder};
+use twilight_util::builder::embed::{EmbedBuilder, EmbedFooterBuilder};
+
+/// Characters per embed, which keeps a message of [`PAGES_PER_MESSAGE`] embeds below
+/// the limit of Discord.
+const PAGE_LENGTH: usize = 1800;
+
+const PAGES_PER_MESSAGE: usize = 3;
+
+/// Lines of synced lyrics that are shown before and after the one being sung.
+const LINES_BEFORE: usize = 2;
+const LINES_AFTER: usize = 4;
+
+/// How long synced lyrics follow the track, as the response to an interaction can only be
+/// edited for 15 minutes.
+const SYNC_DURATION: Duration = Duration::from_secs(14 * 60);
+
+/// How often the position is checked at least, to notice seeks.
+const SYNC_INTERVAL: Duration = Duration::from_secs(2);
+
+pub(crate) struct LyricsCommand;
+
+pub(crate) struct LyricsOptions {
+    synced: bool,
+}
+
+impl FromOptions for LyricsOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        Ok(Self {
+            synced: optional(options.boolean("synced"))?.unwrap_or_default(),
+        })
+    }
+}
+
+/// Splits `text` into pages of at most `max` characters, between lines where possible.
+fn pages(text: &str, max: usize) -> Vec<String> {
+    let mut pages = Vec::new();
+    let mut page = String::new();
+    let mut length = 0;
+    for line in text.trim().lines() {
+        let mut line = line.trim_end();
+        loop {
+            let line_length = line.chars().count();
+            if length > 0 && length + 1 + line_length > max {
+                pages.push(std::mem::take(&mut page));
+                length = 0;
+            }
+            if line_length <= max {
+                if length > 0 {
+                    page.push('\n');
+                    length += 1;
+                }
+                page.push_str(line);
+                length += line_length;
+                break;
+            }
+            // lines longer than a page are cut
+            let (index, _) = line
+                .char_indices()
+                .nth(max)
+                .expect("line is longer than max");
+            pages.push(line[..index].to_string());
+            line = &line[index..];
+        }
+    }
+    if !page.is_empty() {
+        pages.push(page);
+    }
+    pages
+}
+
+/// Embeds showing `lyrics` of the track titled `title`, grouped into messages.
+fn build_embeds(title: &str, lyrics: &Lyrics, locale: Locale) -> Vec<Vec<Embed>> {
+    let pages = pages(&lyrics.text, PAGE_LENGTH);
+    let count = pages.len();
+    let embeds: Vec<Embed> = pages
+        .into_iter()
+        .enumerate()
+        .map(|(index, page)| {
+            let footer = locale.format(
+                "lyrics.footer",
+                &[
+                    ("source", &lyrics.source),
+                    ("page", &(index + 1)),
+                    ("count", &count),
+                ],
+            );
+            let mut embed = EmbedBuilder::new()
+                .description(page)
+                .color(colors::BLURPLE)
+                .footer(EmbedFooterBuilder::new(footer));
+            if index == 0 {
+                embed = embed.title(title);
+            }
+            embed.build()
+        })
+        .collect();
+    embeds
+        .chunks(PAGES_PER_MESSAGE)
+        .map(|chunk| chunk.to_vec())
+        .collect()
+}
+
+/// Embed showing the lines of `lyrics` around the one at `current`, which is highlighted.
+fn build_synced_embed(
+    title: &str,
+    lyrics: &SyncedLyrics,
+    current: Option<usize>,
+    source: &str,
+    locale: Locale,
+) -> Embed {
+    let start = current.map_or(0, |index| index.saturating_sub(LINES_BEFORE));
+    let end = current.map_or(0, |index| index + 1) + LINES_AFTER;
+    let mut description = String::new();
+    for (index, (_, line)) in lyrics.lines.iter().enumerate().take(end).skip(start) {
+        // empty lines are instrumental parts
+        let line = if line.is_empty() { "♪" } else { line };
+        if Some(index) == current {
+            description.push_str(&format!("**{line}**\n"));
+        } else {
+            description.push_str(&format!("{line}\n"));
+        }
+    }
+    EmbedBuilder::new()
+        .title(title)
+        .description(description)
+        .color(colors::BLURPLE)
+        .footer(EmbedFooterBuilder::new(
+            locale.format("lyrics.synced", &[("source", &source)]),
+        ))
+        .build()
+}
+
+/// Edits the response to `interaction` to show the line of `lyrics` that `handle` plays,
+/// until the track ends or the response can no longer be edited.
+async fn follow(
+    interaction: Box<InteractionCreate>,
+    state: State,
+    handle: TrackHandle,
+    title: String,
+    lyrics: Lyrics,
+    locale: Locale,
+) {
+    let Some(synced) = &lyrics.synced else {
+        return;
+    };
+    let guild_id = interaction.guild_id;
+    let section = handle.data::<Metadata>().section;
+    let started = Instant::now();
+    let mut shown = None;
+    while started.elapsed() < SYNC_DURATION {
+        let Ok(info) = handle.get_info().await else {
+            break;
+        };
+        if info.playing.is_done() {
+            break;
+        }
+        let filters = guild_id
+            .and_then(|guild_id| state.guild_settings.get(&guild_id))
+            .map(|settings| settings.filters)
+            .unwrap_or_default();
+        let time = track_time(info.position, filters, section);
+        let current = synced.line_at(time);
+        if shown != Some(current) {
+            let embeds = [build_synced_embed(
+                &title,
+                synced,
+                current,
+                &lyrics.source,
+                locale,
+            )];
+            let updated = state
+                .http
+                .interaction(interaction.application_id)
+                .update_response(&interaction.token)
+                .embeds(Some(&embeds))
+                .await;
+            if let Err(e) = updated {
+                tracing::debug!(?e, "could not update synced lyrics");
+                break;
+            }
+            shown = Some(current);
+        }
+        let wait = synced.until_next(time).map_or(SYNC_INTERVAL, |until| {
+            until.div_f64(filters.speed).min(SYNC_INTERVAL)
+        });
+        tokio::time::sleep(wait).await;
+    }
+}
+
+#[async_trait]
+impl SlashCommand for LyricsCommand {
+    type Options = LyricsOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "lyrics",
+            "Show the lyrics of the current track",
+            CommandType::ChatInput,
+        )
+        .option(BooleanBuilder::new(
+            "synced",
+            "Show the line that is sung, if the lyrics are synced",
+        ))
+        .build()
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: LyricsOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "lyrics command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        let call_lock = state
+            .songbird
+            .get(guild_id)
+            .ok_or(CommandError::NothingPlaying)?;
+        let handle = call_lock
+            .lock()
+            .await
+            .queue()
+            .current()
+            .ok_or(CommandError::NothingPlaying)?;
+        let metadata = handle.data::<Metadata>();
+
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        let title = metadata
+            .title
+            .clone()
+            .unwrap_or_else(|| locale.text("unknown").to_string());
+        let content = locale.format("lyrics.searching", &[("title", &title)]);
+        defer(&interaction, &state, content).await?;
+
+        let lyrics = match LyricsQuery::new(&metadata) {
+            Some(query) => state.lyrics.find(&query).await,
+            None => None,
+        };
+        let Some(lyrics) = lyrics else {
+            let message = locale.format("lyrics.not_found", &[("title", &title)]);
+            return update_response(&interaction, &state, message).await;
+        };
+        if options.synced && lyrics.synced.is_some() {
+            tokio::spawn(follow(interaction, state, handle, title, lyrics, locale));
+            return Ok(());
+        }
+        respond_embeds(&interaction, &state, &build_embeds(&title, &lyrics, locale)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages() {
+        assert_eq!(
+            pages("She's a model\nand she's looking good\n", 40),
+            ["She's a model\nand she's looking good"]
+        );
+        assert_eq!(
+            pages("She's a model\nand she's looking good", 30),
+            ["She's a model", "and she's looking good"]
+        );
+        assert_eq!(pages("Computer Love", 8), ["Computer", " Love"]);
+        assert!(pages("\n\n", 10).is_empty());
+    }
+
+    #[test]
+    fn test_build_embeds() {
+        let lyrics = Lyrics::new(
+            "She's a model and she's looking good\n".repeat(200),
+            "LRCLIB",
+        );
+        let messages = build_embeds("The Model", &lyrics, Locale::En);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].len(), PAGES_PER_MESSAGE);
+        assert_eq!(messages[0][0].title.as_deref(), Some("The Model"));
+        assert_eq!(messages[1][0].title, None);
+        let footer = &messages[1][0].footer.as_ref().unwrap().text;
+        assert_eq!(footer, "Lyrics from LRCLIB · page 4/5");
+    }
+
+    #[test]
+    fn test_build_synced_embed() {
+        let text = (1..=10)
+            .map(|n| format!("[00:{n:02}.00]line {n}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let lyrics = SyncedLyrics::parse(&format!("{text}\n[00:20.00]")).unwrap();
+        let description = |current| {
+            build_synced_embed("The Model", &lyrics, current, "LRC", Locale::En)
+                .description
+                .unwrap()
+        };
+        assert_eq!(
+            description(Some(4)),
+            "line 3\nline 4\n**line 5**\nline 6\nline 7\nline 8\nline 9\n"
+        );
+        assert_eq!(description(None), "line 1\nline 2\nline 3\nline 4\n");
+        assert_eq!(description(Some(10)), "line 9\nline 10\n**♪**\n");
+        let embed = build_synced_embed("The Model", &lyrics, None, "LRC", Locale::En);
+        assert_eq!(embed.footer.unwrap().text, "Synced lyrics from LRC");
+    }
+}
@@ -1,49 +1,117 @@
+mod autoplay;
+use autoplay::Autoplay;
+
+mod blocklist;
+use blocklist::Blocklist;
+
+pub(crate) mod chapter;
+use chapter::Chapters;
+
+mod crossfade;
+use crossfade::Crossfade;
+
+mod error;
+pub(crate) use error::CommandError;
+
+mod export;
+use export::Export;
+
+mod favorites;
+use favorites::Favorites;
+pub(crate) use favorites::{like, LIKE_PREFIX};
+
+mod filter;
+use filter::Filter;
+
+mod framework;
+pub(crate) use framework::{run_handler, Registry};
+
+mod import;
+use import::Import;
+
 mod join;
-pub(crate) use join::join;
+use join::Join;
+
+mod language;
+use language::Language;
 
 mod leave;
-pub(crate) use leave::leave;
 pub(crate) use leave::leave_if_alone;
+use leave::Leave;
 
 mod pause;
-pub(crate) use pause::pause;
+use pause::Pause;
 
 mod skip;
-pub(crate) use skip::skip;
+use skip::Skip;
+
+mod lyrics;
+use lyrics::LyricsCommand;
 
 mod loop_queue;
-pub(crate) use loop_queue::loop_queue;
+use loop_queue::Loop;
 
 mod play;
-pub(crate) use play::play;
+use play::Play;
+
+mod playlist;
+use playlist::Playlist;
+
+mod purge;
+use purge::Purge;
 
 pub(crate) mod queue;
-pub(crate) use queue::queue;
+use queue::Queue;
 
 mod resume;
-pub(crate) use resume::resume;
+use resume::Resume;
+
+mod settings;
+use settings::Settings;
 
 mod stop;
-pub(crate) use stop::stop;
+use stop::Stop;
+
+mod trim;
+use trim::Trim;
 
-mod delete;
-pub(crate) use delete::delete;
+mod volume;
+use volume::Volume;
 
-use twilight_model::application::command::CommandType;
-use twilight_util::builder::command::{CommandBuilder, StringBuilder};
+mod registration;
+pub(crate) use registration::register_commands;
+
+/// Returns the registry of all slash commands.
+pub(crate) fn registry() -> Registry {
+    let registry = Registry::new()
+        .command(Join)
+        .command(Leave)
+        .command(Loop)
+        .command(Skip)
+        .command(Queue)
+        .command(Stop)
+        .command(Pause)
+        .command(Resume)
+        .command(Play)
+        .command(Language)
+        .command(Purge)
+        .command(Playlist)
+        .command(Favorites)
+        .command(Export)
+        .command(Import)
+        .command(Autoplay)
+        .command(Filter)
+        .command(Crossfade)
+        .command(Volume)
+        .command(Trim)
+        .command(Chapters)
+        .command(LyricsCommand)
+        .command(Blocklist);
+    // the settings can configure every command registered before them
+    let commands = registry.configurable_commands();
+    registry.command(Settings::new(commands))
+}
 
 pub(crate) fn get_chat_commands() -> Vec<twilight_model::application::command::Command> {
-    vec![
-        CommandBuilder::new("join", "Join the channel", CommandType::ChatInput).build(),
-        CommandBuilder::new("leave", "Leave the channel", CommandType::ChatInput).build(),
-        CommandBuilder::new("loop", "Loop queue", CommandType::ChatInput).build(),
-        CommandBuilder::new("skip", "Skip track", CommandType::ChatInput).build(),
-        CommandBuilder::new("queue", "Print track queue", CommandType::ChatInput).build(),
-        CommandBuilder::new("stop", "Stop playing", CommandType::ChatInput).build(),
-        CommandBuilder::new("pause", "Pause playing", CommandType::ChatInput).build(),
-        CommandBuilder::new("resume", "Resume playing", CommandType::ChatInput).build(),
-        CommandBuilder::new("play", "Add a song to the queue", CommandType::ChatInput)
-            .option(StringBuilder::new("query", "URL of a song").required(true))
-            .build(),
-    ]
+    registry().definitions()
 }
@@ -1,47 +1,53 @@
+use crate::commands::framework::{announce, CommandResult, SlashCommand};
+use crate::commands::CommandError;
+use crate::i18n::Locale;
 use crate::state::State;
-use std::error::Error;
+use async_trait::async_trait;
 use twilight_model::{
-    channel::message::MessageFlags,
+    application::command::{Command, CommandType},
     gateway::payload::incoming::InteractionCreate,
-    http::interaction::{InteractionResponse, InteractionResponseType},
 };
-use twilight_util::builder::InteractionResponseDataBuilder;
+use twilight_util::builder::command::CommandBuilder;
 
-pub(crate) async fn pause(
-    interaction: Box<InteractionCreate>,
-    state: State,
-) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-    tracing::debug!(
-        "pause command in guild {:?} in channel {:?} by {:?}",
-        interaction.guild_id,
-        interaction.channel,
-        interaction.author(),
-    );
+pub(crate) struct Pause;
 
-    let Some(guild_id) = interaction.guild_id else {
-        return Ok(());
-    };
+#[async_trait]
+impl SlashCommand for Pause {
+    type Options = ();
 
-    if let Some(call_lock) = state.songbird.get(guild_id) {
-        let call = call_lock.lock().await;
-        call.queue().pause()?;
+    fn definition(&self) -> Command {
+        CommandBuilder::new("pause", "Pause playing", CommandType::ChatInput).build()
     }
 
-    let interaction_response_data = InteractionResponseDataBuilder::new()
-        .content("Paused the track")
-        .flags(MessageFlags::EPHEMERAL)
-        .build();
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        _options: (),
+    ) -> CommandResult {
+        tracing::debug!(
+            "pause command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
 
-    let response = InteractionResponse {
-        kind: InteractionResponseType::ChannelMessageWithSource,
-        data: Some(interaction_response_data),
-    };
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
 
-    state
-        .http
-        .interaction(interaction.application_id)
-        .create_response(interaction.id, &interaction.token, &response)
-        .await?;
+        let call_lock = state
+            .songbird
+            .get(guild_id)
+            .ok_or(CommandError::NothingPlaying)?;
+        let call = call_lock.lock().await;
+        if call.queue().is_empty() {
+            return Err(CommandError::NothingPlaying.into());
+        }
+        call.queue().pause()?;
+        drop(call);
 
-    Ok(())
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        announce(&interaction, &state, locale.text("pause.paused")).await
+    }
 }
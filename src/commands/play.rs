@@ -1,32 +1,46 @@
+use crate::blocklist;
+use crate::commands::crossfade;
+use crate::commands::favorites::like_button;
+use crate::commands::framework::{
+    announce_embeds, defer_announcement, optional, CommandResult, FromOptions, OptionError,
+    Options, SlashCommand,
+};
 use crate::commands::join::join_channel;
+use crate::commands::volume;
+use crate::commands::CommandError;
+use crate::i18n::Locale;
+use crate::limits::{self, Admitted};
 use crate::metadata::Metadata;
+use crate::resolve::ResolveError;
+use crate::section::{self, Section};
 use crate::state::State;
-use crate::{colors, db};
+use crate::utils::{format_duration, parse_timestamp};
+use crate::ytdlp::YouTubeTrack;
+use crate::{colors, db, filter};
 
 use anyhow::Context;
-use serde::{Deserialize, Serialize};
-use songbird::input::{Compose, YoutubeDl};
+use async_trait::async_trait;
 use songbird::tracks::Track;
+use std::error::Error;
 use std::sync::Arc;
-use std::{error::Error, time::Duration};
-use std::{
-    io::{BufRead, BufReader},
-    ops::Sub,
-};
-use tokio::process::Command;
+use std::time::Duration;
 use tracing::debug;
+use twilight_model::application::command::{Command, CommandType};
+use twilight_model::channel::message::component::ActionRow;
 use twilight_model::channel::message::embed::{
     EmbedAuthor, EmbedField, EmbedFooter, EmbedThumbnail,
 };
-use twilight_model::channel::message::{Embed, MessageFlags};
+use twilight_model::channel::message::{Component, Embed};
 use twilight_model::gateway::payload::incoming::InteractionCreate;
-use twilight_model::http::interaction::{InteractionResponse, InteractionResponseType};
+use twilight_model::id::{marker::GuildMarker, Id};
+use twilight_util::builder::command::{CommandBuilder, StringBuilder};
 use twilight_util::builder::embed::EmbedBuilder;
-use twilight_util::builder::InteractionResponseDataBuilder;
 use url::Url;
 
 #[derive(Debug)]
 struct TrackType {
+    /// Id in the database, `None` if the track could not be saved.
+    id: Option<i64>,
     url: String,
     title: Option<String>,
     duration_string: String,
@@ -34,68 +48,34 @@ struct TrackType {
     thumbnail: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct YouTubeTrack {
-    url: Option<String>,
-    original_url: Option<String>,
-    title: String,
-    channel: String,
-    playlist: Option<String>,
-    playlist_id: Option<String>,
-    duration_string: String,
-    thumbnail: Option<String>,
-}
-
 fn build_playlist_url(playlist_id: &str) -> String {
     format!("https://www.youtube.com/playlist?list={playlist_id}")
 }
 
-async fn get_tracks(
-    url: String,
-) -> Result<Vec<YouTubeTrack>, Box<dyn Error + Send + Sync + 'static>> {
-    let output = Command::new("yt-dlp")
-        .args(vec![&url, "--flat-playlist", "-j"])
-        .output()
-        .await?;
-
-    tracing::info!(
-        "yt-dlp output: {:?}",
-        String::from_utf8_lossy(&output.stdout)
-    );
-
-    let reader = BufReader::new(output.stdout.as_slice());
-    let tracks: Vec<YouTubeTrack> = reader
-        .lines()
-        .map_while(Result::ok)
-        .flat_map(|line| serde_json::from_str(&line))
-        .collect();
-    tracing::info!("yt-dlp tracks: {:?}", tracks);
-
-    if tracks.is_empty() {
-        if let Ok(stderr) = String::from_utf8(output.stderr) {
-            if stderr.contains("This video is only available to Music Premium members") {
-                return Err("This video is only available to Music Premium members".into());
-            }
-            if stderr.contains("YouTube said: The playlist does not exist.") {
-                return Err("YouTube said: The playlist does not exist.".into());
-            }
-        }
-        return Err("No tracks found".into());
-    }
-    tracing::info!("tracks: {:?}", tracks);
-    Ok(tracks)
+fn adding_playlist_message(first_track: &YouTubeTrack, locale: Locale) -> String {
+    let unknown = locale.text("unknown");
+    locale.format(
+        "play.adding_playlist",
+        &[
+            ("title", &first_track.playlist.as_deref().unwrap_or(unknown)),
+            (
+                "url",
+                &build_playlist_url(first_track.playlist_id.as_deref().unwrap_or(unknown)),
+            ),
+        ],
+    )
 }
 
 async fn persistence(
     interaction: &InteractionCreate,
     track: &YouTubeTrack,
     state: State,
-) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+) -> Result<Option<i64>, Box<dyn Error + Send + Sync + 'static>> {
     let Some(guild_id) = interaction.guild_id else {
-        return Ok(());
+        return Ok(None);
     };
     let Some(user_id) = interaction.author_id() else {
-        return Ok(());
+        return Ok(None);
     };
     let url = track
         .original_url
@@ -138,10 +118,10 @@ async fn persistence(
     )
     .await
     .context("failed to insert query")?;
-    Ok(())
+    Ok(Some(track_id))
 }
 
-fn build_single_track_added_embeds(tracks_added: &[TrackType]) -> Vec<Embed> {
+fn build_single_track_added_embeds(tracks_added: &[TrackType], locale: Locale) -> Vec<Embed> {
     let track = tracks_added.first().unwrap();
 
     let host = if let Ok(host) = Url::parse(&track.url) {
@@ -157,7 +137,7 @@ fn build_single_track_added_embeds(tracks_added: &[TrackType]) -> Vec<Embed> {
 
     let footer = match host {
         Some(host) => EmbedFooter {
-            text: format!("Streaming from {host}"),
+            text: locale.format("play.streaming_from", &[("host", &host)]),
             icon_url: Some(format!("https://www.google.com/s2/favicons?domain={host}")),
             proxy_icon_url: None,
         },
@@ -170,23 +150,28 @@ fn build_single_track_added_embeds(tracks_added: &[TrackType]) -> Vec<Embed> {
 
     let mut embed = EmbedBuilder::new()
         .author(EmbedAuthor {
-            name: "🔊 Added to queue".to_string(),
+            name: locale.text("play.added_to_queue").to_string(),
             icon_url: None,
             proxy_icon_url: None,
             url: None,
         })
-        .title(track.title.clone().unwrap_or("Unknown".to_string()))
+        .title(
+            track
+                .title
+                .clone()
+                .unwrap_or(locale.text("unknown").to_string()),
+        )
         .url(track.url.clone())
         .color(colors::BLURPLE)
         .footer(footer)
         .field(EmbedField {
             inline: true,
-            name: "Duration".to_string(),
+            name: locale.text("play.duration").to_string(),
             value: track.duration_string.clone(),
         })
         .field(EmbedField {
             inline: true,
-            name: "Channel".to_string(),
+            name: locale.text("play.channel").to_string(),
             value: track.channel.clone(),
         })
         .build();
@@ -203,23 +188,17 @@ fn build_single_track_added_embeds(tracks_added: &[TrackType]) -> Vec<Embed> {
     vec![embed]
 }
 
-fn build_playlist_added_embeds(tracks: &[YouTubeTrack], num_tracks_added: usize) -> Vec<Embed> {
-    let mut content = String::new();
+fn build_playlist_added_embeds(
+    tracks: &[YouTubeTrack],
+    num_tracks_added: usize,
+    locale: Locale,
+) -> Vec<Embed> {
     let first_track = tracks.first().unwrap();
-    content.push_str(&format!(
-        "Adding playlist: [{}]({})\n",
-        &first_track
-            .playlist
-            .clone()
-            .unwrap_or("Unknown".to_string()),
-        build_playlist_url(
-            &first_track
-                .playlist_id
-                .clone()
-                .unwrap_or("Unknown".to_string())
-        )
-    ));
-    content.push_str(&format!("Added {num_tracks_added} tracks to the queue.\n"));
+    let content = format!(
+        "{}\n{}\n",
+        adding_playlist_message(first_track, locale),
+        locale.format("play.added_tracks", &[("count", &num_tracks_added)]),
+    );
     let embed = EmbedBuilder::new()
         .description(content)
         .color(colors::BLURPLE)
@@ -227,43 +206,106 @@ fn build_playlist_added_embeds(tracks: &[YouTubeTrack], num_tracks_added: usize)
     vec![embed]
 }
 
-fn build_embeds(tracks: &[YouTubeTrack], tracks_added: &[TrackType]) -> Vec<Embed> {
+fn build_embeds(tracks: &[YouTubeTrack], tracks_added: &[TrackType], locale: Locale) -> Vec<Embed> {
     let num_tracks_added = tracks_added.len();
     match num_tracks_added {
         0 => vec![],
-        1 => build_single_track_added_embeds(tracks_added),
-        _ => build_playlist_added_embeds(tracks, num_tracks_added),
+        1 => build_single_track_added_embeds(tracks_added, locale),
+        _ => build_playlist_added_embeds(tracks, num_tracks_added, locale),
     }
 }
 
-pub(crate) async fn play(
-    interaction: Box<InteractionCreate>,
-    state: State,
+/// A like button for a single added track.
+fn build_components(tracks_added: &[TrackType], locale: Locale) -> Vec<Component> {
+    match tracks_added {
+        [TrackType { id: Some(id), .. }] => vec![Component::ActionRow(ActionRow {
+            components: vec![like_button(Some(*id), locale)],
+        })],
+        _ => vec![],
+    }
+}
+
+pub(crate) struct Play;
+
+pub(crate) struct PlayOptions {
     query: String,
-) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-    tracing::info!(
-        "play command in channel {:?} by {:?}",
-        interaction.channel,
-        interaction.author(),
-    );
-    match play_inner(&interaction, Arc::clone(&state), query).await {
-        Ok(_) => Ok(()),
-        Err(e) => {
-            tracing::debug!("Search did not result in any tracks: {}", e);
-            let content = "Search did not result in any tracks.".to_string();
-
-            let embeds = vec![EmbedBuilder::new()
-                .description(content)
-                .color(colors::RED)
-                .build()];
-            state
-                .http
-                .interaction(interaction.application_id)
-                .update_response(&interaction.token)
-                .embeds(Some(&embeds))
-                .await?;
-            Ok(())
+    start: Option<Duration>,
+    end: Option<Duration>,
+}
+
+/// Reads the timestamp option `name`, if it is given.
+fn timestamp(options: &Options<'_>, name: &str) -> Result<Option<Duration>, OptionError> {
+    optional(options.string(name))?
+        .map(|value| parse_timestamp(&value).ok_or(OptionError::InvalidType(name.to_string())))
+        .transpose()
+}
+
+impl FromOptions for PlayOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        let start = timestamp(options, "start")?;
+        let end = timestamp(options, "end")?;
+        if let (Some(end), start) = (end, start.unwrap_or_default()) {
+            if end <= start {
+                return Err(OptionError::InvalidType("end".to_string()));
+            }
         }
+        Ok(Self {
+            query: options.string("query")?,
+            start,
+            end,
+        })
+    }
+}
+
+impl PlayOptions {
+    /// The section of the track to play, from the options or else from the URL.
+    fn section(&self) -> Section {
+        let section = Section::from_url(&self.query);
+        let section = Section {
+            start: self.start.unwrap_or(section.start),
+            end: self.end.or(section.end),
+        };
+        match section.end {
+            Some(end) if end <= section.start => Section {
+                end: None,
+                ..section
+            },
+            _ => section,
+        }
+    }
+}
+
+#[async_trait]
+impl SlashCommand for Play {
+    type Options = PlayOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new("play", "Add a song to the queue", CommandType::ChatInput)
+            .option(StringBuilder::new("query", "URL of a song").required(true))
+            .option(StringBuilder::new(
+                "start",
+                "Where to start playing, like 1:30 or 1m30s",
+            ))
+            .option(StringBuilder::new(
+                "end",
+                "Where to stop playing, like 5:00 or 5m",
+            ))
+            .build()
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: PlayOptions,
+    ) -> CommandResult {
+        tracing::info!(
+            "play command in channel {:?} by {:?}",
+            interaction.channel,
+            interaction.author(),
+        );
+        let section = options.section();
+        play_inner(&interaction, state, options.query, section).await
     }
 }
 
@@ -271,6 +313,7 @@ pub(crate) async fn play_inner(
     interaction: &InteractionCreate,
     state: State,
     query: String,
+    section: Section,
 ) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
     tracing::info!(
         "play_inner in channel {:?} by {:?}",
@@ -278,62 +321,26 @@ pub(crate) async fn play_inner(
         interaction.author(),
     );
 
-    let content = format!("Adding track(s) to the queue: {query}");
+    let locale = Locale::for_interaction(interaction, &state).await;
+    let content = locale.format("play.adding", &[("query", &query)]);
     tracing::info!("content: {:?}", content);
-    let embeds = vec![EmbedBuilder::new()
-        .description(content)
-        .color(colors::YELLOW)
-        .build()];
-    let interaction_response_data = InteractionResponseDataBuilder::new()
-        .flags(MessageFlags::LOADING)
-        .embeds(embeds)
-        .build();
-    let response = InteractionResponse {
-        kind: InteractionResponseType::DeferredChannelMessageWithSource,
-        data: Some(interaction_response_data),
-    };
-    state
-        .http
-        .interaction(interaction.application_id)
-        .create_response(interaction.id, &interaction.token, &response)
-        .await?;
+    defer_announcement(interaction, &state, content).await?;
 
-    let Some(user_id) = interaction.author_id() else {
-        return Ok(());
-    };
-    let Some(guild_id) = interaction.guild_id else {
-        return Ok(());
+    let (Some(user_id), Some(guild_id)) = (interaction.author_id(), interaction.guild_id) else {
+        return Err(CommandError::NotInGuild.into());
     };
 
     join_channel(state.clone(), guild_id, user_id).await?;
 
-    // handle keyword queries
-    let query = if Url::parse(&query).is_err() {
-        format!("ytsearch:{query}")
-    } else {
-        query
-    };
-
-    tracing::info!("query: {:?}", query);
-
-    let tracks = get_tracks(query).await?;
-    tracing::info!("got tracks: {:?}", tracks);
+    let tracks = resolve(&state, query).await?;
+    let (tracks, blocked) = blocklist::remove_blocked(&state, guild_id, user_id, tracks).await?;
+    let queue_limits = db::guild::queue_limits(&state.pool, guild_id.to_string()).await?;
+    let queued = limits::queued_by(&state, guild_id, user_id).await;
+    let Admitted { tracks, left_out } =
+        limits::admit_resolved(queue_limits, tracks, queued, section)?;
 
     if tracks.len() > 1 {
-        let first_track = tracks.first().unwrap();
-        let content = format!(
-            "Adding playlist [{}]({})",
-            first_track
-                .playlist
-                .clone()
-                .unwrap_or("Unknown".to_string()),
-            build_playlist_url(
-                &first_track
-                    .playlist_id
-                    .clone()
-                    .unwrap_or("Unknown".to_string())
-            )
-        );
+        let content = adding_playlist_message(tracks.first().unwrap(), locale);
         let embeds = vec![EmbedBuilder::new()
             .description(content)
             .color(colors::BLURPLE)
@@ -352,8 +359,67 @@ pub(crate) async fn play_inner(
         call.queue().resume().context("Could not resume playing")?;
     }
 
+    let tracks_added = enqueue_resolved(interaction, &state, guild_id, &tracks, section).await?;
+
+    let mut embeds = build_embeds(&tracks, &tracks_added, locale);
+    if let Some(embed) = embeds.first_mut() {
+        let description = embed.description.take().unwrap_or_default();
+        embed.description = Some(with_notes(description, blocked, left_out, locale));
+    }
+    let components = build_components(&tracks_added, locale);
+    announce_embeds(interaction, &state, &embeds, &components).await
+}
+
+/// Adds notes to `message` if `blocked` tracks are blocked in the guild and `left_out`
+/// tracks did not fit its limits.
+pub(crate) fn with_notes(
+    mut message: String,
+    blocked: usize,
+    left_out: usize,
+    locale: Locale,
+) -> String {
+    let notes = [("play.blocked", blocked), ("play.left_out", left_out)];
+    for (key, count) in notes.into_iter().filter(|(_, count)| *count > 0) {
+        let note = locale.format(key, &[("count", &count)]);
+        message.push_str(&format!("\n\n{note}"));
+    }
+    message
+}
+
+/// Looks up `query`, a URL or search terms, with yt-dlp.
+async fn resolve(state: &State, query: String) -> Result<Vec<YouTubeTrack>, ResolveError> {
+    // handle keyword queries
+    let query = if Url::parse(&query).is_err() {
+        format!("ytsearch:{query}")
+    } else {
+        query
+    };
+
+    tracing::info!("query: {:?}", query);
+
+    let tracks = state.ytdlp.get_tracks(query).await?;
+    tracing::info!("got tracks: {:?}", tracks);
+    Ok(tracks)
+}
+
+/// Adds `tracks` to the queue of `guild_id`, skipping tracks whose metadata
+/// cannot be fetched. Fails if none of them could be added.
+///
+/// Only `section` of a single track is played, the tracks of playlists play in full.
+async fn enqueue_resolved(
+    interaction: &InteractionCreate,
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    tracks: &[YouTubeTrack],
+    section: Section,
+) -> Result<Vec<TrackType>, Box<dyn Error + Send + Sync + 'static>> {
+    let section = match tracks {
+        [_] => section,
+        _ => Section::default(),
+    };
     let mut tracks_added = vec![];
-    for yttrack in &tracks {
+    let mut last_error = None;
+    for yttrack in tracks {
         tracing::debug!("track: {:?}", yttrack);
         let url = yttrack
             .original_url
@@ -361,134 +427,227 @@ pub(crate) async fn play_inner(
             .or(yttrack.url.clone())
             .context("Could not find url")?;
 
-        let mut src = YoutubeDl::new(state.client.clone(), url.clone());
+        let src = state.ytdlp.source(state.client.clone(), url.clone());
 
-        match src.aux_metadata().await {
+        match state.ytdlp.aux_metadata(&url).await {
             Ok(metadata) => {
                 debug!("metadata: {:?}", metadata);
 
-                let track: Track = Track::new_with_data(
-                    src.clone().into(),
-                    Arc::new(Metadata {
-                        title: metadata.title.clone(),
-                        duration: metadata.duration,
-                        url: url.clone(),
-                        src,
-                    }),
-                );
-
-                persistence(interaction, yttrack, Arc::clone(&state))
+                let id = persistence(interaction, yttrack, Arc::clone(state))
                     .await
                     .unwrap_or_else(|e| {
                         tracing::error!("could not persist track: {:?}", e);
+                        None
                     });
 
+                let duration = section.duration(metadata.duration);
+                let duration_string = match (section.is_full(), duration) {
+                    (false, Some(duration)) => format_duration(duration),
+                    _ => yttrack.duration_string.clone(),
+                };
                 tracks_added.push(TrackType {
+                    id,
                     url: url.clone(),
                     title: metadata.title.clone(),
-                    duration_string: yttrack.duration_string.clone(),
+                    duration_string,
                     channel: yttrack.channel.clone(),
                     thumbnail: metadata.thumbnail.clone(),
                 });
 
-                match state.songbird.get(guild_id) {
-                    Some(call_lock) => {
-                        let mut call = call_lock.lock().await;
-                        let _handle = call.enqueue_with_preload(
-                            track,
-                            metadata.duration.map(|duration| -> Duration {
-                                if duration.as_secs() > 5 {
-                                    duration.sub(Duration::from_secs(5))
-                                } else {
-                                    duration
-                                }
-                            }),
-                        );
-                    }
-                    None => tracing::error!("could not get call lock"),
-                }
+                enqueue(
+                    state,
+                    guild_id,
+                    Metadata {
+                        title: metadata.title.clone(),
+                        channel: Some(yttrack.channel.clone()),
+                        duration,
+                        url: url.clone(),
+                        src,
+                        autoplay: false,
+                        requester: interaction.author_id(),
+                        gain: 1.0,
+                        section,
+                        chapters: yttrack.chapters.clone(),
+                    },
+                )
+                .await;
             }
             Err(e) => {
                 tracing::error!("could not get metadata: {:?}", e);
-                if e.to_string()
-                    .contains("Sign in to confirm you’re not a bot.")
-                {
-                    let content =
-                        "I seem to have been flagged by YouTube as a bot. :-(".to_string();
-                    let embeds = vec![EmbedBuilder::new()
-                        .description(content)
-                        .color(colors::RED)
-                        .build()];
-                    state
-                        .http
-                        .interaction(interaction.application_id)
-                        .update_response(&interaction.token)
-                        .embeds(Some(&embeds))
-                        .await?;
-                    return Ok(());
+                if e.is_fatal() {
+                    return Err(e.into());
                 }
+                last_error = Some(e);
             }
         }
     }
 
-    let embeds = build_embeds(&tracks, &tracks_added);
-    state
-        .http
-        .interaction(interaction.application_id)
-        .update_response(&interaction.token)
-        .embeds(Some(&embeds))
-        .await
-        .context("Could not send final play message")?;
+    if let (true, Some(e)) = (tracks_added.is_empty(), last_error) {
+        return Err(e.into());
+    }
+    Ok(tracks_added)
+}
+
+/// Resolves `query` like `/play` and adds the tracks to the queue of `guild_id`,
+/// which the bot must have joined already. Returns how many tracks were added.
+pub(crate) async fn enqueue_query(
+    interaction: &InteractionCreate,
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    query: String,
+) -> Result<usize, Box<dyn Error + Send + Sync + 'static>> {
+    let Some(user_id) = interaction.author_id() else {
+        return Err(CommandError::NotInGuild.into());
+    };
+    let section = Section::from_url(&query);
+    let tracks = resolve(state, query).await?;
+    let (tracks, _) = blocklist::remove_blocked(state, guild_id, user_id, tracks).await?;
+    let queue_limits = db::guild::queue_limits(&state.pool, guild_id.to_string()).await?;
+    let queued = limits::queued_by(state, guild_id, user_id).await;
+    let Admitted { tracks, .. } = limits::admit_resolved(queue_limits, tracks, queued, section)?;
+    Ok(
+        enqueue_resolved(interaction, state, guild_id, &tracks, section)
+            .await?
+            .len(),
+    )
+}
+
+/// Creates the track of `metadata` for the queue of `guild_id`.
+///
+/// Tracks play at the loudness measured when they were played before. The loudness of
+/// tracks that were not measured yet is saved once they played to the end. Segments
+/// that the guild skips are fetched ahead.
+pub(crate) async fn track(
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    mut metadata: Metadata,
+) -> Track {
+    let gain = match db::track::track_gain(&state.pool, metadata.url.clone()).await {
+        Ok(gain) => gain,
+        Err(e) => {
+            tracing::warn!("could not get the gain of {}: {e:?}", metadata.url);
+            None
+        }
+    };
+    let on_loudness = match gain {
+        Some(gain) => {
+            metadata.gain = 10f64.powf(gain / 20.0) as f32;
+            None
+        }
+        // a section says little about the loudness of the whole track
+        None if !metadata.section.is_full() => None,
+        None => {
+            let (pool, url) = (state.pool.clone(), metadata.url.clone());
+            let runtime = tokio::runtime::Handle::current();
+            let on_loudness: filter::LoudnessCallback = Arc::new(move |loudness| {
+                let (pool, url) = (pool.clone(), url.clone());
+                let gain = filter::normalization_gain(loudness);
+                runtime.spawn(async move {
+                    if let Err(e) = db::track::set_track_gain(&pool, url, gain).await {
+                        tracing::warn!("could not save the gain of a track: {e:?}");
+                    }
+                });
+            });
+            Some(on_loudness)
+        }
+    };
+    if let Ok(skip) = db::guild::skip_settings(&state.pool, guild_id.to_string()).await {
+        if skip.segments {
+            // fetch them ahead, so the track does not wait for yt-dlp when it starts
+            let (state, url) = (State::clone(state), metadata.url.clone());
+            tokio::spawn(async move { filter::load_segments(&state, &url).await });
+        }
+    }
+    let volume = volume::track_volume(state, guild_id, &metadata);
+    let input = filter::input(state, guild_id, &metadata, on_loudness);
+    Track::new_with_data(input, Arc::new(metadata)).volume(volume)
+}
 
-    Ok(())
+/// Adds a track to the end of the queue of `guild_id`.
+pub(crate) async fn enqueue(state: &State, guild_id: Id<GuildMarker>, metadata: Metadata) {
+    let preload_position = state.config.queue.preload_position(metadata.duration);
+    let (duration, section) = (metadata.duration, metadata.section);
+    let track = track(state, guild_id, metadata).await;
+    match state.songbird.get(guild_id) {
+        Some(call_lock) => {
+            let mut call = call_lock.lock().await;
+            let handle = call.enqueue_with_preload(track, preload_position);
+            crossfade::schedule(&handle, state, guild_id, duration);
+            section::schedule_end(&handle, state, guild_id, section);
+        }
+        None => tracing::error!("could not get call lock"),
+    }
+    limits::reorder(state, guild_id).await;
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ytdlp::tests::fake_ytdlp;
 
-    #[tokio::test]
-    async fn test_get_tracks() {
-        let urls = [
-            "https://www.youtube.com/playlist?list=PLFxxhcEeloYa1OlnWD6UgxlVQKJH5i_0p",
-            "https://music.youtube.com/watch?v=RO75ZzqUOJw",
-            "https://www.youtube.com/watch?v=qVHyl0P_P-M",
-            "https://www.youtube.com/watch?v=34CZjsEI1yU",
-        ];
-        for url in urls.iter() {
-            println!("url: {:?}", url);
-            let tracks = get_tracks(url.to_string()).await.unwrap();
-            assert!(!tracks.is_empty());
+    fn track_type(yttrack: &YouTubeTrack) -> TrackType {
+        TrackType {
+            id: Some(1),
+            url: yttrack
+                .original_url
+                .clone()
+                .or(yttrack.url.clone())
+                .unwrap_or_default(),
+            title: Some(yttrack.title.clone()),
+            duration_string: yttrack.duration_string.clone(),
+            channel: yttrack.channel.clone(),
+            thumbnail: yttrack.thumbnail.clone(),
         }
     }
 
+    #[test]
+    fn test_build_embeds_nothing_added() {
+        assert!(build_embeds(&[], &[], Locale::En).is_empty());
+    }
+
     #[tokio::test]
-    async fn test_premium_tracks() {
-        let urls = ["https://www.youtube.com/watch?v=QgMZRmxQ0Dc"];
-        for url in urls.iter() {
-            println!("url: {:?}", url);
-            let tracks = get_tracks(url.to_string()).await;
-            assert!(tracks.is_err());
-            assert!(tracks
-                .err()
-                .unwrap()
-                .to_string()
-                .contains("This video is only available to Music Premium members"));
-        }
+    async fn test_build_single_track_embeds() {
+        let tracks = fake_ytdlp()
+            .get_tracks("https://www.youtube.com/watch?v=34CZjsEI1yU".to_string())
+            .await
+            .unwrap();
+        let tracks_added: Vec<_> = tracks.iter().map(track_type).collect();
+
+        let embeds = build_embeds(&tracks, &tracks_added, Locale::En);
+        assert_eq!(embeds.len(), 1);
+        assert_eq!(build_components(&tracks_added, Locale::En).len(), 1);
+        let embed = &embeds[0];
+        assert_eq!(embed.title.as_deref(), Some("Kraftwerk - The Model"));
+        assert_eq!(
+            embed.url.as_deref(),
+            Some("https://www.youtube.com/watch?v=34CZjsEI1yU")
+        );
+        assert_eq!(
+            embed.footer.as_ref().map(|f| f.text.as_str()),
+            Some("Streaming from youtube.com")
+        );
+        assert_eq!(embed.fields[0].value, "3:42");
+        assert_eq!(embed.fields[1].value, "Kraftwerk");
     }
 
     #[tokio::test]
-    async fn test_playlist_does_not_exist_tracks() {
-        let urls = ["https://www.youtube.com/playlist?list=PLox0oG0uy8Lc1IaIfGyrvtuRItuEyJiyG"];
-        for url in urls.iter() {
-            println!("url: {:?}", url);
-            let tracks = get_tracks(url.to_string()).await;
-            assert!(tracks.is_err());
-            assert!(tracks
-                .err()
-                .unwrap()
-                .to_string()
-                .contains("YouTube said: The playlist does not exist."));
-        }
+    async fn test_build_playlist_embeds() {
+        let tracks = fake_ytdlp()
+            .get_tracks(
+                "https://www.youtube.com/playlist?list=PLFxxhcEeloYa1OlnWD6UgxlVQKJH5i_0p"
+                    .to_string(),
+            )
+            .await
+            .unwrap();
+        let tracks_added: Vec<_> = tracks.iter().map(track_type).collect();
+
+        let embeds = build_embeds(&tracks, &tracks_added, Locale::En);
+        assert_eq!(embeds.len(), 1);
+        let description = embeds[0].description.clone().unwrap();
+        assert!(description.contains(
+            "[Kraftwerk Essentials](https://www.youtube.com/playlist?list=PLFxxhcEeloYa1OlnWD6UgxlVQKJH5i_0p)"
+        ));
+        assert!(description.contains("Added 3 tracks to the queue."));
+        assert!(build_components(&tracks_added, Locale::En).is_empty());
     }
 }
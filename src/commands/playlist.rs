@@ -0,0 +1,530 @@
+use crate::blocklist;
+use crate::colors;
+use crate::commands::framework::{
+    announce_embeds, confirm, defer, defer_announcement, optional, update_response, CommandResult,
+    FromOptions, OptionError, Options, SlashCommand,
+};
+use crate::commands::join::join_channel;
+use crate::commands::play::{enqueue, with_notes};
+use crate::commands::CommandError;
+use crate::db::{self, track::SavedTrack};
+use crate::i18n::Locale;
+use crate::limits::{self, Admitted};
+use crate::metadata::Metadata;
+use crate::section::Section;
+use crate::state::State;
+use crate::utils::{format_duration, parse_duration};
+use anyhow::Context;
+use async_trait::async_trait;
+use std::error::Error;
+use twilight_model::{
+    application::command::{Command, CommandType},
+    gateway::payload::incoming::InteractionCreate,
+    id::{
+        marker::{GuildMarker, UserMarker},
+        Id,
+    },
+};
+use twilight_util::builder::{
+    command::{BooleanBuilder, CommandBuilder, IntegerBuilder, StringBuilder, SubCommandBuilder},
+    embed::EmbedBuilder,
+};
+use url::Url;
+
+/// Longest name of a playlist, in characters.
+const MAX_NAME_LENGTH: u16 = 100;
+
+/// Tracks that are listed when showing a playlist.
+const SHOWN_TRACKS: usize = 20;
+
+pub(crate) struct Playlist;
+
+pub(crate) enum PlaylistOptions {
+    Create {
+        name: String,
+        shared: bool,
+        /// Whether to fill the new playlist with the current queue.
+        from_queue: bool,
+    },
+    Add {
+        name: String,
+        query: String,
+    },
+    Remove {
+        name: String,
+        /// 1-based position of the track in the playlist.
+        position: i64,
+    },
+    /// `None` to list all playlists.
+    Show(Option<String>),
+    Play(String),
+    Delete(String),
+    Rename {
+        name: String,
+        new_name: String,
+    },
+}
+
+/// Returns the playlist name in the option called `option`.
+fn name(options: &Options<'_>, option: &str) -> Result<String, OptionError> {
+    let name = options.string(option)?.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH as usize {
+        return Err(OptionError::InvalidType(option.to_string()));
+    }
+    Ok(name)
+}
+
+impl FromOptions for PlaylistOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        match options.subcommand()? {
+            ("create", options) => Ok(Self::Create {
+                name: name(&options, "name")?,
+                shared: optional(options.boolean("shared"))?.unwrap_or(false),
+                from_queue: optional(options.boolean("from_queue"))?.unwrap_or(false),
+            }),
+            ("add", options) => Ok(Self::Add {
+                name: name(&options, "name")?,
+                query: options.string("query")?,
+            }),
+            ("remove", options) => {
+                let position = options.integer("position")?;
+                if position < 1 {
+                    return Err(OptionError::InvalidType("position".to_string()));
+                }
+                Ok(Self::Remove {
+                    name: name(&options, "name")?,
+                    position,
+                })
+            }
+            ("show", options) => Ok(Self::Show(optional(name(&options, "name"))?)),
+            ("play", options) => Ok(Self::Play(name(&options, "name")?)),
+            ("delete", options) => Ok(Self::Delete(name(&options, "name")?)),
+            ("rename", options) => Ok(Self::Rename {
+                name: name(&options, "name")?,
+                new_name: name(&options, "new_name")?,
+            }),
+            (name, _) => Err(OptionError::InvalidType(name.to_string())),
+        }
+    }
+}
+
+fn name_option(description: &str) -> StringBuilder {
+    StringBuilder::new("name", description)
+        .required(true)
+        .max_length(MAX_NAME_LENGTH)
+}
+
+#[async_trait]
+impl SlashCommand for Playlist {
+    type Options = PlaylistOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "playlist",
+            "Save tracks as playlists and play them",
+            CommandType::ChatInput,
+        )
+        .option(
+            SubCommandBuilder::new("create", "Create a playlist")
+                .option(name_option("Name of the playlist"))
+                .option(BooleanBuilder::new(
+                    "shared",
+                    "Let everyone in this server play the playlist",
+                ))
+                .option(BooleanBuilder::new(
+                    "from_queue",
+                    "Save the current queue as the playlist",
+                )),
+        )
+        .option(
+            SubCommandBuilder::new("add", "Add tracks to a playlist")
+                .option(name_option("Name of the playlist"))
+                .option(
+                    StringBuilder::new("query", "URL or search term of a track or playlist")
+                        .required(true),
+                ),
+        )
+        .option(
+            SubCommandBuilder::new("remove", "Remove a track from a playlist")
+                .option(name_option("Name of the playlist"))
+                .option(
+                    IntegerBuilder::new("position", "Position of the track in the playlist")
+                        .required(true)
+                        .min_value(1),
+                ),
+        )
+        .option(
+            SubCommandBuilder::new("show", "Show a playlist, or all of them").option(
+                StringBuilder::new("name", "Name of the playlist, none to list all")
+                    .max_length(MAX_NAME_LENGTH),
+            ),
+        )
+        .option(
+            SubCommandBuilder::new("play", "Add a playlist to the queue")
+                .option(name_option("Name of the playlist")),
+        )
+        .option(
+            SubCommandBuilder::new("delete", "Delete a playlist")
+                .option(name_option("Name of the playlist")),
+        )
+        .option(
+            SubCommandBuilder::new("rename", "Rename a playlist")
+                .option(name_option("Current name of the playlist"))
+                .option(
+                    StringBuilder::new("new_name", "New name of the playlist")
+                        .required(true)
+                        .max_length(MAX_NAME_LENGTH),
+                ),
+        )
+        .build()
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: PlaylistOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "playlist command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let (Some(guild_id), Some(user_id)) = (interaction.guild_id, interaction.author_id())
+        else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        let locale = Locale::for_interaction(&interaction, &state).await;
+
+        match options {
+            PlaylistOptions::Create {
+                name,
+                shared,
+                from_queue,
+            } => {
+                // read the queue first, to not create a playlist if that fails
+                let queue = if from_queue {
+                    queued_tracks(&state, guild_id).await?
+                } else {
+                    Vec::new()
+                };
+                let playlist = db::playlist::Playlist::new(
+                    name.clone(),
+                    user_id.to_string(),
+                    guild_id.to_string(),
+                    shared,
+                );
+                let Some(id) = db::playlist::insert_playlist(&state.pool, playlist).await? else {
+                    return Err(CommandError::PlaylistExists.into());
+                };
+                let mut track_ids = Vec::with_capacity(queue.len());
+                for track in queue {
+                    track_ids.push(db::track::insert_track_if_missing(&state.pool, track).await?);
+                }
+                db::playlist::append_playlist_tracks(&state.pool, id, &track_ids).await?;
+
+                let message = if from_queue {
+                    locale.format(
+                        "playlist.created_from_queue",
+                        &[("name", &name), ("count", &track_ids.len())],
+                    )
+                } else {
+                    locale.format("playlist.created", &[("name", &name)])
+                };
+                confirm(&interaction, &state, message).await
+            }
+            PlaylistOptions::Add { name, query } => {
+                let playlist = owned_playlist(&state, guild_id, user_id, name).await?;
+                let content = locale.format("playlist.adding", &[("query", &query)]);
+                defer(&interaction, &state, content).await?;
+
+                // handle keyword queries
+                let query = if Url::parse(&query).is_err() {
+                    format!("ytsearch:{query}")
+                } else {
+                    query
+                };
+                let tracks = state.ytdlp.get_tracks(query).await?;
+                let (tracks, blocked) =
+                    blocklist::remove_blocked(&state, guild_id, user_id, tracks).await?;
+                let mut track_ids = Vec::new();
+                for track in tracks {
+                    let url = track
+                        .original_url
+                        .or(track.url)
+                        .context("Could not find url")?;
+                    let track = db::track::Track::new(
+                        url,
+                        track.title,
+                        track.channel,
+                        track.duration_string,
+                        track.thumbnail.unwrap_or_default(),
+                    );
+                    track_ids.push(db::track::insert_track(&state.pool, track).await?);
+                }
+                db::playlist::append_playlist_tracks(&state.pool, playlist.id, &track_ids).await?;
+
+                let message = locale.format(
+                    "playlist.added",
+                    &[("name", &playlist.name), ("count", &track_ids.len())],
+                );
+                let message = with_notes(message, blocked, 0, locale);
+                update_response(&interaction, &state, message).await
+            }
+            PlaylistOptions::Remove { name, position } => {
+                let playlist = owned_playlist(&state, guild_id, user_id, name).await?;
+                if !db::playlist::remove_playlist_track(&state.pool, playlist.id, position).await? {
+                    return Err(OptionError::InvalidType("position".to_string()).into());
+                }
+                let message = locale.format(
+                    "playlist.removed",
+                    &[("name", &playlist.name), ("position", &position)],
+                );
+                confirm(&interaction, &state, message).await
+            }
+            PlaylistOptions::Show(name) => {
+                defer(&interaction, &state, locale.text("playlist.loading")).await?;
+                let message = match name {
+                    Some(name) => {
+                        let playlist = find_playlist(&state, guild_id, user_id, name).await?;
+                        let tracks =
+                            db::playlist::playlist_tracks(&state.pool, playlist.id).await?;
+                        describe_playlist(&playlist, &tracks, locale)
+                    }
+                    None => {
+                        let playlists = db::playlist::playlists(
+                            &state.pool,
+                            guild_id.to_string(),
+                            user_id.to_string(),
+                        )
+                        .await?;
+                        list_playlists(&playlists, locale)
+                    }
+                };
+                update_response(&interaction, &state, message).await
+            }
+            PlaylistOptions::Play(name) => {
+                let playlist = find_playlist(&state, guild_id, user_id, name).await?;
+                let content = locale.format("playlist.loading_tracks", &[("name", &playlist.name)]);
+                defer_announcement(&interaction, &state, content).await?;
+
+                let tracks = db::playlist::playlist_tracks(&state.pool, playlist.id).await?;
+                let (admitted, blocked) = play_tracks(&state, guild_id, user_id, tracks).await?;
+
+                let message = locale.format(
+                    "playlist.playing",
+                    &[("name", &playlist.name), ("count", &admitted.tracks.len())],
+                );
+                let message = with_notes(message, blocked, admitted.left_out, locale);
+                let embeds = vec![EmbedBuilder::new()
+                    .description(message)
+                    .color(colors::BLURPLE)
+                    .build()];
+                announce_embeds(&interaction, &state, &embeds, &[]).await
+            }
+            PlaylistOptions::Delete(name) => {
+                let playlist = owned_playlist(&state, guild_id, user_id, name).await?;
+                db::playlist::delete_playlist(&state.pool, playlist.id).await?;
+                let message = locale.format("playlist.deleted", &[("name", &playlist.name)]);
+                confirm(&interaction, &state, message).await
+            }
+            PlaylistOptions::Rename { name, new_name } => {
+                let playlist = owned_playlist(&state, guild_id, user_id, name).await?;
+                if !db::playlist::rename_playlist(&state.pool, playlist.id, new_name.clone())
+                    .await?
+                {
+                    return Err(CommandError::PlaylistExists.into());
+                }
+                let message = locale.format(
+                    "playlist.renamed",
+                    &[("name", &playlist.name), ("new_name", &new_name)],
+                );
+                confirm(&interaction, &state, message).await
+            }
+        }
+    }
+}
+
+/// Finds the playlist called `name` that the user can play in the guild.
+pub(crate) async fn find_playlist(
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    user_id: Id<UserMarker>,
+    name: String,
+) -> Result<db::playlist::Playlist, Box<dyn Error + Send + Sync + 'static>> {
+    db::playlist::find_playlist(&state.pool, guild_id.to_string(), user_id.to_string(), name)
+        .await?
+        .ok_or_else(|| CommandError::UnknownPlaylist.into())
+}
+
+/// Like [`find_playlist`], for changes that only the owner may make.
+async fn owned_playlist(
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    user_id: Id<UserMarker>,
+    name: String,
+) -> Result<db::playlist::Playlist, Box<dyn Error + Send + Sync + 'static>> {
+    let playlist = find_playlist(state, guild_id, user_id, name).await?;
+    if playlist.owner_id != user_id.to_string() {
+        return Err(CommandError::NotPlaylistOwner.into());
+    }
+    Ok(playlist)
+}
+
+/// Joins the voice channel of the user and adds the `tracks` that are not blocked and fit
+/// the limits of the guild to the queue. Returns them with how many were blocked.
+pub(crate) async fn play_tracks(
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    user_id: Id<UserMarker>,
+    tracks: Vec<SavedTrack>,
+) -> Result<(Admitted<SavedTrack>, usize), Box<dyn Error + Send + Sync + 'static>> {
+    let (tracks, blocked) = blocklist::remove_blocked(state, guild_id, user_id, tracks).await?;
+    let queue_limits = db::guild::queue_limits(&state.pool, guild_id.to_string()).await?;
+    let queued = limits::queued_by(state, guild_id, user_id).await;
+    let admitted = limits::admit(queue_limits, tracks, queued, |track| {
+        parse_duration(&track.duration)
+    })?;
+    join_channel(state.clone(), guild_id, user_id).await?;
+    if let Some(call_lock) = state.songbird.get(guild_id) {
+        let call = call_lock.lock().await;
+        call.queue().resume().context("Could not resume playing")?;
+    }
+    for track in &admitted.tracks {
+        let metadata = Metadata {
+            title: Some(track.title.clone()),
+            channel: Some(track.channel.clone()).filter(|channel| !channel.is_empty()),
+            duration: parse_duration(&track.duration),
+            url: track.url.clone(),
+            src: state.ytdlp.source(state.client.clone(), track.url.clone()),
+            autoplay: false,
+            requester: Some(user_id),
+            gain: 1.0,
+            section: Section::default(),
+            chapters: Vec::new(),
+        };
+        enqueue(state, guild_id, metadata).await;
+    }
+    Ok((admitted, blocked))
+}
+
+/// Returns the tracks in the queue of `guild_id`, to be saved in a playlist.
+async fn queued_tracks(
+    state: &State,
+    guild_id: Id<GuildMarker>,
+) -> Result<Vec<db::track::Track>, CommandError> {
+    let Some(call_lock) = state.songbird.get(guild_id) else {
+        return Err(CommandError::NothingPlaying);
+    };
+    let call = call_lock.lock().await;
+    let queue = call.queue().current_queue();
+    if queue.is_empty() {
+        return Err(CommandError::NothingPlaying);
+    }
+    Ok(queue
+        .iter()
+        .map(|handle| {
+            let metadata = handle.data::<Metadata>();
+            db::track::Track::new(
+                metadata.url.clone(),
+                metadata.title.clone().unwrap_or_default(),
+                String::new(),
+                metadata.duration.map(format_duration).unwrap_or_default(),
+                String::new(),
+            )
+        })
+        .collect())
+}
+
+fn describe_playlist(
+    playlist: &db::playlist::Playlist,
+    tracks: &[SavedTrack],
+    locale: Locale,
+) -> String {
+    let mut message = format!("**{}**", playlist.name);
+    if playlist.shared {
+        message.push_str(&locale.format(
+            "playlist.shared_by",
+            &[("user", &format!("<@{}>", playlist.owner_id))],
+        ));
+    }
+    message.push('\n');
+    if tracks.is_empty() {
+        message.push_str(locale.text("playlist.empty"));
+    }
+    message.push_str(&list_tracks(tracks, locale));
+    message
+}
+
+/// Lists the first [`SHOWN_TRACKS`] of `tracks` with their position.
+pub(crate) fn list_tracks(tracks: &[SavedTrack], locale: Locale) -> String {
+    let mut message = String::new();
+    for (i, track) in tracks.iter().take(SHOWN_TRACKS).enumerate() {
+        let title = if track.title.is_empty() {
+            locale.text("unknown")
+        } else {
+            &track.title
+        };
+        message.push_str(&format!("{}. [{}]({})", i + 1, title, track.url));
+        if !track.duration.is_empty() {
+            message.push_str(&format!(" ({})", track.duration));
+        }
+        message.push('\n');
+    }
+    if tracks.len() > SHOWN_TRACKS {
+        message.push_str(&locale.format(
+            "playlist.more",
+            &[("count", &(tracks.len() - SHOWN_TRACKS))],
+        ));
+    }
+    message
+}
+
+fn list_playlists(playlists: &[db::playlist::Playlist], locale: Locale) -> String {
+    if playlists.is_empty() {
+        return locale.text("playlist.none").to_string();
+    }
+    let mut message = String::new();
+    for playlist in playlists {
+        message.push_str(&format!("* {}", playlist.name));
+        if playlist.shared {
+            message.push_str(&locale.format(
+                "playlist.shared_by",
+                &[("user", &format!("<@{}>", playlist.owner_id))],
+            ));
+        }
+        message.push('\n');
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(n: usize) -> SavedTrack {
+        SavedTrack {
+            url: format!("https://example.com/{n}"),
+            title: format!("Track {n}"),
+            channel: "Kraftwerk".to_string(),
+            duration: "3:42".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_describe_playlist() {
+        let playlist =
+            db::playlist::Playlist::new("mix".to_string(), "42".to_string(), "1".to_string(), true);
+        let tracks: Vec<_> = (1..=SHOWN_TRACKS + 2).map(track).collect();
+        let message = describe_playlist(&playlist, &tracks, Locale::En);
+        assert!(message.starts_with("**mix**, shared by <@42>\n"));
+        assert!(message.contains("1. [Track 1](https://example.com/1) (3:42)\n"));
+        assert!(!message.contains(&format!("Track {}", SHOWN_TRACKS + 1)));
+        assert!(message.ends_with("…and 2 more tracks."));
+
+        let message = describe_playlist(&playlist, &[], Locale::En);
+        assert!(message.ends_with("This playlist has no tracks yet."));
+    }
+}
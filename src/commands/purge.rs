@@ -0,0 +1,293 @@
+use crate::commands::framework::{
+    defer, optional, update_response, CommandResult, FromOptions, OptionError, Options,
+    SlashCommand,
+};
+use crate::commands::CommandError;
+use crate::i18n::Locale;
+use crate::state::State;
+use crate::utils::{created_at, unix_now};
+use async_trait::async_trait;
+use std::time::Duration;
+use twilight_http::error::ErrorType;
+use twilight_model::{
+    application::command::{Command, CommandType},
+    gateway::payload::incoming::InteractionCreate,
+    guild::Permissions,
+    id::{
+        marker::{MessageMarker, UserMarker},
+        Id,
+    },
+};
+use twilight_util::builder::command::{
+    BooleanBuilder, CommandBuilder, IntegerBuilder, UserBuilder,
+};
+
+/// Most messages that can be deleted with one command.
+const MAX_COUNT: i64 = 1000;
+
+/// Messages that are looked at to find the ones to delete, newest first.
+const MAX_SCANNED: usize = 5000;
+
+/// Discord only bulk deletes messages younger than two weeks.
+/// The margin accounts for the time the purge takes.
+const BULK_DELETE_MAX_AGE: Duration = Duration::from_secs(14 * 24 * 60 * 60 - 10 * 60);
+
+pub(crate) struct Purge;
+
+pub(crate) struct PurgeOptions {
+    count: usize,
+    filter: Filter,
+}
+
+/// Which messages a purge deletes.
+#[derive(Debug, Default)]
+struct Filter {
+    bots_only: bool,
+    user: Option<Id<UserMarker>>,
+    /// Only messages younger than this, relative to the start of the purge.
+    max_age: Option<Duration>,
+}
+
+impl Filter {
+    fn matches(
+        &self,
+        id: Id<MessageMarker>,
+        author: Id<UserMarker>,
+        bot: bool,
+        now: Duration,
+    ) -> bool {
+        (!self.bots_only || bot)
+            && self.user.is_none_or(|user| user == author)
+            && !self.too_old(id, now)
+    }
+
+    /// Whether `id` and all messages before it are older than the filter allows.
+    fn too_old(&self, id: Id<MessageMarker>, now: Duration) -> bool {
+        self.max_age
+            .is_some_and(|max_age| now.saturating_sub(created_at(id)) > max_age)
+    }
+}
+
+impl FromOptions for PurgeOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        let count = options.integer("count")?;
+        if !(1..=MAX_COUNT).contains(&count) {
+            return Err(OptionError::InvalidType("count".to_string()));
+        }
+        let max_age = match optional(options.integer("minutes"))? {
+            Some(minutes) if minutes < 1 => {
+                return Err(OptionError::InvalidType("minutes".to_string()))
+            }
+            Some(minutes) => Some(Duration::from_secs(minutes as u64 * 60)),
+            None => None,
+        };
+        Ok(Self {
+            count: count as usize,
+            filter: Filter {
+                bots_only: optional(options.boolean("bots"))?.unwrap_or(false),
+                user: optional(options.user("user"))?,
+                max_age,
+            },
+        })
+    }
+}
+
+/// Splits `ids` into messages that can be bulk deleted and older ones.
+fn partition_by_age(
+    ids: Vec<Id<MessageMarker>>,
+    now: Duration,
+) -> (Vec<Id<MessageMarker>>, Vec<Id<MessageMarker>>) {
+    ids.into_iter()
+        .partition(|&id| now.saturating_sub(created_at(id)) < BULK_DELETE_MAX_AGE)
+}
+
+fn is_not_found(e: &twilight_http::Error) -> bool {
+    matches!(e.kind(), ErrorType::Response { status, .. } if status.get() == 404)
+}
+
+#[async_trait]
+impl SlashCommand for Purge {
+    type Options = PurgeOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "purge",
+            "Delete recent messages in this channel",
+            CommandType::ChatInput,
+        )
+        .option(
+            IntegerBuilder::new("count", "How many messages to delete")
+                .required(true)
+                .min_value(1)
+                .max_value(MAX_COUNT),
+        )
+        .option(BooleanBuilder::new("bots", "Only delete messages of bots"))
+        .option(UserBuilder::new(
+            "user",
+            "Only delete messages of this user",
+        ))
+        .option(
+            IntegerBuilder::new("minutes", "Only delete messages of the last minutes").min_value(1),
+        )
+        .build()
+    }
+
+    fn required_permissions(&self) -> Option<Permissions> {
+        Some(Permissions::MANAGE_MESSAGES)
+    }
+
+    fn public_by_default(&self) -> bool {
+        false
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: PurgeOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "purge command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let (Some(_), Some(channel)) = (interaction.guild_id, &interaction.channel) else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        let channel_id = channel.id;
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        defer(&interaction, &state, locale.text("purge.searching")).await?;
+
+        let now = unix_now();
+        let mut ids = Vec::new();
+        let mut scanned = 0;
+        let mut before = None;
+        'scan: while ids.len() < options.count && scanned < MAX_SCANNED {
+            let messages = match before {
+                Some(before) => {
+                    state
+                        .http
+                        .channel_messages(channel_id)
+                        .before(before)
+                        .limit(100)
+                        .await?
+                }
+                None => state.http.channel_messages(channel_id).limit(100).await?,
+            }
+            .model()
+            .await?;
+            let Some(last) = messages.last() else {
+                break;
+            };
+            before = Some(last.id);
+            scanned += messages.len();
+
+            for message in messages {
+                if options.filter.too_old(message.id, now) {
+                    break 'scan;
+                }
+                // a public response to this command is in the channel as well
+                let metadata = message.interaction_metadata.as_deref();
+                if metadata.is_some_and(|metadata| metadata.id == interaction.id) {
+                    continue;
+                }
+                if options
+                    .filter
+                    .matches(message.id, message.author.id, message.author.bot, now)
+                {
+                    ids.push(message.id);
+                    if ids.len() == options.count {
+                        break 'scan;
+                    }
+                }
+            }
+        }
+
+        let (bulk, single) = partition_by_age(ids, now);
+        let mut deleted = 0;
+        for chunk in bulk.chunks(100) {
+            match chunk {
+                [id] => state.http.delete_message(channel_id, *id).await.map(drop),
+                ids => state.http.delete_messages(channel_id, ids).await.map(drop),
+            }?;
+            deleted += chunk.len();
+        }
+        // older messages can only be deleted one by one, and the http client
+        // waits whenever Discord's rate limit for that is exhausted
+        let mut deleted_single = 0;
+        for id in single {
+            match state.http.delete_message(channel_id, id).await {
+                Ok(_) => deleted_single += 1,
+                Err(e) if is_not_found(&e) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut summary = locale.format("purge.deleted", &[("count", &(deleted + deleted_single))]);
+        if deleted_single > 0 {
+            summary.push('\n');
+            summary.push_str(&locale.format("purge.deleted_old", &[("count", &deleted_single)]));
+        }
+        update_response(&interaction, &state, summary).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::DISCORD_EPOCH_MS;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// A message id created `age` before `now`.
+    fn message_id(now: Duration, age: Duration) -> Id<MessageMarker> {
+        let millis = (now - age).as_millis() as u64 - DISCORD_EPOCH_MS;
+        Id::new(millis << 22 | 1)
+    }
+
+    #[test]
+    fn test_filter() {
+        let now = Duration::from_secs(1_700_000_000);
+        let id = message_id(now, Duration::from_secs(30 * 60));
+        let alice = Id::new(1);
+        let bob = Id::new(2);
+
+        assert!(Filter::default().matches(id, alice, false, now));
+
+        let bots = Filter {
+            bots_only: true,
+            ..Default::default()
+        };
+        assert!(bots.matches(id, alice, true, now));
+        assert!(!bots.matches(id, alice, false, now));
+
+        let user = Filter {
+            user: Some(bob),
+            ..Default::default()
+        };
+        assert!(user.matches(id, bob, false, now));
+        assert!(!user.matches(id, alice, false, now));
+
+        let recent = Filter {
+            max_age: Some(Duration::from_secs(60 * 60)),
+            ..Default::default()
+        };
+        assert!(recent.matches(id, alice, false, now));
+        let old = message_id(now, Duration::from_secs(2 * 60 * 60));
+        assert!(!recent.matches(old, alice, false, now));
+        assert!(recent.too_old(old, now));
+    }
+
+    #[test]
+    fn test_partition_by_age() {
+        let now = Duration::from_secs(1_700_000_000);
+        let young = message_id(now, DAY);
+        let almost = message_id(now, 13 * DAY);
+        let old = message_id(now, 15 * DAY);
+        let (bulk, single) = partition_by_age(vec![young, almost, old], now);
+        assert_eq!(bulk, [young, almost]);
+        assert_eq!(single, [old]);
+    }
+}
@@ -1,49 +1,47 @@
+use async_trait::async_trait;
 use songbird::tracks::TrackHandle;
+use twilight_model::application::command::{Command, CommandType};
 use twilight_model::channel::message::component::{ActionRow, Button, ButtonStyle};
-use twilight_model::channel::message::{Component, Embed, EmojiReactionType, MessageFlags};
+use twilight_model::channel::message::{Component, Embed, EmojiReactionType};
 use twilight_model::gateway::payload::incoming::InteractionCreate;
-use twilight_model::http::interaction::InteractionResponse;
-use twilight_model::http::interaction::InteractionResponseType;
+use twilight_util::builder::command::CommandBuilder;
 use twilight_util::builder::embed::EmbedBuilder;
-use twilight_util::builder::InteractionResponseDataBuilder;
 
+use crate::cleanup::MessageKind;
 use crate::colors;
+use crate::commands::chapter::{current_chapter, describe_chapter};
+use crate::commands::favorites::like_button;
+use crate::commands::framework::{defer, remember, CommandResult, SlashCommand};
+use crate::commands::CommandError;
+use crate::filter::Filters;
+use crate::i18n::Locale;
 use crate::metadata::Metadata;
 use crate::state::State;
-use std::error::Error;
+use crate::utils::format_duration;
 
-pub(crate) const TRACKS_PER_PAGE: usize = 5;
-
-fn format_duration(duration: std::time::Duration) -> String {
-    let res = duration.as_secs();
-    let hours = res / (60 * 60);
-    let res = res - hours * 60 * 60;
-    let minutes = res / 60;
-    let res = res - minutes * 60;
-    let seconds = res;
-    let mut s = String::new();
-    if hours > 0 {
-        s.push_str(format!("{hours:02}:").as_str());
-    }
-    s.push_str(format!("{minutes:02}:{seconds:02}").as_str());
-    s
-}
-
-pub(crate) async fn build_queue_embeds(queue: &[TrackHandle], page: usize) -> Vec<Embed> {
+pub(crate) async fn build_queue_embeds(
+    queue: &[TrackHandle],
+    page: usize,
+    tracks_per_page: usize,
+    filters: Filters,
+    locale: Locale,
+) -> Vec<Embed> {
     let mut message = String::new();
     if queue.is_empty() {
-        message.push_str("There are no tracks in the queue.\n");
+        message.push_str(locale.text("queue.empty"));
+        message.push('\n');
     }
-    for track in queue
+    for (index, track) in queue
         .iter()
-        .skip(TRACKS_PER_PAGE * page)
-        .take(TRACKS_PER_PAGE)
+        .enumerate()
+        .skip(tracks_per_page * page)
+        .take(tracks_per_page)
     {
         let metadata = track.data::<Metadata>();
         message.push_str(
             format!(
                 "* [{}]({})",
-                metadata.title.clone().unwrap_or("Unknown".to_string()),
+                metadata.title.as_deref().unwrap_or(locale.text("unknown")),
                 metadata.url,
             )
             .as_str(),
@@ -51,15 +49,33 @@ pub(crate) async fn build_queue_embeds(queue: &[TrackHandle], page: usize) -> Ve
         if let Some(duration) = metadata.duration {
             message.push_str(" (");
             message.push_str(&format_duration(duration));
+            if !metadata.section.is_full() {
+                message.push_str(" ✂️ ");
+                message.push_str(&metadata.section.range());
+            }
             message.push(')');
         }
+        if metadata.autoplay {
+            message.push_str(" 📻 ");
+            message.push_str(locale.text("queue.autoplay"));
+        }
         message.push('\n');
+        // the first track is the one that is playing
+        if index == 0 {
+            if let Some(chapter) = current_chapter(track, filters).await {
+                let chapter = describe_chapter(&metadata.chapters, chapter, locale);
+                message.push_str("  📖 ");
+                message.push_str(&locale.format("queue.chapter", &[("chapter", &chapter)]));
+                message.push('\n');
+            }
+        }
     }
     message.push('\n');
 
-    let n_pages = queue.len().div_ceil(TRACKS_PER_PAGE);
+    let n_pages = queue.len().div_ceil(tracks_per_page);
     if n_pages > 1 {
-        message.push_str(&format!("page {}/{}", 1 + page, n_pages));
+        message
+            .push_str(&locale.format("queue.page", &[("page", &(1 + page)), ("pages", &n_pages)]));
     }
     vec![EmbedBuilder::new()
         .description(&message)
@@ -67,13 +83,13 @@ pub(crate) async fn build_queue_embeds(queue: &[TrackHandle], page: usize) -> Ve
         .build()]
 }
 
-pub(crate) fn build_action_row(page: usize, n_pages: usize) -> Vec<Component> {
+pub(crate) fn build_action_row(page: usize, n_pages: usize, locale: Locale) -> Vec<Component> {
     vec![Component::ActionRow(ActionRow {
         components: vec![
             Component::Button(Button {
                 custom_id: Some(format!("page:{}", page as i32 - 1)),
                 style: ButtonStyle::Primary,
-                label: Some("Previous page".to_string()),
+                label: Some(locale.text("queue.previous_page").to_string()),
                 emoji: Some(EmojiReactionType::Unicode {
                     name: "⬅️".to_string(),
                 }),
@@ -84,7 +100,7 @@ pub(crate) fn build_action_row(page: usize, n_pages: usize) -> Vec<Component> {
             Component::Button(Button {
                 custom_id: Some(format!("page:{page}")),
                 style: ButtonStyle::Primary,
-                label: Some("Refresh".to_string()),
+                label: Some(locale.text("queue.refresh").to_string()),
                 emoji: Some(EmojiReactionType::Unicode {
                     name: "🔄".to_string(),
                 }),
@@ -95,7 +111,7 @@ pub(crate) fn build_action_row(page: usize, n_pages: usize) -> Vec<Component> {
             Component::Button(Button {
                 custom_id: Some(format!("page:{}", page + 1)),
                 style: ButtonStyle::Primary,
-                label: Some("Next page".to_string()),
+                label: Some(locale.text("queue.next_page").to_string()),
                 emoji: Some(EmojiReactionType::Unicode {
                     name: "➡️".to_string(),
                 }),
@@ -103,60 +119,67 @@ pub(crate) fn build_action_row(page: usize, n_pages: usize) -> Vec<Component> {
                 disabled: page >= n_pages - 1,
                 sku_id: None,
             }),
+            like_button(None, locale),
         ],
     })]
 }
 
-pub(crate) async fn queue(
-    interaction: Box<InteractionCreate>,
-    state: State,
-) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-    tracing::debug!(
-        "queue command in guild {:?} in channel {:?} by {:?}",
-        interaction.guild_id,
-        interaction.channel,
-        interaction.author(),
-    );
-    let Some(guild_id) = interaction.guild_id else {
-        return Ok(());
-    };
+pub(crate) struct Queue;
 
-    let content = "Fetching queue".to_string();
-    let embeds = vec![EmbedBuilder::new()
-        .description(content)
-        .color(colors::YELLOW)
-        .build()];
-    let interaction_response_data = InteractionResponseDataBuilder::new()
-        .embeds(embeds)
-        .flags(MessageFlags::LOADING)
-        .build();
-    let response = InteractionResponse {
-        kind: InteractionResponseType::DeferredChannelMessageWithSource,
-        data: Some(interaction_response_data),
-    };
-    state
-        .http
-        .interaction(interaction.application_id)
-        .create_response(interaction.id, &interaction.token, &response)
-        .await?;
+#[async_trait]
+impl SlashCommand for Queue {
+    type Options = ();
 
-    let mut queue = Vec::new();
-    if let Some(call_lock) = state.songbird.get(guild_id) {
-        let call = call_lock.lock().await;
-        queue = call.queue().current_queue();
+    fn definition(&self) -> Command {
+        CommandBuilder::new("queue", "Print track queue", CommandType::ChatInput).build()
     }
 
-    let embeds = build_queue_embeds(&queue, 0).await;
-    let n_pages = queue.len().div_ceil(TRACKS_PER_PAGE);
-    let action_row = build_action_row(0, n_pages);
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        _options: (),
+    ) -> CommandResult {
+        tracing::debug!(
+            "queue command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
+
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        defer(&interaction, &state, locale.text("queue.fetching")).await?;
+
+        let mut queue = Vec::new();
+        if let Some(call_lock) = state.songbird.get(guild_id) {
+            let call = call_lock.lock().await;
+            queue = call.queue().current_queue();
+        }
+
+        let tracks_per_page = state.config.queue.tracks_per_page;
+        let filters = state
+            .guild_settings
+            .get(&guild_id)
+            .map(|settings| settings.filters)
+            .unwrap_or_default();
+        let embeds = build_queue_embeds(&queue, 0, tracks_per_page, filters, locale).await;
+        let n_pages = queue.len().div_ceil(tracks_per_page);
+        let action_row = build_action_row(0, n_pages, locale);
 
-    state
-        .http
-        .interaction(interaction.application_id)
-        .update_response(&interaction.token)
-        .embeds(Some(&embeds))
-        .components(Some(&action_row))
-        .await?;
+        let message = state
+            .http
+            .interaction(interaction.application_id)
+            .update_response(&interaction.token)
+            .embeds(Some(&embeds))
+            .components(Some(&action_row))
+            .await?
+            .model()
+            .await?;
+        remember(&interaction, &state, &message, MessageKind::Queue).await;
 
-    Ok(())
+        Ok(())
+    }
 }
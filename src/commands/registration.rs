@@ -0,0 +1,152 @@
+use crate::config::Config;
+use std::{collections::HashMap, error::Error};
+use tracing::info;
+use twilight_http::client::InteractionClient;
+use twilight_model::{
+    application::command::{Command, CommandOption},
+    id::{marker::GuildMarker, Id},
+};
+
+/// Registers `commands` globally, or only in the configured development guilds.
+///
+/// Registration is skipped where Discord already has the same set of commands.
+/// With `clear`, all global and development guild commands are removed first.
+pub(crate) async fn register_commands(
+    client: &InteractionClient<'_>,
+    config: &Config,
+    commands: &[Command],
+    clear: bool,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let dev_guilds: Vec<Id<GuildMarker>> = config
+        .discord
+        .dev_guilds
+        .iter()
+        .map(|&id| Id::new(id))
+        .collect();
+
+    if clear {
+        info!("Clearing global commands");
+        client.set_global_commands(&[]).await?;
+        for &guild_id in &dev_guilds {
+            info!("Clearing commands in guild {guild_id}");
+            client.set_guild_commands(guild_id, &[]).await?;
+        }
+    }
+
+    if dev_guilds.is_empty() {
+        let registered = client.global_commands().await?.models().await?;
+        if commands_match(&registered, commands) {
+            info!("Global commands are up to date");
+        } else {
+            info!("Registering global commands");
+            client.set_global_commands(commands).await?;
+        }
+    }
+
+    for guild_id in dev_guilds {
+        let registered = client.guild_commands(guild_id).await?.models().await?;
+        if commands_match(&registered, commands) {
+            info!("Commands in guild {guild_id} are up to date");
+        } else {
+            info!("Registering commands in guild {guild_id}");
+            client.set_guild_commands(guild_id, commands).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Compares commands ignoring ids, order and fields that Discord fills in with defaults.
+fn commands_match(registered: &[Command], wanted: &[Command]) -> bool {
+    if registered.len() != wanted.len() {
+        return false;
+    }
+    let mut registered: Vec<_> = registered.iter().map(normalize).collect();
+    let mut wanted: Vec<_> = wanted.iter().map(normalize).collect();
+    registered.sort_by(|a, b| a.name.cmp(&b.name));
+    wanted.sort_by(|a, b| a.name.cmp(&b.name));
+    registered == wanted
+}
+
+#[allow(deprecated)]
+fn normalize(command: &Command) -> Command {
+    let mut command = command.clone();
+    command.application_id = None;
+    command.guild_id = None;
+    command.id = None;
+    command.version = Id::new(1);
+    command.contexts = None;
+    command.integration_types = None;
+    command.dm_permission = None;
+    command.nsfw = command.nsfw.filter(|&nsfw| nsfw);
+    command.name_localizations = non_empty(command.name_localizations);
+    command.description_localizations = non_empty(command.description_localizations);
+    command.options = command.options.iter().map(normalize_option).collect();
+    command
+}
+
+fn normalize_option(option: &CommandOption) -> CommandOption {
+    let mut option = option.clone();
+    option.autocomplete = option.autocomplete.filter(|&autocomplete| autocomplete);
+    option.required = option.required.filter(|&required| required);
+    option.channel_types = option.channel_types.filter(|types| !types.is_empty());
+    option.choices = option.choices.filter(|choices| !choices.is_empty());
+    option.name_localizations = non_empty(option.name_localizations);
+    option.description_localizations = non_empty(option.description_localizations);
+    option.options = option
+        .options
+        .filter(|options| !options.is_empty())
+        .map(|options| options.iter().map(normalize_option).collect());
+    option
+}
+
+fn non_empty(map: Option<HashMap<String, String>>) -> Option<HashMap<String, String>> {
+    map.filter(|map| !map.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::get_chat_commands;
+
+    /// Mimics what Discord returns for registered commands.
+    #[allow(deprecated)]
+    fn registered(commands: &[Command]) -> Vec<Command> {
+        commands
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, command)| {
+                let mut command = command.clone();
+                command.id = Some(Id::new(100 + i as u64));
+                command.application_id = Some(Id::new(42));
+                command.version = Id::new(7);
+                command.dm_permission = Some(true);
+                command.nsfw = Some(false);
+                for option in &mut command.options {
+                    option.required = option.required.or(Some(false));
+                }
+                command
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_commands_match() {
+        let commands = get_chat_commands();
+        assert!(commands_match(&registered(&commands), &commands));
+    }
+
+    #[test]
+    fn test_commands_differ() {
+        let commands = get_chat_commands();
+
+        let mut changed = registered(&commands);
+        changed[0].description.push('!');
+        assert!(!commands_match(&changed, &commands));
+
+        let removed = registered(&commands[1..]);
+        assert!(!commands_match(&removed, &commands));
+
+        assert!(!commands_match(&[], &commands));
+    }
+}
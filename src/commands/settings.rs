@@ -0,0 +1,319 @@
+use crate::commands::framework::{
+    confirm, optional, CommandResult, FromOptions, OptionError, Options, SlashCommand,
+};
+use crate::commands::CommandError;
+use crate::db::{self, guild::QueueLimits};
+use crate::i18n::Locale;
+use crate::limits;
+use crate::state::State;
+use crate::utils::format_duration;
+use async_trait::async_trait;
+use std::time::Duration;
+use twilight_model::{
+    application::command::{Command, CommandType},
+    channel::ChannelType,
+    gateway::payload::incoming::InteractionCreate,
+    guild::Permissions,
+    id::{marker::ChannelMarker, Id},
+};
+use twilight_util::builder::command::{
+    BooleanBuilder, ChannelBuilder, CommandBuilder, IntegerBuilder, StringBuilder,
+    SubCommandBuilder,
+};
+
+/// Longest time after which messages can be cleaned up, a week.
+const MAX_CLEANUP_MINUTES: i64 = 7 * 24 * 60;
+
+/// Largest number of tracks that the queue limits can be set to.
+const MAX_TRACKS_LIMIT: i64 = 1000;
+
+/// Longest track length that can be set as a limit, a day.
+const MAX_DURATION_MINUTES: i64 = 24 * 60;
+
+pub(crate) struct Settings {
+    /// Commands whose confirmations can be made public or private.
+    commands: Vec<String>,
+}
+
+impl Settings {
+    pub(crate) fn new(commands: Vec<String>) -> Self {
+        Self { commands }
+    }
+}
+
+pub(crate) enum SettingsOptions {
+    Quiet(bool),
+    /// `None` to post in the channel the command came from again.
+    Announce(Option<Id<ChannelMarker>>),
+    /// `None` resets the command to its default.
+    Visibility {
+        command: String,
+        public: Option<bool>,
+    },
+    /// Minutes after which messages of the bot are deleted, `None` to keep them.
+    Cleanup(Option<i64>),
+    /// Limits that are left out are lifted.
+    Limits(QueueLimits),
+    Fair(bool),
+}
+
+impl FromOptions for SettingsOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        match options.subcommand()? {
+            ("quiet", options) => Ok(Self::Quiet(options.boolean("enabled")?)),
+            ("announce", options) => Ok(Self::Announce(optional(options.channel("channel"))?)),
+            ("visibility", options) => {
+                let command = options.string("command")?;
+                let public = match options.string("visibility")?.as_str() {
+                    "public" => Some(true),
+                    "private" => Some(false),
+                    "default" => None,
+                    _ => return Err(OptionError::InvalidType("visibility".to_string())),
+                };
+                Ok(Self::Visibility { command, public })
+            }
+            ("cleanup", options) => {
+                let minutes = optional(options.integer("minutes"))?;
+                if minutes.is_some_and(|minutes| !(1..=MAX_CLEANUP_MINUTES).contains(&minutes)) {
+                    return Err(OptionError::InvalidType("minutes".to_string()));
+                }
+                Ok(Self::Cleanup(minutes))
+            }
+            ("limits", options) => {
+                let limit = |name: &str, max: i64| {
+                    let value = optional(options.integer(name))?;
+                    if value.is_some_and(|value| !(1..=max).contains(&value)) {
+                        return Err(OptionError::InvalidType(name.to_string()));
+                    }
+                    Ok(value)
+                };
+                Ok(Self::Limits(QueueLimits {
+                    tracks_per_user: limit("tracks_per_user", MAX_TRACKS_LIMIT)?,
+                    playlist_size: limit("playlist_size", MAX_TRACKS_LIMIT)?,
+                    track_duration_secs: limit("track_minutes", MAX_DURATION_MINUTES)?
+                        .map(|minutes| minutes * 60),
+                }))
+            }
+            ("fair", options) => Ok(Self::Fair(options.boolean("enabled")?)),
+            (name, _) => Err(OptionError::InvalidType(name.to_string())),
+        }
+    }
+}
+
+/// Describes the queue `limits` of a guild.
+fn describe_limits(limits: QueueLimits, locale: Locale) -> String {
+    let describe =
+        |limit: Option<String>| limit.unwrap_or(locale.text("settings.no_limit").to_string());
+    let duration = limits
+        .track_duration_secs
+        .map(|secs| format_duration(Duration::from_secs(secs as u64)));
+    [
+        locale.text("settings.limits").to_string(),
+        locale.format(
+            "settings.limit_tracks_per_user",
+            &[(
+                "limit",
+                &describe(limits.tracks_per_user.map(|n| n.to_string())),
+            )],
+        ),
+        locale.format(
+            "settings.limit_playlist_size",
+            &[(
+                "limit",
+                &describe(limits.playlist_size.map(|n| n.to_string())),
+            )],
+        ),
+        locale.format(
+            "settings.limit_track_duration",
+            &[("limit", &describe(duration))],
+        ),
+    ]
+    .join("\n")
+}
+
+#[async_trait]
+impl SlashCommand for Settings {
+    type Options = SettingsOptions;
+
+    fn definition(&self) -> Command {
+        let commands = self
+            .commands
+            .iter()
+            .map(|command| (command.as_str(), command.as_str()));
+        CommandBuilder::new(
+            "settings",
+            "Configure how the bot responds in this server",
+            CommandType::ChatInput,
+        )
+        .option(
+            SubCommandBuilder::new("quiet", "Only respond privately to whoever used a command")
+                .option(BooleanBuilder::new("enabled", "Enable quiet mode").required(true)),
+        )
+        .option(
+            SubCommandBuilder::new(
+                "announce",
+                "Post queue events in a channel instead of where a command was used",
+            )
+            .option(
+                ChannelBuilder::new("channel", "Channel for queue events, none to reset")
+                    .channel_types([ChannelType::GuildText, ChannelType::GuildAnnouncement]),
+            ),
+        )
+        .option(
+            SubCommandBuilder::new(
+                "visibility",
+                "Make confirmations of a command public or private",
+            )
+            .option(
+                StringBuilder::new("command", "Command to configure")
+                    .required(true)
+                    .choices(commands),
+            )
+            .option(
+                StringBuilder::new("visibility", "Who sees the confirmations")
+                    .required(true)
+                    .choices([
+                        ("Everyone", "public"),
+                        ("Only whoever used the command", "private"),
+                        ("Default", "default"),
+                    ]),
+            ),
+        )
+        .option(
+            SubCommandBuilder::new("cleanup", "Delete messages of the bot after a while").option(
+                IntegerBuilder::new("minutes", "Minutes to keep messages, none to keep them")
+                    .min_value(1)
+                    .max_value(MAX_CLEANUP_MINUTES),
+            ),
+        )
+        .option(
+            SubCommandBuilder::new(
+                "limits",
+                "Limit what members can add to the queue, leave options out for no limit",
+            )
+            .option(
+                IntegerBuilder::new("tracks_per_user", "Tracks a member can have in the queue")
+                    .min_value(1)
+                    .max_value(MAX_TRACKS_LIMIT),
+            )
+            .option(
+                IntegerBuilder::new("playlist_size", "Tracks that are added of a playlist")
+                    .min_value(1)
+                    .max_value(MAX_TRACKS_LIMIT),
+            )
+            .option(
+                IntegerBuilder::new("track_minutes", "Longest track in minutes")
+                    .min_value(1)
+                    .max_value(MAX_DURATION_MINUTES),
+            ),
+        )
+        .option(
+            SubCommandBuilder::new(
+                "fair",
+                "Take turns between the members who added tracks to the queue",
+            )
+            .option(BooleanBuilder::new("enabled", "Enable the fair queue").required(true)),
+        )
+        .build()
+    }
+
+    fn required_permissions(&self) -> Option<Permissions> {
+        Some(Permissions::MANAGE_GUILD)
+    }
+
+    fn public_by_default(&self) -> bool {
+        false
+    }
+
+    fn configurable_visibility(&self) -> bool {
+        false
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: SettingsOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "settings command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        let guild = db::track::Guild::new(guild_id.to_string());
+        let locale = Locale::for_interaction(&interaction, &state).await;
+
+        let message = match options {
+            SettingsOptions::Quiet(quiet) => {
+                db::guild::set_guild_quiet(&state.pool, guild, quiet).await?;
+                if quiet {
+                    locale.text("settings.quiet_enabled").to_string()
+                } else {
+                    locale.text("settings.quiet_disabled").to_string()
+                }
+            }
+            SettingsOptions::Announce(channel_id) => {
+                db::guild::set_guild_announce_channel(
+                    &state.pool,
+                    guild,
+                    channel_id.map(|id| id.to_string()),
+                )
+                .await?;
+                match channel_id {
+                    Some(channel_id) => locale.format(
+                        "settings.announce_set",
+                        &[("channel", &format!("<#{channel_id}>"))],
+                    ),
+                    None => locale.text("settings.announce_reset").to_string(),
+                }
+            }
+            SettingsOptions::Visibility { command, public } => {
+                if !self.commands.contains(&command) {
+                    return Err(OptionError::InvalidType("command".to_string()).into());
+                }
+                db::guild::set_command_visibility(
+                    &state.pool,
+                    guild_id.to_string(),
+                    command.clone(),
+                    public,
+                )
+                .await?;
+                let key = match public {
+                    Some(true) => "settings.visibility_public",
+                    Some(false) => "settings.visibility_private",
+                    None => "settings.visibility_default",
+                };
+                locale.format(key, &[("command", &command)])
+            }
+            SettingsOptions::Cleanup(minutes) => {
+                db::guild::set_guild_cleanup_ttl(&state.pool, guild, minutes.map(|m| m * 60))
+                    .await?;
+                match minutes {
+                    Some(minutes) => {
+                        locale.format("settings.cleanup_set", &[("minutes", &minutes)])
+                    }
+                    None => locale.text("settings.cleanup_reset").to_string(),
+                }
+            }
+            SettingsOptions::Limits(limits) => {
+                db::guild::set_queue_limits(&state.pool, guild, limits).await?;
+                describe_limits(limits, locale)
+            }
+            SettingsOptions::Fair(fair) => {
+                db::guild::set_guild_fair_queue(&state.pool, guild, fair).await?;
+                if fair {
+                    limits::reorder(&state, guild_id).await;
+                    locale.text("settings.fair_enabled").to_string()
+                } else {
+                    locale.text("settings.fair_disabled").to_string()
+                }
+            }
+        };
+        confirm(&interaction, &state, message).await
+    }
+}
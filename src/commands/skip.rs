@@ -1,45 +1,53 @@
+use crate::commands::framework::{announce, CommandResult, SlashCommand};
+use crate::commands::CommandError;
+use crate::i18n::Locale;
 use crate::state::State;
-use std::error::Error;
+use async_trait::async_trait;
 use twilight_model::{
+    application::command::{Command, CommandType},
     gateway::payload::incoming::InteractionCreate,
-    http::interaction::{InteractionResponse, InteractionResponseType},
 };
-use twilight_util::builder::InteractionResponseDataBuilder;
+use twilight_util::builder::command::CommandBuilder;
 
-pub(crate) async fn skip(
-    interaction: Box<InteractionCreate>,
-    state: State,
-) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-    tracing::debug!(
-        "skip command in guild {:?} in channel {:?} by {:?}",
-        interaction.guild_id,
-        interaction.channel,
-        interaction.author(),
-    );
+pub(crate) struct Skip;
 
-    let Some(guild_id) = interaction.guild_id else {
-        return Ok(());
-    };
+#[async_trait]
+impl SlashCommand for Skip {
+    type Options = ();
 
-    if let Some(call_lock) = state.songbird.get(guild_id) {
-        let call = call_lock.lock().await;
-        call.queue().skip()?;
+    fn definition(&self) -> Command {
+        CommandBuilder::new("skip", "Skip track", CommandType::ChatInput).build()
     }
 
-    let interaction_response_data = InteractionResponseDataBuilder::new()
-        .content("Skipped a track")
-        .build();
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        _options: (),
+    ) -> CommandResult {
+        tracing::debug!(
+            "skip command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
 
-    let response = InteractionResponse {
-        kind: InteractionResponseType::ChannelMessageWithSource,
-        data: Some(interaction_response_data),
-    };
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
 
-    state
-        .http
-        .interaction(interaction.application_id)
-        .create_response(interaction.id, &interaction.token, &response)
-        .await?;
+        let call_lock = state
+            .songbird
+            .get(guild_id)
+            .ok_or(CommandError::NothingPlaying)?;
+        let call = call_lock.lock().await;
+        if call.queue().is_empty() {
+            return Err(CommandError::NothingPlaying.into());
+        }
+        call.queue().skip()?;
+        drop(call);
 
-    Ok(())
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        announce(&interaction, &state, locale.text("skip.skipped")).await
+    }
 }
@@ -1,52 +1,52 @@
+use async_trait::async_trait;
 use twilight_model::{
-    channel::message::MessageFlags,
+    application::command::{Command, CommandType},
     gateway::payload::incoming::InteractionCreate,
-    http::interaction::{InteractionResponse, InteractionResponseType},
 };
-use twilight_util::builder::InteractionResponseDataBuilder;
+use twilight_util::builder::command::CommandBuilder;
 
+use crate::commands::framework::{announce, CommandResult, SlashCommand};
+use crate::commands::CommandError;
+use crate::i18n::Locale;
 use crate::state::State;
-use std::error::Error;
-
-pub(crate) async fn stop(
-    interaction: Box<InteractionCreate>,
-    state: State,
-) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-    tracing::debug!(
-        "stop command in guild {:?} in channel {:?} by {:?}",
-        interaction.guild_id,
-        interaction.channel,
-        interaction.author(),
-    );
-
-    let Some(guild_id) = interaction.guild_id else {
-        return Ok(());
-    };
-
-    state.guild_settings.entry(guild_id).and_modify(|settings| {
-        settings.loop_queue = false;
-    });
-
-    if let Some(call_lock) = state.songbird.get(guild_id) {
-        let call = call_lock.lock().await;
-        call.queue().stop();
-    }
 
-    let interaction_response_data = InteractionResponseDataBuilder::new()
-        .content("Stopped the track and cleared the queue")
-        .flags(MessageFlags::EPHEMERAL)
-        .build();
+pub(crate) struct Stop;
 
-    let response = InteractionResponse {
-        kind: InteractionResponseType::ChannelMessageWithSource,
-        data: Some(interaction_response_data),
-    };
+#[async_trait]
+impl SlashCommand for Stop {
+    type Options = ();
 
-    state
-        .http
-        .interaction(interaction.application_id)
-        .create_response(interaction.id, &interaction.token, &response)
-        .await?;
+    fn definition(&self) -> Command {
+        CommandBuilder::new("stop", "Stop playing", CommandType::ChatInput).build()
+    }
 
-    Ok(())
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        _options: (),
+    ) -> CommandResult {
+        tracing::debug!(
+            "stop command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
+
+        state.guild_settings.entry(guild_id).and_modify(|settings| {
+            settings.loop_queue = false;
+        });
+
+        if let Some(call_lock) = state.songbird.get(guild_id) {
+            let call = call_lock.lock().await;
+            call.queue().stop();
+        }
+
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        announce(&interaction, &state, locale.text("stop.stopped")).await
+    }
 }
@@ -0,0 +1,147 @@
+use crate::commands::framework::{
+    announce, optional, CommandResult, FromOptions, OptionError, Options, SlashCommand,
+};
+use crate::commands::CommandError;
+use crate::db::{self, guild::SkipSettings};
+use crate::i18n::Locale;
+use crate::state::State;
+use async_trait::async_trait;
+use twilight_model::{
+    application::command::{Command, CommandType},
+    gateway::payload::incoming::InteractionCreate,
+};
+use twilight_util::builder::command::{BooleanBuilder, CommandBuilder};
+
+pub(crate) struct Trim;
+
+pub(crate) struct TrimOptions {
+    silence: Option<bool>,
+    segments: Option<bool>,
+}
+
+impl FromOptions for TrimOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        Ok(Self {
+            silence: optional(options.boolean("silence"))?,
+            segments: optional(options.boolean("segments"))?,
+        })
+    }
+}
+
+impl TrimOptions {
+    fn apply(&self, settings: SkipSettings) -> SkipSettings {
+        SkipSettings {
+            silence: self.silence.unwrap_or(settings.silence),
+            segments: self.segments.unwrap_or(settings.segments),
+        }
+    }
+}
+
+/// Lists what is skipped.
+fn describe(settings: SkipSettings, locale: Locale) -> String {
+    let mut lines = Vec::new();
+    if settings.silence {
+        lines.push(locale.text("trim.silence"));
+    }
+    if settings.segments {
+        lines.push(locale.text("trim.segments"));
+    }
+    if lines.is_empty() {
+        return locale.text("trim.off").to_string();
+    }
+    lines.join("\n")
+}
+
+#[async_trait]
+impl SlashCommand for Trim {
+    type Options = TrimOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new(
+            "trim",
+            "Skip silence and parts of tracks that are not music, or show what is skipped",
+            CommandType::ChatInput,
+        )
+        .option(BooleanBuilder::new(
+            "silence",
+            "Skip silence at the start and the end of tracks",
+        ))
+        .option(BooleanBuilder::new(
+            "segments",
+            "Skip sponsor messages, intros and other segments marked on SponsorBlock",
+        ))
+        .build()
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: TrimOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "trim command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        let previous = db::guild::skip_settings(&state.pool, guild_id.to_string()).await?;
+        let settings = options.apply(previous);
+        if settings != previous {
+            db::guild::set_skip_settings(
+                &state.pool,
+                db::track::Guild::new(guild_id.to_string()),
+                settings,
+            )
+            .await?;
+        }
+
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        announce(&interaction, &state, describe(settings, locale)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let options = TrimOptions {
+            silence: Some(true),
+            segments: None,
+        };
+        let settings = SkipSettings {
+            silence: false,
+            segments: true,
+        };
+        assert_eq!(
+            options.apply(settings),
+            SkipSettings {
+                silence: true,
+                segments: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(
+            describe(SkipSettings::default(), Locale::En),
+            "Tracks play in full."
+        );
+        let settings = SkipSettings {
+            silence: true,
+            segments: true,
+        };
+        assert_eq!(
+            describe(settings, Locale::En),
+            "Silence at the start and the end of tracks is skipped.\n\
+             Sponsor messages, intros and other parts that are not music are skipped."
+        );
+    }
+}
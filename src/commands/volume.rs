@@ -0,0 +1,131 @@
+use crate::commands::framework::{
+    announce, CommandResult, FromOptions, OptionError, Options, SlashCommand,
+};
+use crate::commands::CommandError;
+use crate::i18n::Locale;
+use crate::metadata::Metadata;
+use crate::state::{Settings, State};
+use async_trait::async_trait;
+use songbird::tracks::ControlError;
+use twilight_model::{
+    application::command::{Command, CommandType},
+    gateway::payload::incoming::InteractionCreate,
+    id::{marker::GuildMarker, Id},
+};
+use twilight_util::builder::command::{CommandBuilder, IntegerBuilder};
+
+/// Loudest volume that can be set, in percent.
+const MAX_VOLUME: i64 = 200;
+
+pub(crate) struct Volume;
+
+pub(crate) struct VolumeOptions {
+    percent: i64,
+}
+
+impl FromOptions for VolumeOptions {
+    fn from_options(options: &Options<'_>) -> Result<Self, OptionError> {
+        Ok(Self {
+            percent: options.integer("percent")?.clamp(0, MAX_VOLUME),
+        })
+    }
+}
+
+/// Volume to play a track measured to need `gain` at, with the `settings` of its guild.
+///
+/// The gain evens out the loudness of tracks, unless the normalization filter does that
+/// already while they play.
+fn volume(settings: Option<&Settings>, gain: f32) -> f32 {
+    match settings {
+        Some(settings) if settings.filters.normalize => settings.volume,
+        Some(settings) => settings.volume * gain,
+        None => gain,
+    }
+}
+
+/// Volume to play the track of `metadata` at in `guild_id`.
+pub(crate) fn track_volume(state: &State, guild_id: Id<GuildMarker>, metadata: &Metadata) -> f32 {
+    let settings = state.guild_settings.get(&guild_id);
+    volume(settings.as_deref(), metadata.gain)
+}
+
+/// Sets the volume of every track in the queue of `guild_id` after its settings changed.
+pub(crate) async fn update_volumes(
+    state: &State,
+    guild_id: Id<GuildMarker>,
+) -> Result<(), ControlError> {
+    let Some(call_lock) = state.songbird.get(guild_id) else {
+        return Ok(());
+    };
+    let queue = call_lock.lock().await.queue().current_queue();
+    for handle in queue {
+        handle.set_volume(track_volume(state, guild_id, &handle.data::<Metadata>()))?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl SlashCommand for Volume {
+    type Options = VolumeOptions;
+
+    fn definition(&self) -> Command {
+        CommandBuilder::new("volume", "Change the volume", CommandType::ChatInput)
+            .option(
+                IntegerBuilder::new("percent", "Volume in percent of the normal volume")
+                    .required(true)
+                    .min_value(0)
+                    .max_value(MAX_VOLUME),
+            )
+            .build()
+    }
+
+    async fn execute(
+        &self,
+        interaction: Box<InteractionCreate>,
+        state: State,
+        options: VolumeOptions,
+    ) -> CommandResult {
+        tracing::debug!(
+            "volume command in guild {:?} in channel {:?} by {:?}",
+            interaction.guild_id,
+            interaction.channel,
+            interaction.author(),
+        );
+
+        let Some(guild_id) = interaction.guild_id else {
+            return Err(CommandError::NotInGuild.into());
+        };
+        state.guild_settings.entry(guild_id).or_default().volume = options.percent as f32 / 100.0;
+        update_volumes(&state, guild_id).await?;
+
+        let locale = Locale::for_interaction(&interaction, &state).await;
+        let message = locale.format("volume.set", &[("percent", &options.percent)]);
+        announce(&interaction, &state, message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::Filters;
+
+    #[test]
+    fn test_volume() {
+        assert_eq!(volume(None, 0.5), 0.5);
+        let settings = Settings {
+            volume: 0.8,
+            ..Settings::new()
+        };
+        assert_eq!(volume(Some(&settings), 0.5), 0.4);
+        // the normalization filter corrects the loudness instead of the measured gain
+        let settings = Settings {
+            filters: Filters {
+                normalize: true,
+                ..Filters::new()
+            },
+            ..settings
+        };
+        assert_eq!(volume(Some(&settings), 0.5), 0.8);
+        assert_eq!(volume(Some(&settings), 2.0), 0.8);
+    }
+}
@@ -0,0 +1,320 @@
+use serde::Deserialize;
+use std::{env, error::Error, fmt, fs, io, ops::Sub, time::Duration};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Config {
+    pub(crate) discord: DiscordConfig,
+    pub(crate) database: DatabaseConfig,
+    pub(crate) http: HttpConfig,
+    pub(crate) queue: QueueConfig,
+    pub(crate) ytdlp: YtDlpConfig,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct DiscordConfig {
+    pub(crate) token: String,
+    pub(crate) app_id: u64,
+    /// Register commands only in these guilds instead of globally, for development.
+    pub(crate) dev_guilds: Vec<u64>,
+}
+
+/// Leaves out the token, so logging the configuration or the state does not leak it.
+impl fmt::Debug for DiscordConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DiscordConfig")
+            .field("token", &"<redacted>")
+            .field("app_id", &self.app_id)
+            .field("dev_guilds", &self.dev_guilds)
+            .finish()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct DatabaseConfig {
+    pub(crate) url: String,
+    pub(crate) max_connections: u32,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            max_connections: 5,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct HttpConfig {
+    pub(crate) connect_timeout_secs: u64,
+    pub(crate) timeout_secs: u64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            timeout_secs: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct QueueConfig {
+    pub(crate) tracks_per_page: usize,
+    pub(crate) preload_offset_secs: u64,
+    /// Age after which the pagination buttons of a queue are disabled.
+    pub(crate) pagination_timeout_secs: u64,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            tracks_per_page: 5,
+            preload_offset_secs: 5,
+            pagination_timeout_secs: 900,
+        }
+    }
+}
+
+impl QueueConfig {
+    pub(crate) fn pagination_timeout(&self) -> Duration {
+        Duration::from_secs(self.pagination_timeout_secs)
+    }
+
+    /// Position in a track of length `duration` at which the next track is preloaded.
+    pub(crate) fn preload_position(&self, duration: Option<Duration>) -> Option<Duration> {
+        let offset = Duration::from_secs(self.preload_offset_secs);
+        duration.map(|duration| -> Duration {
+            if duration.as_secs() > self.preload_offset_secs {
+                duration.sub(offset)
+            } else {
+                duration
+            }
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct YtDlpConfig {
+    pub(crate) path: String,
+    pub(crate) args: Vec<String>,
+    pub(crate) timeout_secs: u64,
+    pub(crate) max_concurrent: usize,
+}
+
+impl Default for YtDlpConfig {
+    fn default() -> Self {
+        Self {
+            path: "yt-dlp".to_string(),
+            args: Vec::new(),
+            timeout_secs: 60,
+            max_concurrent: 4,
+        }
+    }
+}
+
+type ConfigResult<T> = Result<T, Box<dyn Error + Send + Sync + 'static>>;
+
+impl Config {
+    /// Loads the configuration file named by `CONFIG` (default `config.toml`),
+    /// applies environment variable overrides and validates the result.
+    ///
+    /// A missing file is fine as long as the environment provides the required values.
+    pub(crate) fn load() -> ConfigResult<Self> {
+        let path = env::var("CONFIG").unwrap_or_else(|_| "config.toml".to_string());
+        let mut config = match fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content).map_err(|e| format!("{path}: {e}"))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(format!("could not read {path}: {e}").into()),
+        };
+        config.apply_env(|name| env::var(name).ok())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn parse(content: &str) -> ConfigResult<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> ConfigResult<()> {
+        fn parse<T: std::str::FromStr>(name: &str, value: String) -> ConfigResult<T> {
+            value
+                .parse()
+                .map_err(|_| format!("{name} has an invalid value: {value:?}").into())
+        }
+
+        if let Some(token) = var("DISCORD_TOKEN") {
+            self.discord.token = token;
+        }
+        if let Some(app_id) = var("DISCORD_APP_ID") {
+            self.discord.app_id = parse("DISCORD_APP_ID", app_id)?;
+        }
+        if let Some(dev_guilds) = var("DEV_GUILDS") {
+            self.discord.dev_guilds = dev_guilds
+                .split(',')
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(|id| parse("DEV_GUILDS", id.to_string()))
+                .collect::<ConfigResult<_>>()?;
+        }
+        if let Some(url) = var("DATABASE_URL") {
+            self.database.url = url;
+        }
+        if let Some(max_connections) = var("DATABASE_MAX_CONNECTIONS") {
+            self.database.max_connections = parse("DATABASE_MAX_CONNECTIONS", max_connections)?;
+        }
+        if let Some(path) = var("YTDLP_PATH") {
+            self.ytdlp.path = path;
+        }
+        if let Some(args) = var("YTDLP_ARGS") {
+            // split like a shell, so paths with spaces can be quoted
+            self.ytdlp.args = shlex::split(&args)
+                .ok_or_else(|| format!("YTDLP_ARGS has an invalid value: {args:?}"))?;
+        }
+        if let Some(timeout) = var("YTDLP_TIMEOUT") {
+            self.ytdlp.timeout_secs = parse("YTDLP_TIMEOUT", timeout)?;
+        }
+        if let Some(max_concurrent) = var("YTDLP_MAX_CONCURRENT") {
+            self.ytdlp.max_concurrent = parse("YTDLP_MAX_CONCURRENT", max_concurrent)?;
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> ConfigResult<()> {
+        if self.discord.token.is_empty() {
+            return Err("discord.token is not set (or DISCORD_TOKEN)".into());
+        }
+        if self.discord.app_id == 0 {
+            return Err("discord.app_id is not set (or DISCORD_APP_ID)".into());
+        }
+        if self.discord.dev_guilds.contains(&0) {
+            return Err("discord.dev_guilds must contain guild ids".into());
+        }
+        if self.database.url.is_empty() {
+            return Err("database.url is not set (or DATABASE_URL)".into());
+        }
+        if self.database.max_connections == 0 {
+            return Err("database.max_connections must be at least 1".into());
+        }
+        if !(1..=25).contains(&self.queue.tracks_per_page) {
+            return Err("queue.tracks_per_page must be between 1 and 25".into());
+        }
+        if self.queue.pagination_timeout_secs == 0 {
+            return Err("queue.pagination_timeout_secs must be at least 1".into());
+        }
+        if self.ytdlp.timeout_secs == 0 {
+            return Err("ytdlp.timeout_secs must be at least 1".into());
+        }
+        if self.ytdlp.max_concurrent == 0 {
+            return Err("ytdlp.max_concurrent must be at least 1".into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const EXAMPLE: &str = include_str!("../config.example.toml");
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        move |name| vars.get(name).map(|v| v.to_string())
+    }
+
+    #[test]
+    fn test_example_is_valid() {
+        let config = Config::parse(EXAMPLE).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.database.max_connections, 5);
+        assert_eq!(config.queue.tracks_per_page, 5);
+        assert_eq!(config.ytdlp.path, "yt-dlp");
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.http.connect_timeout_secs, 10);
+        assert_eq!(config.http.timeout_secs, 3600);
+        assert_eq!(config.queue.preload_offset_secs, 5);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let mut config = Config::parse(EXAMPLE).unwrap();
+        config
+            .apply_env(env(&[
+                ("DISCORD_TOKEN", "secret"),
+                ("DISCORD_APP_ID", "42"),
+                (
+                    "YTDLP_ARGS",
+                    "--cookies '/data/my cookies.txt' --no-warnings",
+                ),
+                ("YTDLP_TIMEOUT", "5"),
+                ("DEV_GUILDS", "1234, 5678"),
+            ]))
+            .unwrap();
+        assert_eq!(config.discord.token, "secret");
+        assert_eq!(config.discord.app_id, 42);
+        assert_eq!(
+            config.ytdlp.args,
+            ["--cookies", "/data/my cookies.txt", "--no-warnings"]
+        );
+        assert_eq!(config.ytdlp.timeout_secs, 5);
+        assert_eq!(config.discord.dev_guilds, [1234, 5678]);
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_debug_redacts_token() {
+        let mut config = Config::parse(EXAMPLE).unwrap();
+        config.discord.token = "secret-token".to_string();
+        let debug = format!("{config:?}");
+        assert!(!debug.contains("secret-token"));
+        assert!(debug.contains("<redacted>"));
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let mut config = Config::parse(EXAMPLE).unwrap();
+        let e = config
+            .apply_env(env(&[("DISCORD_APP_ID", "ohrwurm")]))
+            .unwrap_err();
+        assert!(e.to_string().contains("DISCORD_APP_ID"));
+        let e = config
+            .apply_env(env(&[("YTDLP_ARGS", "--cookies '/data/cookies.txt")]))
+            .unwrap_err();
+        assert!(e.to_string().contains("YTDLP_ARGS"));
+
+        let e = Config::parse("[queue]\ntracks_per_pages = 5").unwrap_err();
+        assert!(e.to_string().contains("tracks_per_pages"));
+
+        let mut config = Config::parse(EXAMPLE).unwrap();
+        config.queue.tracks_per_page = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_preload_position() {
+        let queue = QueueConfig::default();
+        assert_eq!(
+            queue.preload_position(Some(Duration::from_secs(60))),
+            Some(Duration::from_secs(55))
+        );
+        assert_eq!(
+            queue.preload_position(Some(Duration::from_secs(3))),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(queue.preload_position(None), None);
+    }
+}
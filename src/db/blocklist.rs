@@ -0,0 +1,172 @@
+use sqlx::FromRow;
+
+/// A pattern that tracks are blocked by in a guild.
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub(crate) struct BlockRule {
+    /// What the pattern is matched against, `url`, `channel` or `keyword`.
+    pub(crate) kind: String,
+    pub(crate) pattern: String,
+    /// How often the rule blocked a track.
+    pub(crate) blocked: i64,
+}
+
+/// The rules of `guild_id`, in the order they were added.
+pub(crate) async fn block_rules(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+) -> Result<Vec<BlockRule>, sqlx::Error> {
+    let query = r#"
+        SELECT
+            blocklist.kind,
+            blocklist.pattern,
+            (
+                SELECT COUNT(*) FROM blocked_attempts
+                WHERE blocked_attempts.guild_id = blocklist.guild_id
+                    AND blocked_attempts.kind = blocklist.kind
+                    AND blocked_attempts.pattern = blocklist.pattern
+            ) AS blocked
+        FROM blocklist
+        WHERE blocklist.guild_id = ?
+        ORDER BY blocklist.id
+        "#;
+    sqlx::query_as(query).bind(guild_id).fetch_all(pool).await
+}
+
+/// Adds a rule to the blocklist of `guild_id`. Returns `false` if it exists already.
+pub(crate) async fn add_block_rule(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+    kind: String,
+    pattern: String,
+    created_by: String,
+) -> Result<bool, sqlx::Error> {
+    let query = r#"
+        INSERT INTO blocklist (guild_id, kind, pattern, created_by, created)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT DO NOTHING
+        "#;
+    let res = sqlx::query(query)
+        .bind(guild_id)
+        .bind(kind)
+        .bind(pattern)
+        .bind(created_by)
+        .bind(chrono::offset::Utc::now())
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Removes a rule from the blocklist of `guild_id`. Returns `false` if there was none.
+pub(crate) async fn remove_block_rule(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+    kind: String,
+    pattern: String,
+) -> Result<bool, sqlx::Error> {
+    let query = r#"
+        DELETE FROM blocklist WHERE guild_id = ? AND kind = ? AND pattern = ?
+        "#;
+    let res = sqlx::query(query)
+        .bind(guild_id)
+        .bind(kind)
+        .bind(pattern)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// A track that `user_id` tried to add to the queue of `guild_id`, but a rule blocked.
+#[derive(Debug)]
+pub(crate) struct BlockedAttempt {
+    pub(crate) guild_id: String,
+    pub(crate) user_id: String,
+    pub(crate) kind: String,
+    pub(crate) pattern: String,
+    pub(crate) url: String,
+    pub(crate) title: String,
+}
+
+pub(crate) async fn log_blocked_attempt(
+    pool: &sqlx::SqlitePool,
+    attempt: BlockedAttempt,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        INSERT INTO blocked_attempts (guild_id, user_id, kind, pattern, url, title, created)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#;
+    sqlx::query(query)
+        .bind(attempt.guild_id)
+        .bind(attempt.user_id)
+        .bind(attempt.kind)
+        .bind(attempt.pattern)
+        .bind(attempt.url)
+        .bind(attempt.title)
+        .bind(chrono::offset::Utc::now())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::pool;
+
+    #[tokio::test]
+    async fn test_block_rules() {
+        let pool = pool().await;
+        let add = |guild_id: &str, kind: &str, pattern: &str| {
+            add_block_rule(
+                &pool,
+                guild_id.to_string(),
+                kind.to_string(),
+                pattern.to_string(),
+                "alice".to_string(),
+            )
+        };
+        assert!(add("g1", "keyword", "nightcore").await.unwrap());
+        assert!(add("g1", "channel", "Kraftwerk").await.unwrap());
+        assert!(!add("g1", "keyword", "nightcore").await.unwrap());
+        assert!(add("g2", "keyword", "nightcore").await.unwrap());
+
+        let attempt = BlockedAttempt {
+            guild_id: "g1".to_string(),
+            user_id: "bob".to_string(),
+            kind: "keyword".to_string(),
+            pattern: "nightcore".to_string(),
+            url: "https://www.youtube.com/watch?v=34CZjsEI1yU".to_string(),
+            title: "The Model (Nightcore)".to_string(),
+        };
+        log_blocked_attempt(&pool, attempt).await.unwrap();
+
+        let rules = block_rules(&pool, "g1".to_string()).await.unwrap();
+        let rule = |kind: &str, pattern: &str, blocked| BlockRule {
+            kind: kind.to_string(),
+            pattern: pattern.to_string(),
+            blocked,
+        };
+        assert_eq!(
+            rules,
+            [
+                rule("keyword", "nightcore", 1),
+                rule("channel", "Kraftwerk", 0)
+            ]
+        );
+        assert_eq!(
+            block_rules(&pool, "g2".to_string()).await.unwrap(),
+            [rule("keyword", "nightcore", 0)]
+        );
+
+        let remove = |kind: &str, pattern: &str| {
+            remove_block_rule(
+                &pool,
+                "g1".to_string(),
+                kind.to_string(),
+                pattern.to_string(),
+            )
+        };
+        assert!(remove("channel", "Kraftwerk").await.unwrap());
+        assert!(!remove("channel", "Kraftwerk").await.unwrap());
+        assert_eq!(block_rules(&pool, "g1".to_string()).await.unwrap().len(), 1);
+    }
+}
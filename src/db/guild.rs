@@ -0,0 +1,593 @@
+use crate::db::track::Guild;
+use sqlx::FromRow;
+
+pub(crate) async fn guild_locale(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+) -> Result<Option<String>, sqlx::Error> {
+    let query = r#"
+        SELECT locale FROM guilds WHERE id = ?
+        "#;
+    let locale: Option<Option<String>> = sqlx::query_scalar(query)
+        .bind(guild_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(locale.flatten())
+}
+
+pub(crate) async fn set_guild_locale(
+    pool: &sqlx::SqlitePool,
+    guild: Guild,
+    locale: Option<String>,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        INSERT INTO guilds (id, locale, updated)
+        VALUES (?, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET
+            locale = EXCLUDED.locale,
+            updated = EXCLUDED.updated
+        "#;
+    sqlx::query(query)
+        .bind(guild.id)
+        .bind(locale)
+        .bind(guild.updated)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Settings of a guild that decide how commands respond.
+#[derive(Debug, Default, PartialEq, FromRow)]
+pub(crate) struct ResponseSettings {
+    pub(crate) quiet: bool,
+    pub(crate) announce_channel_id: Option<String>,
+    /// Whether confirmations of the command are public, `None` for its default.
+    pub(crate) public: Option<bool>,
+    /// Whether messages of the bot are deleted after a while.
+    pub(crate) cleanup: bool,
+}
+
+pub(crate) async fn response_settings(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+    command: String,
+) -> Result<ResponseSettings, sqlx::Error> {
+    let query = r#"
+        SELECT
+            guilds.quiet,
+            guilds.announce_channel_id,
+            command_visibility.public,
+            guilds.cleanup_ttl_secs IS NOT NULL AS cleanup
+        FROM guilds
+        LEFT JOIN command_visibility
+            ON command_visibility.guild_id = guilds.id AND command_visibility.command = ?
+        WHERE guilds.id = ?
+        "#;
+    let settings = sqlx::query_as(query)
+        .bind(command)
+        .bind(guild_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(settings.unwrap_or_default())
+}
+
+pub(crate) async fn set_guild_quiet(
+    pool: &sqlx::SqlitePool,
+    guild: Guild,
+    quiet: bool,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        INSERT INTO guilds (id, quiet, updated)
+        VALUES (?, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET
+            quiet = EXCLUDED.quiet,
+            updated = EXCLUDED.updated
+        "#;
+    sqlx::query(query)
+        .bind(guild.id)
+        .bind(quiet)
+        .bind(guild.updated)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn set_guild_announce_channel(
+    pool: &sqlx::SqlitePool,
+    guild: Guild,
+    channel_id: Option<String>,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        INSERT INTO guilds (id, announce_channel_id, updated)
+        VALUES (?, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET
+            announce_channel_id = EXCLUDED.announce_channel_id,
+            updated = EXCLUDED.updated
+        "#;
+    sqlx::query(query)
+        .bind(guild.id)
+        .bind(channel_id)
+        .bind(guild.updated)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Deletes messages of the bot after `ttl_secs`, or never with `None`.
+pub(crate) async fn set_guild_cleanup_ttl(
+    pool: &sqlx::SqlitePool,
+    guild: Guild,
+    ttl_secs: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        INSERT INTO guilds (id, cleanup_ttl_secs, updated)
+        VALUES (?, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET
+            cleanup_ttl_secs = EXCLUDED.cleanup_ttl_secs,
+            updated = EXCLUDED.updated
+        "#;
+    sqlx::query(query)
+        .bind(guild.id)
+        .bind(ttl_secs)
+        .bind(guild.updated)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn guild_autoplay(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+) -> Result<Option<String>, sqlx::Error> {
+    let query = r#"
+        SELECT autoplay FROM guilds WHERE id = ?
+        "#;
+    let autoplay: Option<Option<String>> = sqlx::query_scalar(query)
+        .bind(guild_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(autoplay.flatten())
+}
+
+/// Sets where follow-up tracks come from when the queue runs out, `None` to turn autoplay off.
+pub(crate) async fn set_guild_autoplay(
+    pool: &sqlx::SqlitePool,
+    guild: Guild,
+    autoplay: Option<String>,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        INSERT INTO guilds (id, autoplay, updated)
+        VALUES (?, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET
+            autoplay = EXCLUDED.autoplay,
+            updated = EXCLUDED.updated
+        "#;
+    sqlx::query(query)
+        .bind(guild.id)
+        .bind(autoplay)
+        .bind(guild.updated)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Seconds that the end of a track is mixed with the start of the next one, 0 if they follow
+/// each other directly.
+pub(crate) async fn guild_crossfade(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+) -> Result<i64, sqlx::Error> {
+    let query = r#"
+        SELECT crossfade_secs FROM guilds WHERE id = ?
+        "#;
+    let crossfade: Option<i64> = sqlx::query_scalar(query)
+        .bind(guild_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(crossfade.unwrap_or_default())
+}
+
+pub(crate) async fn set_guild_crossfade(
+    pool: &sqlx::SqlitePool,
+    guild: Guild,
+    crossfade_secs: i64,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        INSERT INTO guilds (id, crossfade_secs, updated)
+        VALUES (?, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET
+            crossfade_secs = EXCLUDED.crossfade_secs,
+            updated = EXCLUDED.updated
+        "#;
+    sqlx::query(query)
+        .bind(guild.id)
+        .bind(crossfade_secs)
+        .bind(guild.updated)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Which parts of tracks are skipped in a guild.
+#[derive(Debug, Default, Clone, Copy, PartialEq, FromRow)]
+pub(crate) struct SkipSettings {
+    /// Silence at the start and the end of tracks.
+    pub(crate) silence: bool,
+    /// Segments that SponsorBlock users marked as not being music.
+    pub(crate) segments: bool,
+}
+
+pub(crate) async fn skip_settings(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+) -> Result<SkipSettings, sqlx::Error> {
+    let query = r#"
+        SELECT skip_silence AS silence, skip_segments AS segments FROM guilds WHERE id = ?
+        "#;
+    let settings = sqlx::query_as(query)
+        .bind(guild_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(settings.unwrap_or_default())
+}
+
+pub(crate) async fn set_skip_settings(
+    pool: &sqlx::SqlitePool,
+    guild: Guild,
+    settings: SkipSettings,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        INSERT INTO guilds (id, skip_silence, skip_segments, updated)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET
+            skip_silence = EXCLUDED.skip_silence,
+            skip_segments = EXCLUDED.skip_segments,
+            updated = EXCLUDED.updated
+        "#;
+    sqlx::query(query)
+        .bind(guild.id)
+        .bind(settings.silence)
+        .bind(settings.segments)
+        .bind(guild.updated)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Limits on what members of a guild can add to its queue, `None` for no limit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, FromRow)]
+pub(crate) struct QueueLimits {
+    /// Tracks that a member can have in the queue at once.
+    pub(crate) tracks_per_user: Option<i64>,
+    /// Tracks that are added of a playlist.
+    pub(crate) playlist_size: Option<i64>,
+    pub(crate) track_duration_secs: Option<i64>,
+}
+
+pub(crate) async fn queue_limits(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+) -> Result<QueueLimits, sqlx::Error> {
+    let query = r#"
+        SELECT
+            max_tracks_per_user AS tracks_per_user,
+            max_playlist_size AS playlist_size,
+            max_track_duration_secs AS track_duration_secs
+        FROM guilds WHERE id = ?
+        "#;
+    let limits = sqlx::query_as(query)
+        .bind(guild_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(limits.unwrap_or_default())
+}
+
+pub(crate) async fn set_queue_limits(
+    pool: &sqlx::SqlitePool,
+    guild: Guild,
+    limits: QueueLimits,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        INSERT INTO guilds (id, max_tracks_per_user, max_playlist_size, max_track_duration_secs, updated)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET
+            max_tracks_per_user = EXCLUDED.max_tracks_per_user,
+            max_playlist_size = EXCLUDED.max_playlist_size,
+            max_track_duration_secs = EXCLUDED.max_track_duration_secs,
+            updated = EXCLUDED.updated
+        "#;
+    sqlx::query(query)
+        .bind(guild.id)
+        .bind(limits.tracks_per_user)
+        .bind(limits.playlist_size)
+        .bind(limits.track_duration_secs)
+        .bind(guild.updated)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Whether the queue of a guild takes turns between the members who added tracks.
+pub(crate) async fn guild_fair_queue(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+) -> Result<bool, sqlx::Error> {
+    let query = r#"
+        SELECT fair_queue FROM guilds WHERE id = ?
+        "#;
+    let fair: Option<bool> = sqlx::query_scalar(query)
+        .bind(guild_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(fair.unwrap_or_default())
+}
+
+pub(crate) async fn set_guild_fair_queue(
+    pool: &sqlx::SqlitePool,
+    guild: Guild,
+    fair: bool,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        INSERT INTO guilds (id, fair_queue, updated)
+        VALUES (?, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET
+            fair_queue = EXCLUDED.fair_queue,
+            updated = EXCLUDED.updated
+        "#;
+    sqlx::query(query)
+        .bind(guild.id)
+        .bind(fair)
+        .bind(guild.updated)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Filters that the tracks of a guild are played with, see [`crate::filter::Filters`].
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub(crate) struct FilterSettings {
+    pub(crate) equalizer: String,
+    pub(crate) speed: f64,
+    pub(crate) pitch: f64,
+    pub(crate) normalize: bool,
+}
+
+/// The filters of `guild_id`, `None` if it never set any.
+pub(crate) async fn filter_settings(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+) -> Result<Option<FilterSettings>, sqlx::Error> {
+    let query = r#"
+        SELECT
+            filter_equalizer AS equalizer,
+            filter_speed AS speed,
+            filter_pitch AS pitch,
+            filter_normalize AS normalize
+        FROM guilds WHERE id = ?
+        "#;
+    sqlx::query_as(query)
+        .bind(guild_id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub(crate) async fn set_filter_settings(
+    pool: &sqlx::SqlitePool,
+    guild: Guild,
+    settings: FilterSettings,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        INSERT INTO guilds (id, filter_equalizer, filter_speed, filter_pitch, filter_normalize, updated)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET
+            filter_equalizer = EXCLUDED.filter_equalizer,
+            filter_speed = EXCLUDED.filter_speed,
+            filter_pitch = EXCLUDED.filter_pitch,
+            filter_normalize = EXCLUDED.filter_normalize,
+            updated = EXCLUDED.updated
+        "#;
+    sqlx::query(query)
+        .bind(guild.id)
+        .bind(settings.equalizer)
+        .bind(settings.speed)
+        .bind(settings.pitch)
+        .bind(settings.normalize)
+        .bind(guild.updated)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Makes confirmations of `command` public or private, or resets them to the default with `None`.
+pub(crate) async fn set_command_visibility(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+    command: String,
+    public: Option<bool>,
+) -> Result<(), sqlx::Error> {
+    let Some(public) = public else {
+        let query = r#"
+            DELETE FROM command_visibility WHERE guild_id = ? AND command = ?
+            "#;
+        sqlx::query(query)
+            .bind(guild_id)
+            .bind(command)
+            .execute(pool)
+            .await?;
+        return Ok(());
+    };
+    let query = r#"
+        INSERT INTO command_visibility (guild_id, command, public)
+        VALUES (?, ?, ?)
+        ON CONFLICT (guild_id, command) DO UPDATE SET
+            public = EXCLUDED.public
+        "#;
+    sqlx::query(query)
+        .bind(guild_id)
+        .bind(command)
+        .bind(public)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{tests::pool, track::insert_guild};
+
+    #[tokio::test]
+    async fn test_guild_locale() {
+        let pool = pool().await;
+        let id = "1234".to_string();
+        assert_eq!(guild_locale(&pool, id.clone()).await.unwrap(), None);
+
+        insert_guild(&pool, Guild::new(id.clone())).await.unwrap();
+        assert_eq!(guild_locale(&pool, id.clone()).await.unwrap(), None);
+
+        set_guild_locale(&pool, Guild::new(id.clone()), Some("de".to_string()))
+            .await
+            .unwrap();
+        insert_guild(&pool, Guild::new(id.clone())).await.unwrap();
+        assert_eq!(
+            guild_locale(&pool, id.clone()).await.unwrap().as_deref(),
+            Some("de")
+        );
+
+        set_guild_locale(&pool, Guild::new(id.clone()), None)
+            .await
+            .unwrap();
+        assert_eq!(guild_locale(&pool, id).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_response_settings() {
+        let pool = pool().await;
+        let id = "1234".to_string();
+        let settings = |command: &str| response_settings(&pool, id.clone(), command.to_string());
+        assert_eq!(settings("skip").await.unwrap(), ResponseSettings::default());
+
+        set_guild_quiet(&pool, Guild::new(id.clone()), true)
+            .await
+            .unwrap();
+        set_guild_announce_channel(&pool, Guild::new(id.clone()), Some("42".to_string()))
+            .await
+            .unwrap();
+        set_command_visibility(&pool, id.clone(), "skip".to_string(), Some(false))
+            .await
+            .unwrap();
+        assert_eq!(
+            settings("skip").await.unwrap(),
+            ResponseSettings {
+                quiet: true,
+                announce_channel_id: Some("42".to_string()),
+                public: Some(false),
+                cleanup: false,
+            }
+        );
+        assert_eq!(settings("stop").await.unwrap().public, None);
+
+        set_command_visibility(&pool, id.clone(), "skip".to_string(), None)
+            .await
+            .unwrap();
+        assert_eq!(settings("skip").await.unwrap().public, None);
+
+        set_guild_cleanup_ttl(&pool, Guild::new(id.clone()), Some(600))
+            .await
+            .unwrap();
+        assert!(settings("skip").await.unwrap().cleanup);
+    }
+
+    #[tokio::test]
+    async fn test_guild_crossfade() {
+        let pool = pool().await;
+        let id = "1234".to_string();
+        assert_eq!(guild_crossfade(&pool, id.clone()).await.unwrap(), 0);
+
+        set_guild_locale(&pool, Guild::new(id.clone()), Some("de".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(guild_crossfade(&pool, id.clone()).await.unwrap(), 0);
+
+        set_guild_crossfade(&pool, Guild::new(id.clone()), 6)
+            .await
+            .unwrap();
+        assert_eq!(guild_crossfade(&pool, id).await.unwrap(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_skip_settings() {
+        let pool = pool().await;
+        let id = "1234".to_string();
+        assert_eq!(
+            skip_settings(&pool, id.clone()).await.unwrap(),
+            SkipSettings::default()
+        );
+
+        let settings = SkipSettings {
+            silence: true,
+            segments: false,
+        };
+        set_skip_settings(&pool, Guild::new(id.clone()), settings)
+            .await
+            .unwrap();
+        assert_eq!(skip_settings(&pool, id).await.unwrap(), settings);
+    }
+
+    #[tokio::test]
+    async fn test_filter_settings() {
+        let pool = pool().await;
+        let id = "1234".to_string();
+        assert_eq!(filter_settings(&pool, id.clone()).await.unwrap(), None);
+
+        set_guild_crossfade(&pool, Guild::new(id.clone()), 6)
+            .await
+            .unwrap();
+        let settings = FilterSettings {
+            equalizer: "flat".to_string(),
+            speed: 1.0,
+            pitch: 1.0,
+            normalize: false,
+        };
+        assert_eq!(
+            filter_settings(&pool, id.clone()).await.unwrap(),
+            Some(settings.clone())
+        );
+
+        let settings = FilterSettings {
+            equalizer: "bassboost".to_string(),
+            speed: 1.25,
+            pitch: 1.25,
+            normalize: true,
+        };
+        set_filter_settings(&pool, Guild::new(id.clone()), settings.clone())
+            .await
+            .unwrap();
+        assert_eq!(filter_settings(&pool, id).await.unwrap(), Some(settings));
+    }
+
+    #[tokio::test]
+    async fn test_queue_limits() {
+        let pool = pool().await;
+        let id = "1234".to_string();
+        assert_eq!(
+            queue_limits(&pool, id.clone()).await.unwrap(),
+            QueueLimits::default()
+        );
+        assert!(!guild_fair_queue(&pool, id.clone()).await.unwrap());
+
+        let limits = QueueLimits {
+            tracks_per_user: Some(10),
+            playlist_size: None,
+            track_duration_secs: Some(600),
+        };
+        set_queue_limits(&pool, Guild::new(id.clone()), limits)
+            .await
+            .unwrap();
+        set_guild_fair_queue(&pool, Guild::new(id.clone()), true)
+            .await
+            .unwrap();
+        assert_eq!(queue_limits(&pool, id.clone()).await.unwrap(), limits);
+        assert!(guild_fair_queue(&pool, id).await.unwrap());
+    }
+}
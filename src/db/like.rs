@@ -0,0 +1,94 @@
+use crate::db::track::SavedTrack;
+
+/// Likes the track with `track_id` for `user_id`, or takes the like back
+/// if there already is one. Returns whether the track is liked afterwards.
+pub(crate) async fn toggle_like(
+    pool: &sqlx::SqlitePool,
+    user_id: String,
+    track_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let query = r#"
+        INSERT INTO likes (user_id, track_id, created)
+        VALUES (?, ?, ?)
+        ON CONFLICT DO NOTHING
+        "#;
+    let res = sqlx::query(query)
+        .bind(&user_id)
+        .bind(track_id)
+        .bind(chrono::offset::Utc::now())
+        .execute(&mut *tx)
+        .await?;
+    let liked = res.rows_affected() > 0;
+    if !liked {
+        sqlx::query("DELETE FROM likes WHERE user_id = ? AND track_id = ?")
+            .bind(&user_id)
+            .bind(track_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(liked)
+}
+
+/// The tracks liked by `user_id`, most recently liked first.
+pub(crate) async fn liked_tracks(
+    pool: &sqlx::SqlitePool,
+    user_id: String,
+) -> Result<Vec<SavedTrack>, sqlx::Error> {
+    let query = r#"
+        SELECT tracks.url, tracks.title, tracks.channel, tracks.duration
+        FROM likes
+        JOIN tracks ON tracks.id = likes.track_id
+        WHERE likes.user_id = ?
+        ORDER BY likes.created DESC, likes.rowid DESC
+        "#;
+    sqlx::query_as(query).bind(user_id).fetch_all(pool).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::pool;
+    use crate::db::track::{insert_track, insert_user, Track, User};
+
+    #[tokio::test]
+    async fn test_toggle_like() {
+        let pool = pool().await;
+        insert_user(
+            &pool,
+            User::new("alice".to_string(), "alice".to_string(), None),
+        )
+        .await
+        .unwrap();
+        let mut ids = Vec::new();
+        for url in ["https://a", "https://b"] {
+            let track = Track::new(
+                url.to_string(),
+                url.to_string(),
+                "channel".to_string(),
+                "3:42".to_string(),
+                String::new(),
+            );
+            ids.push(insert_track(&pool, track).await.unwrap());
+        }
+
+        let user = || "alice".to_string();
+        assert!(toggle_like(&pool, user(), ids[0]).await.unwrap());
+        assert!(toggle_like(&pool, user(), ids[1]).await.unwrap());
+        let urls: Vec<_> = liked_tracks(&pool, user())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.url)
+            .collect();
+        assert_eq!(urls, ["https://b", "https://a"]);
+
+        assert!(!toggle_like(&pool, user(), ids[1]).await.unwrap());
+        assert_eq!(liked_tracks(&pool, user()).await.unwrap().len(), 1);
+        assert!(liked_tracks(&pool, "bob".to_string())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+/// A message the bot posted that is cleaned up later.
+#[derive(Debug, FromRow)]
+pub(crate) struct BotMessage {
+    pub(crate) id: String,
+    pub(crate) channel_id: String,
+    pub(crate) guild_id: String,
+    pub(crate) kind: String,
+    pub(crate) disabled: bool,
+    pub(crate) created: DateTime<Utc>,
+}
+
+impl BotMessage {
+    pub(crate) fn new(id: String, channel_id: String, guild_id: String, kind: String) -> Self {
+        Self {
+            id,
+            channel_id,
+            guild_id,
+            kind,
+            disabled: false,
+            created: chrono::offset::Utc::now(),
+        }
+    }
+}
+
+pub(crate) async fn insert_bot_message(
+    pool: &sqlx::SqlitePool,
+    message: BotMessage,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        INSERT INTO bot_messages (id, channel_id, guild_id, kind, disabled, created)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT (id) DO NOTHING
+        "#;
+    sqlx::query(query)
+        .bind(message.id)
+        .bind(message.channel_id)
+        .bind(message.guild_id)
+        .bind(message.kind)
+        .bind(message.disabled)
+        .bind(message.created)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Messages in guilds with auto-cleanup that are older than the guild's TTL at `now`.
+pub(crate) async fn expired_bot_messages(
+    pool: &sqlx::SqlitePool,
+    now: DateTime<Utc>,
+) -> Result<Vec<BotMessage>, sqlx::Error> {
+    let query = r#"
+        SELECT bot_messages.*
+        FROM bot_messages
+        JOIN guilds ON guilds.id = bot_messages.guild_id
+        WHERE guilds.cleanup_ttl_secs IS NOT NULL
+            AND unixepoch(bot_messages.created) + guilds.cleanup_ttl_secs <= unixepoch(?)
+        "#;
+    sqlx::query_as(query).bind(now).fetch_all(pool).await
+}
+
+/// Messages of `kind` created before `before` that have not been disabled yet.
+pub(crate) async fn stale_bot_messages(
+    pool: &sqlx::SqlitePool,
+    kind: String,
+    before: DateTime<Utc>,
+) -> Result<Vec<BotMessage>, sqlx::Error> {
+    let query = r#"
+        SELECT * FROM bot_messages
+        WHERE kind = ? AND NOT disabled AND unixepoch(created) < unixepoch(?)
+        "#;
+    sqlx::query_as(query)
+        .bind(kind)
+        .bind(before)
+        .fetch_all(pool)
+        .await
+}
+
+pub(crate) async fn disable_bot_message(
+    pool: &sqlx::SqlitePool,
+    id: String,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        UPDATE bot_messages SET disabled = TRUE WHERE id = ?
+        "#;
+    sqlx::query(query).bind(id).execute(pool).await?;
+    Ok(())
+}
+
+pub(crate) async fn delete_bot_message(
+    pool: &sqlx::SqlitePool,
+    id: String,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        DELETE FROM bot_messages WHERE id = ?
+        "#;
+    sqlx::query(query).bind(id).execute(pool).await?;
+    Ok(())
+}
+
+/// Forgets messages that neither get deleted nor disabled anymore,
+/// because their guild has no auto-cleanup.
+pub(crate) async fn forget_bot_messages(
+    pool: &sqlx::SqlitePool,
+    disabled_kind: String,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        DELETE FROM bot_messages
+        WHERE (disabled OR kind != ?)
+            AND guild_id NOT IN (SELECT id FROM guilds WHERE cleanup_ttl_secs IS NOT NULL)
+        "#;
+    sqlx::query(query).bind(disabled_kind).execute(pool).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{guild::set_guild_cleanup_ttl, tests::pool, track::Guild};
+    use chrono::TimeDelta;
+
+    fn message(id: &str, guild_id: &str, kind: &str, age: TimeDelta) -> BotMessage {
+        let mut message = BotMessage::new(
+            id.to_string(),
+            "10".to_string(),
+            guild_id.to_string(),
+            kind.to_string(),
+        );
+        message.created -= age;
+        message
+    }
+
+    fn ids(messages: Vec<BotMessage>) -> Vec<String> {
+        let mut ids: Vec<_> = messages.into_iter().map(|m| m.id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_bot_messages() {
+        let pool = pool().await;
+        set_guild_cleanup_ttl(&pool, Guild::new("1".to_string()), Some(600))
+            .await
+            .unwrap();
+        for message in [
+            message("a", "1", "confirmation", TimeDelta::minutes(20)),
+            message("b", "1", "queue", TimeDelta::minutes(5)),
+            message("c", "2", "confirmation", TimeDelta::minutes(20)),
+            message("d", "2", "queue", TimeDelta::minutes(20)),
+        ] {
+            insert_bot_message(&pool, message).await.unwrap();
+        }
+
+        let now = Utc::now();
+        assert_eq!(ids(expired_bot_messages(&pool, now).await.unwrap()), ["a"]);
+        let stale = stale_bot_messages(&pool, "queue".to_string(), now - TimeDelta::minutes(1));
+        assert_eq!(ids(stale.await.unwrap()), ["b", "d"]);
+
+        disable_bot_message(&pool, "b".to_string()).await.unwrap();
+        disable_bot_message(&pool, "d".to_string()).await.unwrap();
+        forget_bot_messages(&pool, "queue".to_string())
+            .await
+            .unwrap();
+        delete_bot_message(&pool, "a".to_string()).await.unwrap();
+
+        let remaining = sqlx::query_as("SELECT * FROM bot_messages")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(ids(remaining), ["b"]);
+    }
+}
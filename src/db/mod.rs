@@ -1 +1,23 @@
+pub mod blocklist;
+pub mod guild;
+pub mod like;
+pub mod message;
+pub mod playlist;
 pub mod track;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+
+    /// An empty in-memory database with all migrations applied.
+    pub(crate) async fn pool() -> SqlitePool {
+        // every connection to :memory: opens a separate database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        pool
+    }
+}
@@ -0,0 +1,313 @@
+use crate::db::track::SavedTrack;
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+/// A named list of tracks, owned by a user.
+///
+/// Personal playlists can be used by their owner in every guild,
+/// shared ones by everyone in the guild they were created in.
+#[derive(Debug, FromRow)]
+pub(crate) struct Playlist {
+    pub(crate) id: i64,
+    pub(crate) name: String,
+    pub(crate) owner_id: String,
+    pub(crate) guild_id: String,
+    pub(crate) shared: bool,
+    pub(crate) updated: DateTime<Utc>,
+}
+
+impl Playlist {
+    pub(crate) fn new(name: String, owner_id: String, guild_id: String, shared: bool) -> Self {
+        Self {
+            id: 0,
+            name,
+            owner_id,
+            guild_id,
+            shared,
+            updated: chrono::offset::Utc::now(),
+        }
+    }
+}
+
+/// Inserts `playlist` and returns its id, or `None` if its name is already taken.
+pub(crate) async fn insert_playlist(
+    pool: &sqlx::SqlitePool,
+    playlist: Playlist,
+) -> Result<Option<i64>, sqlx::Error> {
+    let query = r#"
+        INSERT INTO playlists (name, owner_id, guild_id, shared, updated)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT DO NOTHING
+        RETURNING id
+        "#;
+    sqlx::query_scalar(query)
+        .bind(playlist.name)
+        .bind(playlist.owner_id)
+        .bind(playlist.guild_id)
+        .bind(playlist.shared)
+        .bind(playlist.updated)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Finds the playlist called `name` that `user_id` can use in `guild_id`.
+///
+/// Personal playlists of the user take precedence over shared ones.
+pub(crate) async fn find_playlist(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+    user_id: String,
+    name: String,
+) -> Result<Option<Playlist>, sqlx::Error> {
+    let query = r#"
+        SELECT * FROM playlists
+        WHERE name = ? AND ((owner_id = ? AND NOT shared) OR (guild_id = ? AND shared))
+        ORDER BY shared
+        LIMIT 1
+        "#;
+    sqlx::query_as(query)
+        .bind(name)
+        .bind(user_id)
+        .bind(guild_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// All playlists that `user_id` can use in `guild_id`, personal ones first.
+pub(crate) async fn playlists(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+    user_id: String,
+) -> Result<Vec<Playlist>, sqlx::Error> {
+    let query = r#"
+        SELECT * FROM playlists
+        WHERE (owner_id = ? AND NOT shared) OR (guild_id = ? AND shared)
+        ORDER BY shared, name
+        "#;
+    sqlx::query_as(query)
+        .bind(user_id)
+        .bind(guild_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Renames a playlist, returns `false` if `name` is already taken.
+pub(crate) async fn rename_playlist(
+    pool: &sqlx::SqlitePool,
+    id: i64,
+    name: String,
+) -> Result<bool, sqlx::Error> {
+    let query = r#"
+        UPDATE OR IGNORE playlists SET name = ?, updated = ? WHERE id = ?
+        "#;
+    let res = sqlx::query(query)
+        .bind(name)
+        .bind(chrono::offset::Utc::now())
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+pub(crate) async fn delete_playlist(pool: &sqlx::SqlitePool, id: i64) -> Result<(), sqlx::Error> {
+    let query = r#"
+        DELETE FROM playlists WHERE id = ?
+        "#;
+    sqlx::query(query).bind(id).execute(pool).await?;
+    Ok(())
+}
+
+/// Appends the tracks with `track_ids` to the end of a playlist.
+pub(crate) async fn append_playlist_tracks(
+    pool: &sqlx::SqlitePool,
+    playlist_id: i64,
+    track_ids: &[i64],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let query = r#"
+        INSERT INTO playlist_tracks (playlist_id, track_id, position)
+        SELECT ?, ?, COALESCE(MAX(position), 0) + 1
+        FROM playlist_tracks WHERE playlist_id = ?
+        "#;
+    for track_id in track_ids {
+        sqlx::query(query)
+            .bind(playlist_id)
+            .bind(track_id)
+            .bind(playlist_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query("UPDATE playlists SET updated = ? WHERE id = ?")
+        .bind(chrono::offset::Utc::now())
+        .bind(playlist_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+/// Removes the track at the 1-based `position` of a playlist,
+/// returns `false` if the playlist is shorter than that.
+pub(crate) async fn remove_playlist_track(
+    pool: &sqlx::SqlitePool,
+    playlist_id: i64,
+    position: i64,
+) -> Result<bool, sqlx::Error> {
+    let query = r#"
+        DELETE FROM playlist_tracks WHERE id = (
+            SELECT id FROM playlist_tracks
+            WHERE playlist_id = ?
+            ORDER BY position, id
+            LIMIT 1 OFFSET ?
+        )
+        "#;
+    let res = sqlx::query(query)
+        .bind(playlist_id)
+        .bind(position - 1)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// The tracks of a playlist, in order.
+pub(crate) async fn playlist_tracks(
+    pool: &sqlx::SqlitePool,
+    playlist_id: i64,
+) -> Result<Vec<SavedTrack>, sqlx::Error> {
+    let query = r#"
+        SELECT tracks.url, tracks.title, tracks.channel, tracks.duration
+        FROM playlist_tracks
+        JOIN tracks ON tracks.id = playlist_tracks.track_id
+        WHERE playlist_tracks.playlist_id = ?
+        ORDER BY playlist_tracks.position, playlist_tracks.id
+        "#;
+    sqlx::query_as(query)
+        .bind(playlist_id)
+        .fetch_all(pool)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::pool;
+    use crate::db::track::{insert_track, Track};
+
+    async fn track(pool: &sqlx::SqlitePool, url: &str) -> i64 {
+        let track = Track::new(
+            url.to_string(),
+            format!("title of {url}"),
+            "channel".to_string(),
+            "3:42".to_string(),
+            String::new(),
+        );
+        insert_track(pool, track).await.unwrap()
+    }
+
+    fn playlist(name: &str, owner_id: &str, guild_id: &str, shared: bool) -> Playlist {
+        Playlist::new(
+            name.to_string(),
+            owner_id.to_string(),
+            guild_id.to_string(),
+            shared,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_visibility() {
+        let pool = pool().await;
+        let personal = insert_playlist(&pool, playlist("mix", "alice", "g1", false))
+            .await
+            .unwrap()
+            .unwrap();
+        let shared = insert_playlist(&pool, playlist("mix", "bob", "g1", true))
+            .await
+            .unwrap()
+            .unwrap();
+        // names are unique per owner and per guild
+        assert!(
+            insert_playlist(&pool, playlist("mix", "alice", "g2", false))
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(insert_playlist(&pool, playlist("mix", "carol", "g1", true))
+            .await
+            .unwrap()
+            .is_none());
+
+        let find = |guild_id: &str, user_id: &str| {
+            find_playlist(
+                &pool,
+                guild_id.to_string(),
+                user_id.to_string(),
+                "mix".to_string(),
+            )
+        };
+        // personal playlists can be used everywhere and win over shared ones
+        assert_eq!(find("g1", "alice").await.unwrap().unwrap().id, personal);
+        assert_eq!(find("g2", "alice").await.unwrap().unwrap().id, personal);
+        assert_eq!(find("g1", "carol").await.unwrap().unwrap().id, shared);
+        assert!(find("g2", "carol").await.unwrap().is_none());
+
+        let names: Vec<_> = playlists(&pool, "g1".to_string(), "alice".to_string())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| (p.name, p.shared))
+            .collect();
+        assert_eq!(
+            names,
+            [("mix".to_string(), false), ("mix".to_string(), true)]
+        );
+
+        assert!(rename_playlist(&pool, personal, "mix".to_string())
+            .await
+            .unwrap());
+        insert_playlist(&pool, playlist("other", "alice", "g1", false))
+            .await
+            .unwrap();
+        assert!(!rename_playlist(&pool, personal, "other".to_string())
+            .await
+            .unwrap());
+        assert!(rename_playlist(&pool, personal, "chill".to_string())
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_tracks() {
+        let pool = pool().await;
+        let id = insert_playlist(&pool, playlist("mix", "alice", "g1", false))
+            .await
+            .unwrap()
+            .unwrap();
+        let a = track(&pool, "https://a").await;
+        let b = track(&pool, "https://b").await;
+        let c = track(&pool, "https://c").await;
+        append_playlist_tracks(&pool, id, &[a, b]).await.unwrap();
+        append_playlist_tracks(&pool, id, &[c, a]).await.unwrap();
+
+        let urls = |tracks: Vec<SavedTrack>| -> Vec<String> {
+            tracks.into_iter().map(|t| t.url).collect()
+        };
+        assert_eq!(
+            urls(playlist_tracks(&pool, id).await.unwrap()),
+            ["https://a", "https://b", "https://c", "https://a"]
+        );
+
+        assert!(remove_playlist_track(&pool, id, 2).await.unwrap());
+        assert!(!remove_playlist_track(&pool, id, 4).await.unwrap());
+        assert_eq!(
+            urls(playlist_tracks(&pool, id).await.unwrap()),
+            ["https://a", "https://c", "https://a"]
+        );
+
+        delete_playlist(&pool, id).await.unwrap();
+        assert!(playlist_tracks(&pool, id).await.unwrap().is_empty());
+        let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM playlist_tracks")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(left, 0);
+    }
+}
@@ -61,6 +61,130 @@ pub(crate) async fn insert_track(
     Ok(id)
 }
 
+/// What is needed to list and play a track saved by a user.
+#[derive(Debug, FromRow)]
+pub(crate) struct SavedTrack {
+    pub(crate) url: String,
+    pub(crate) title: String,
+    pub(crate) channel: String,
+    pub(crate) duration: String,
+}
+
+pub(crate) async fn find_track(
+    pool: &sqlx::SqlitePool,
+    id: i64,
+) -> Result<Option<Track>, sqlx::Error> {
+    let query = r#"
+        SELECT * FROM tracks WHERE id = ?
+        "#;
+    sqlx::query_as(query).bind(id).fetch_optional(pool).await
+}
+
+pub(crate) async fn find_track_by_url(
+    pool: &sqlx::SqlitePool,
+    url: String,
+) -> Result<Option<Track>, sqlx::Error> {
+    let query = r#"
+        SELECT * FROM tracks WHERE url = ?
+        "#;
+    sqlx::query_as(query).bind(url).fetch_optional(pool).await
+}
+
+/// Inserts `track` unless a track with its url is known, and returns the id either way.
+///
+/// Unlike [`insert_track`] this keeps the metadata of known tracks, for tracks
+/// that are only partially known, e.g. from the queue.
+pub(crate) async fn insert_track_if_missing(
+    pool: &sqlx::SqlitePool,
+    track: Track,
+) -> Result<i64, sqlx::Error> {
+    let query = r#"
+        INSERT INTO tracks (url, title, channel, duration, thumbnail, updated)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT (url) DO UPDATE SET
+            url = tracks.url
+        RETURNING id
+        "#;
+
+    let id = sqlx::query_scalar(query)
+        .bind(&track.url)
+        .bind(track.title)
+        .bind(track.channel)
+        .bind(track.duration)
+        .bind(track.thumbnail)
+        .bind(track.updated)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(id)
+}
+
+/// Gain in dB that plays the track of `url` at the normal loudness, once it was measured.
+pub(crate) async fn track_gain(
+    pool: &sqlx::SqlitePool,
+    url: String,
+) -> Result<Option<f64>, sqlx::Error> {
+    let query = r#"
+        SELECT gain_db FROM tracks WHERE url = ?
+        "#;
+    let gain: Option<Option<f64>> = sqlx::query_scalar(query)
+        .bind(url)
+        .fetch_optional(pool)
+        .await?;
+    Ok(gain.flatten())
+}
+
+pub(crate) async fn set_track_gain(
+    pool: &sqlx::SqlitePool,
+    url: String,
+    gain_db: f64,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        UPDATE tracks SET gain_db = ? WHERE url = ?
+        "#;
+    sqlx::query(query)
+        .bind(gain_db)
+        .bind(url)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Segments of the track of `url` as JSON, if they were fetched before.
+pub(crate) async fn cached_segments(
+    pool: &sqlx::SqlitePool,
+    url: String,
+) -> Result<Option<String>, sqlx::Error> {
+    let query = r#"
+        SELECT segments FROM segment_cache WHERE url = ?
+        "#;
+    sqlx::query_scalar(query)
+        .bind(url)
+        .fetch_optional(pool)
+        .await
+}
+
+pub(crate) async fn cache_segments(
+    pool: &sqlx::SqlitePool,
+    url: String,
+    segments: String,
+) -> Result<(), sqlx::Error> {
+    let query = r#"
+        INSERT INTO segment_cache (url, segments, updated)
+        VALUES (?, ?, ?)
+        ON CONFLICT (url) DO UPDATE SET
+            segments = EXCLUDED.segments,
+            updated = EXCLUDED.updated
+        "#;
+    sqlx::query(query)
+        .bind(url)
+        .bind(segments)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
 #[derive(Debug, FromRow)]
 pub(crate) struct User {
     pub(crate) id: String,
@@ -136,6 +260,56 @@ pub(crate) async fn insert_query(pool: &sqlx::SqlitePool, q: Query) -> Result<i6
     Ok(res.last_insert_rowid())
 }
 
+/// A track played in a guild before, with how much the guild likes it.
+#[derive(Debug, FromRow)]
+pub(crate) struct RankedTrack {
+    pub(crate) url: String,
+    pub(crate) title: String,
+    pub(crate) channel: String,
+    pub(crate) duration: String,
+    /// Sum over all requests of the track, each weighing less the older it is.
+    pub(crate) score: f64,
+}
+
+/// The `limit` tracks requested most in `guild_id` up to `now`, leaving out
+/// the tracks of the last `skip_recent` requests.
+///
+/// A new request counts fully, one from yesterday half as much, one from
+/// the day before a third, and so on.
+pub(crate) async fn popular_tracks(
+    pool: &sqlx::SqlitePool,
+    guild_id: String,
+    now: DateTime<Utc>,
+    skip_recent: i64,
+    limit: i64,
+) -> Result<Vec<RankedTrack>, sqlx::Error> {
+    let query = r#"
+        SELECT
+            tracks.url,
+            tracks.title,
+            tracks.channel,
+            tracks.duration,
+            SUM(1.0 / (1.0 + MAX(julianday(?) - julianday(queries.updated), 0.0))) AS score
+        FROM queries
+        JOIN tracks ON tracks.id = queries.track_id
+        WHERE queries.guild_id = ?
+            AND queries.track_id NOT IN (
+                SELECT track_id FROM queries WHERE guild_id = ? ORDER BY id DESC LIMIT ?
+            )
+        GROUP BY tracks.id
+        ORDER BY score DESC
+        LIMIT ?
+        "#;
+    sqlx::query_as(query)
+        .bind(now)
+        .bind(&guild_id)
+        .bind(&guild_id)
+        .bind(skip_recent)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
+
 #[derive(Debug, FromRow)]
 pub(crate) struct Guild {
     pub(crate) id: String,
@@ -165,3 +339,86 @@ pub(crate) async fn insert_guild(pool: &sqlx::SqlitePool, guild: Guild) -> Resul
         .await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::pool;
+    use chrono::TimeDelta;
+
+    #[tokio::test]
+    async fn test_popular_tracks() {
+        let pool = pool().await;
+        let now = Utc::now();
+        let mut ids = Vec::new();
+        for url in ["https://old", "https://new", "https://recent"] {
+            let track = Track::new(
+                url.to_string(),
+                url.to_string(),
+                "channel".to_string(),
+                "3:42".to_string(),
+                String::new(),
+            );
+            ids.push(insert_track(&pool, track).await.unwrap());
+        }
+        let request = |track_id: i64, days: i64| Query {
+            id: 0,
+            user_id: "alice".to_string(),
+            guild_id: "g1".to_string(),
+            track_id,
+            updated: now - TimeDelta::days(days),
+        };
+        // three requests a month ago count less than two from today
+        for _ in 0..3 {
+            insert_query(&pool, request(ids[0], 30)).await.unwrap();
+        }
+        for _ in 0..2 {
+            insert_query(&pool, request(ids[1], 0)).await.unwrap();
+        }
+        insert_query(&pool, request(ids[2], 0)).await.unwrap();
+
+        let urls = |tracks: Vec<RankedTrack>| -> Vec<String> {
+            tracks.into_iter().map(|t| t.url).collect()
+        };
+        let tracks = popular_tracks(&pool, "g1".to_string(), now, 1, 10)
+            .await
+            .unwrap();
+        assert!((tracks[0].score - 2.0).abs() < 0.01);
+        assert_eq!(urls(tracks), ["https://new", "https://old"]);
+        assert_eq!(
+            urls(
+                popular_tracks(&pool, "g1".to_string(), now, 0, 10)
+                    .await
+                    .unwrap()
+            ),
+            ["https://new", "https://recent", "https://old"]
+        );
+        assert!(popular_tracks(&pool, "g2".to_string(), now, 0, 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_track_gain() {
+        let pool = pool().await;
+        let url = "https://loud".to_string();
+        let track = Track::new(
+            url.clone(),
+            "loud".to_string(),
+            "channel".to_string(),
+            "3:42".to_string(),
+            String::new(),
+        );
+        insert_track(&pool, track).await.unwrap();
+        assert_eq!(track_gain(&pool, url.clone()).await.unwrap(), None);
+        set_track_gain(&pool, url.clone(), -4.5).await.unwrap();
+        assert_eq!(track_gain(&pool, url).await.unwrap(), Some(-4.5));
+        assert_eq!(
+            track_gain(&pool, "https://unknown".to_string())
+                .await
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,530 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+/// A stereo sample.
+pub(crate) type Frame = [f32; 2];
+
+/// A second order IIR filter, with the coefficients of the Audio EQ Cookbook.
+#[derive(Debug, Clone)]
+pub(crate) struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    /// Last two inputs and outputs of every channel.
+    x: [[f64; 2]; 2],
+    y: [[f64; 2]; 2],
+}
+
+impl Biquad {
+    fn from_coefficients(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b: [b[0] / a[0], b[1] / a[0], b[2] / a[0]],
+            a: [a[1] / a[0], a[2] / a[0]],
+            x: [[0.0; 2]; 2],
+            y: [[0.0; 2]; 2],
+        }
+    }
+
+    pub(crate) fn low_shelf(rate: f64, frequency: f64, gain_db: f64) -> Self {
+        let a = 10f64.powf(gain_db / 40.0);
+        let w = 2.0 * PI * frequency / rate;
+        let alpha = w.sin() / 2.0 * 2f64.sqrt();
+        let cos = w.cos();
+        let sqrt = 2.0 * a.sqrt() * alpha;
+        Self::from_coefficients(
+            [
+                a * ((a + 1.0) - (a - 1.0) * cos + sqrt),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - sqrt),
+            ],
+            [
+                (a + 1.0) + (a - 1.0) * cos + sqrt,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - sqrt,
+            ],
+        )
+    }
+
+    pub(crate) fn high_shelf(rate: f64, frequency: f64, gain_db: f64, q: f64) -> Self {
+        let a = 10f64.powf(gain_db / 40.0);
+        let w = 2.0 * PI * frequency / rate;
+        let alpha = w.sin() / (2.0 * q);
+        let cos = w.cos();
+        let sqrt = 2.0 * a.sqrt() * alpha;
+        Self::from_coefficients(
+            [
+                a * ((a + 1.0) + (a - 1.0) * cos + sqrt),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - sqrt),
+            ],
+            [
+                (a + 1.0) - (a - 1.0) * cos + sqrt,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - sqrt,
+            ],
+        )
+    }
+
+    pub(crate) fn peaking(rate: f64, frequency: f64, gain_db: f64, q: f64) -> Self {
+        let a = 10f64.powf(gain_db / 40.0);
+        let w = 2.0 * PI * frequency / rate;
+        let alpha = w.sin() / (2.0 * q);
+        let cos = w.cos();
+        Self::from_coefficients(
+            [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+            [1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a],
+        )
+    }
+
+    pub(crate) fn high_pass(rate: f64, frequency: f64, q: f64) -> Self {
+        let w = 2.0 * PI * frequency / rate;
+        let alpha = w.sin() / (2.0 * q);
+        let cos = w.cos();
+        Self::from_coefficients(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    pub(crate) fn low_pass(rate: f64, frequency: f64, q: f64) -> Self {
+        let w = 2.0 * PI * frequency / rate;
+        let alpha = w.sin() / (2.0 * q);
+        let cos = w.cos();
+        Self::from_coefficients(
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    pub(crate) fn process(&mut self, frame: Frame) -> Frame {
+        let mut out = [0.0; 2];
+        for (channel, sample) in frame.into_iter().enumerate() {
+            let x = f64::from(sample);
+            let [x1, x2] = self.x[channel];
+            let [y1, y2] = self.y[channel];
+            let y =
+                self.b[0] * x + self.b[1] * x1 + self.b[2] * x2 - self.a[0] * y1 - self.a[1] * y2;
+            self.x[channel] = [x, x1];
+            self.y[channel] = [y, y1];
+            out[channel] = y as f32;
+        }
+        out
+    }
+}
+
+/// Changes the tempo without changing the pitch, by overlapping windows of the input
+/// at a different rate than they are read (WSOLA).
+#[derive(Debug)]
+pub(crate) struct Stretch {
+    /// Input frames read per output frame.
+    tempo: f64,
+    window: Vec<f32>,
+    /// Output frames per step.
+    hop: usize,
+    /// How far a window may be moved to line up with the previous one.
+    tolerance: usize,
+    input: Vec<Frame>,
+    /// Where the next window would start without moving it.
+    position: f64,
+    /// Where the previous window started.
+    previous: usize,
+    /// Overlapped windows that are not complete yet.
+    output: Vec<Frame>,
+}
+
+impl Stretch {
+    const WINDOW_SECONDS: f64 = 0.04;
+
+    pub(crate) fn new(rate: f64, tempo: f64) -> Self {
+        let size = ((rate * Self::WINDOW_SECONDS) as usize).next_power_of_two();
+        let window = (0..size)
+            .map(|i| (0.5 - 0.5 * (2.0 * PI * i as f64 / size as f64).cos()) as f32)
+            .collect();
+        let tolerance = size / 8;
+        Self {
+            tempo,
+            window,
+            hop: size / 4,
+            tolerance,
+            // leave room to move the first window back
+            input: vec![[0.0; 2]; tolerance],
+            position: tolerance as f64,
+            previous: tolerance,
+            output: vec![[0.0; 2]; size],
+        }
+    }
+
+    pub(crate) fn process(&mut self, frames: &[Frame], out: &mut Vec<Frame>) {
+        self.input.extend_from_slice(frames);
+        let size = self.window.len();
+        // overlapping periodic Hann windows at a quarter of their size add up to 2
+        let scale = 0.5;
+        while self.position as usize + self.tolerance + size <= self.input.len() {
+            let start = self.best_start(self.position as usize);
+            for (i, (acc, frame)) in self
+                .output
+                .iter_mut()
+                .zip(&self.input[start..start + size])
+                .enumerate()
+            {
+                acc[0] += frame[0] * self.window[i];
+                acc[1] += frame[1] * self.window[i];
+            }
+            out.extend(
+                self.output
+                    .drain(..self.hop)
+                    .map(|[l, r]| [l * scale, r * scale]),
+            );
+            self.output.resize(size, [0.0; 2]);
+            self.previous = start;
+            self.position += self.hop as f64 * self.tempo;
+
+            // forget input that no window can reach anymore
+            let unused = (self.position as usize)
+                .min(self.previous)
+                .saturating_sub(self.tolerance + self.hop);
+            if unused > size {
+                self.input.drain(..unused);
+                self.position -= unused as f64;
+                self.previous -= unused;
+            }
+        }
+    }
+
+    /// Moves the window at `position` to where it continues the previous window best.
+    fn best_start(&self, position: usize) -> usize {
+        // what would naturally follow the previous window
+        let natural = self.previous + self.hop;
+        let length = self.window.len() / 2;
+        let mono = |frame: &Frame| frame[0] + frame[1];
+        let target = &self.input[natural..natural + length];
+        let correlation = |start: usize| -> f32 {
+            target
+                .iter()
+                .zip(&self.input[start..start + length])
+                .step_by(2)
+                .map(|(x, y)| mono(x) * mono(y))
+                .sum()
+        };
+        (position - self.tolerance..=position + self.tolerance)
+            .map(|start| (start, correlation(start)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(start, _)| start)
+            .unwrap_or(position)
+    }
+}
+
+/// Changes the sample rate by linear interpolation, which changes speed and pitch
+/// together when the result is played at the original rate.
+#[derive(Debug)]
+pub(crate) struct Resampler {
+    /// Input frames per output frame.
+    ratio: f64,
+    position: f64,
+    last: Frame,
+    /// Removes what would alias when more input than output frames are used.
+    anti_alias: Option<Biquad>,
+}
+
+impl Resampler {
+    pub(crate) fn new(rate: f64, ratio: f64) -> Self {
+        Self {
+            ratio,
+            position: 0.0,
+            last: [0.0; 2],
+            anti_alias: (ratio > 1.0).then(|| Biquad::low_pass(rate, 0.45 * rate / ratio, 0.707)),
+        }
+    }
+
+    pub(crate) fn process(&mut self, frames: &[Frame], out: &mut Vec<Frame>) {
+        let mut previous = self.last;
+        for &frame in frames {
+            let frame = match &mut self.anti_alias {
+                Some(filter) => filter.process(frame),
+                None => frame,
+            };
+            // interpolate every output frame between `previous` and `frame`
+            while self.position < 1.0 {
+                let t = self.position as f32;
+                out.push([
+                    previous[0] + (frame[0] - previous[0]) * t,
+                    previous[1] + (frame[1] - previous[1]) * t,
+                ]);
+                self.position += self.ratio;
+            }
+            self.position -= 1.0;
+            previous = frame;
+        }
+        self.last = previous;
+    }
+}
+
+/// Loudness that normalized tracks are turned to, in LUFS.
+pub(crate) const TARGET_LUFS: f64 = -16.0;
+
+/// Loudness in LUFS of a mean K-weighted energy.
+fn lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+/// Measures the K-weighted energy of blocks of 100 ms, which EBU R128 loudness is based on.
+#[derive(Debug)]
+struct BlockEnergy {
+    /// K-weighting, which models how loud frequencies sound.
+    weighting: [Biquad; 2],
+    block_size: usize,
+    /// Weighted energy of the current block so far.
+    energy: f64,
+    count: usize,
+}
+
+impl BlockEnergy {
+    const BLOCK_SECONDS: f64 = 0.1;
+
+    fn new(rate: f64) -> Self {
+        Self {
+            weighting: [
+                Biquad::high_shelf(rate, 1681.97, 4.0, 0.7072),
+                Biquad::high_pass(rate, 38.13, 0.5003),
+            ],
+            block_size: (rate * Self::BLOCK_SECONDS) as usize,
+            energy: 0.0,
+            count: 0,
+        }
+    }
+
+    /// Returns the mean energy of a block when `frame` completes it.
+    fn process(&mut self, frame: Frame) -> Option<f64> {
+        let shelved = self.weighting[0].process(frame);
+        let weighted = self.weighting[1].process(shelved);
+        self.energy += weighted
+            .iter()
+            .map(|&sample| f64::from(sample).powi(2))
+            .sum::<f64>();
+        self.count += 1;
+        if self.count < self.block_size {
+            return None;
+        }
+        let energy = self.energy / self.count as f64;
+        self.energy = 0.0;
+        self.count = 0;
+        Some(energy)
+    }
+}
+
+/// Turns the volume towards a target loudness, measured like EBU R128 over the last
+/// seconds.
+#[derive(Debug)]
+pub(crate) struct Normalizer {
+    energy: BlockEnergy,
+    /// Energy of the last blocks.
+    blocks: VecDeque<f64>,
+    gain: f32,
+    target_gain: f32,
+    /// How much of the way to the target gain is taken per frame.
+    smoothing: f32,
+}
+
+impl Normalizer {
+    /// Quieter parts are not turned up.
+    const SILENCE_LUFS: f64 = -50.0;
+    const MIN_GAIN_DB: f64 = -20.0;
+    const MAX_GAIN_DB: f64 = 12.0;
+    /// Loudness is measured over this many blocks, like the EBU short-term loudness.
+    const BLOCKS: usize = 30;
+
+    pub(crate) fn new(rate: f64) -> Self {
+        Self {
+            energy: BlockEnergy::new(rate),
+            blocks: VecDeque::with_capacity(Self::BLOCKS),
+            gain: 1.0,
+            target_gain: 1.0,
+            smoothing: (1.0 / rate) as f32,
+        }
+    }
+
+    pub(crate) fn process(&mut self, frame: Frame) -> Frame {
+        if let Some(energy) = self.energy.process(frame) {
+            if self.blocks.len() == Self::BLOCKS {
+                self.blocks.pop_front();
+            }
+            self.blocks.push_back(energy);
+            let loudness = lufs(self.blocks.iter().sum::<f64>() / self.blocks.len() as f64);
+            if loudness > Self::SILENCE_LUFS {
+                let gain_db = (TARGET_LUFS - loudness).clamp(Self::MIN_GAIN_DB, Self::MAX_GAIN_DB);
+                self.target_gain = 10f64.powf(gain_db / 20.0) as f32;
+            }
+        }
+        self.gain += (self.target_gain - self.gain) * self.smoothing;
+        frame.map(|sample| (sample * self.gain).clamp(-1.0, 1.0))
+    }
+}
+
+/// Measures the integrated loudness of a whole track, like EBU R128.
+#[derive(Debug)]
+pub(crate) struct LoudnessMeter {
+    energy: BlockEnergy,
+    blocks: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    /// Blocks of 400 ms are measured, overlapping by three quarters.
+    const BLOCKS_PER_GATE: usize = 4;
+    /// Blocks quieter than this do not count.
+    const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+    /// Blocks this much quieter than the mean do not count either.
+    const RELATIVE_GATE_LU: f64 = -10.0;
+    /// Stops measuring after six hours, which is longer than tracks tend to be.
+    const MAX_BLOCKS: usize = 6 * 60 * 60 * 10;
+
+    pub(crate) fn new(rate: f64) -> Self {
+        Self {
+            energy: BlockEnergy::new(rate),
+            blocks: Vec::new(),
+        }
+    }
+
+    pub(crate) fn process(&mut self, frame: Frame) {
+        if let Some(energy) = self.energy.process(frame) {
+            if self.blocks.len() < Self::MAX_BLOCKS {
+                self.blocks.push(energy);
+            }
+        }
+    }
+
+    /// Loudness in LUFS of everything measured, `None` if it was silent.
+    pub(crate) fn integrated(&self) -> Option<f64> {
+        let gates: Vec<f64> = self
+            .blocks
+            .windows(Self::BLOCKS_PER_GATE)
+            .map(|blocks| blocks.iter().sum::<f64>() / Self::BLOCKS_PER_GATE as f64)
+            .filter(|&energy| lufs(energy) > Self::ABSOLUTE_GATE_LUFS)
+            .collect();
+        if gates.is_empty() {
+            return None;
+        }
+        let threshold =
+            lufs(gates.iter().sum::<f64>() / gates.len() as f64) + Self::RELATIVE_GATE_LU;
+        let loud: Vec<f64> = gates
+            .into_iter()
+            .filter(|&energy| lufs(energy) > threshold)
+            .collect();
+        Some(lufs(loud.iter().sum::<f64>() / loud.len() as f64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f64 = 48_000.0;
+
+    fn sine(frequency: f64, amplitude: f32, seconds: f64) -> Vec<Frame> {
+        (0..(RATE * seconds) as usize)
+            .map(|i| {
+                let sample = amplitude * (2.0 * PI * frequency * i as f64 / RATE).sin() as f32;
+                [sample, sample]
+            })
+            .collect()
+    }
+
+    fn peak(frames: &[Frame]) -> f32 {
+        frames
+            .iter()
+            .map(|frame| frame[0].abs())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_low_shelf() {
+        let mut filter = Biquad::low_shelf(RATE, 100.0, 12.0);
+        let bass: Vec<_> = sine(40.0, 0.1, 1.0)
+            .into_iter()
+            .map(|frame| filter.process(frame))
+            .collect();
+        // about 4 times as loud, after the filter settled
+        let boost = peak(&bass[24_000..]) / 0.1;
+        assert!((3.5..4.5).contains(&boost), "{boost}");
+
+        let mut filter = Biquad::low_shelf(RATE, 100.0, 12.0);
+        let treble: Vec<_> = sine(5000.0, 0.1, 1.0)
+            .into_iter()
+            .map(|frame| filter.process(frame))
+            .collect();
+        let boost = peak(&treble[24_000..]) / 0.1;
+        assert!((0.95..1.1).contains(&boost), "{boost}");
+    }
+
+    #[test]
+    fn test_stretch_length() {
+        for tempo in [0.5, 0.8, 1.25, 2.0] {
+            let mut stretch = Stretch::new(RATE, tempo);
+            let mut out = Vec::new();
+            for chunk in sine(440.0, 0.5, 4.0).chunks(960) {
+                stretch.process(chunk, &mut out);
+            }
+            let expected = 4.0 * RATE / tempo;
+            let ratio = out.len() as f64 / expected;
+            assert!((0.97..1.01).contains(&ratio), "{tempo}: {ratio}");
+            // the windows add up to the original volume
+            let volume = peak(&out[out.len() / 4..out.len() * 3 / 4]);
+            assert!((0.45..0.55).contains(&volume), "{tempo}: {volume}");
+        }
+    }
+
+    #[test]
+    fn test_resampler() {
+        let mut resampler = Resampler::new(RATE, 1.25);
+        let mut out = Vec::new();
+        for chunk in sine(440.0, 0.5, 1.0).chunks(960) {
+            resampler.process(chunk, &mut out);
+        }
+        assert_eq!(out.len(), 38_400);
+
+        let mut resampler = Resampler::new(RATE, 0.5);
+        let mut out = Vec::new();
+        resampler.process(&[[0.0; 2], [1.0; 2]], &mut out);
+        assert_eq!(out, [[0.0; 2], [0.0; 2], [0.0; 2], [0.5; 2]]);
+    }
+
+    #[test]
+    fn test_normalizer() {
+        for amplitude in [0.05, 0.8] {
+            let mut normalizer = Normalizer::new(RATE);
+            let out: Vec<_> = sine(1000.0, amplitude, 10.0)
+                .into_iter()
+                .map(|frame| normalizer.process(frame))
+                .collect();
+            // a stereo sine at 1 kHz with a peak of 0.18 is about -16 LUFS
+            let volume = peak(&out[out.len() - 4800..]);
+            assert!((0.15..0.21).contains(&volume), "{amplitude}: {volume}");
+        }
+
+        let mut normalizer = Normalizer::new(RATE);
+        let silence: Vec<_> = (0..48_000)
+            .map(|_| normalizer.process([0.0001; 2]))
+            .collect();
+        assert_eq!(silence.last(), Some(&[0.0001; 2]));
+    }
+
+    #[test]
+    fn test_loudness_meter() {
+        let mut meter = LoudnessMeter::new(RATE);
+        assert_eq!(meter.integrated(), None);
+        // a quiet intro does not count
+        for frame in sine(1000.0, 0.0001, 5.0)
+            .into_iter()
+            .chain(sine(1000.0, 0.5, 10.0))
+        {
+            meter.process(frame);
+        }
+        // a stereo sine at 1 kHz with a peak of 0.5 is about -6.4 LUFS
+        let loudness = meter.integrated().unwrap();
+        assert!((-7.0..-5.8).contains(&loudness), "{loudness}");
+
+        let mut meter = LoudnessMeter::new(RATE);
+        for frame in sine(1000.0, 0.00001, 2.0) {
+            meter.process(frame);
+        }
+        assert_eq!(meter.integrated(), None);
+    }
+}
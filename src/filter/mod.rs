@@ -0,0 +1,640 @@
+mod dsp;
+mod skip;
+
+pub(crate) use skip::load_segments;
+
+use crate::db::{self, guild::FilterSettings};
+use crate::metadata::Metadata;
+use crate::state::State;
+use crate::ytdlp::Segment;
+use async_trait::async_trait;
+use dsp::{Biquad, Frame, LoudnessMeter, Normalizer, Resampler, Stretch};
+use skip::{Segments, Silence, Skip};
+use songbird::input::{
+    codecs::{get_codec_registry, get_probe},
+    AudioStream, AudioStreamError, AuxMetadata, Compose, Input, LiveInput, Parsed, YoutubeDl,
+};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Arc;
+use std::time::Duration;
+use symphonia::core::{
+    audio::SampleBuffer,
+    errors::Error as SymphoniaError,
+    formats::{SeekMode, SeekTo},
+    io::MediaSource,
+    units::Time,
+};
+use twilight_model::id::{marker::GuildMarker, Id};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum Equalizer {
+    #[default]
+    Flat,
+    BassBoost,
+    Treble,
+    Vocals,
+}
+
+impl Equalizer {
+    const ALL: [Self; 4] = [Self::Flat, Self::BassBoost, Self::Treble, Self::Vocals];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Flat => "flat",
+            Self::BassBoost => "bassboost",
+            Self::Treble => "treble",
+            Self::Vocals => "vocals",
+        }
+    }
+
+    fn parse(equalizer: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.as_str() == equalizer)
+    }
+
+    fn filters(self, rate: f64) -> Vec<Biquad> {
+        match self {
+            Self::Flat => Vec::new(),
+            Self::BassBoost => vec![Biquad::low_shelf(rate, 110.0, 8.0)],
+            Self::Treble => vec![Biquad::high_shelf(rate, 5000.0, 6.0, 0.707)],
+            Self::Vocals => vec![
+                Biquad::low_shelf(rate, 150.0, -3.0),
+                Biquad::peaking(rate, 2500.0, 4.0, 1.0),
+            ],
+        }
+    }
+}
+
+/// How the tracks of a guild are played.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Filters {
+    pub(crate) equalizer: Equalizer,
+    /// How fast tracks play, 1 is their normal speed.
+    pub(crate) speed: f64,
+    /// How much higher tracks sound, 1 is their normal pitch.
+    pub(crate) pitch: f64,
+    /// Whether quiet and loud tracks are turned to the same loudness.
+    pub(crate) normalize: bool,
+}
+
+impl Filters {
+    pub(crate) const MIN_SPEED: f64 = 0.5;
+    pub(crate) const MAX_SPEED: f64 = 2.0;
+
+    pub(crate) fn new() -> Self {
+        Self {
+            equalizer: Equalizer::Flat,
+            speed: 1.0,
+            pitch: 1.0,
+            normalize: false,
+        }
+    }
+
+    /// Speeds up tracks and raises their pitch together, like playing a record faster.
+    pub(crate) fn nightcore() -> Self {
+        Self {
+            speed: 1.25,
+            pitch: 1.25,
+            ..Self::new()
+        }
+    }
+
+    /// Slows down tracks and lowers their pitch together.
+    pub(crate) fn vaporwave() -> Self {
+        Self {
+            speed: 0.8,
+            pitch: 0.8,
+            ..Self::new()
+        }
+    }
+
+    /// Pitch factor that raises tracks by `semitones`.
+    pub(crate) fn pitch_from_semitones(semitones: i64) -> f64 {
+        2f64.powf(semitones as f64 / 12.0)
+    }
+
+    pub(crate) fn semitones(self) -> f64 {
+        12.0 * self.pitch.log2()
+    }
+
+    /// Where a track played with `previous` at `position` continues when played with `self`.
+    pub(crate) fn convert_position(self, previous: Filters, position: Duration) -> Duration {
+        position.mul_f64(previous.speed / self.speed)
+    }
+}
+
+impl Default for Filters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<FilterSettings> for Filters {
+    fn from(settings: FilterSettings) -> Self {
+        Self {
+            equalizer: Equalizer::parse(&settings.equalizer).unwrap_or_default(),
+            speed: settings.speed.clamp(Self::MIN_SPEED, Self::MAX_SPEED),
+            pitch: settings.pitch,
+            normalize: settings.normalize,
+        }
+    }
+}
+
+impl From<Filters> for FilterSettings {
+    fn from(filters: Filters) -> Self {
+        Self {
+            equalizer: filters.equalizer.as_str().to_string(),
+            speed: filters.speed,
+            pitch: filters.pitch,
+            normalize: filters.normalize,
+        }
+    }
+}
+
+/// The filters that `guild_id` saved, or none if they cannot be loaded.
+pub(crate) async fn saved_filters(state: &State, guild_id: Id<GuildMarker>) -> Filters {
+    match db::guild::filter_settings(&state.pool, guild_id.to_string()).await {
+        Ok(settings) => settings.map(Filters::from).unwrap_or_default(),
+        Err(e) => {
+            tracing::warn!(?e, "could not load the filters of {guild_id}");
+            Filters::new()
+        }
+    }
+}
+
+/// The filters of a guild, set up for the sample rate of a track.
+struct Chain {
+    equalizer: Vec<Biquad>,
+    stretch: Option<Stretch>,
+    resampler: Option<Resampler>,
+    normalizer: Option<Normalizer>,
+}
+
+impl Chain {
+    fn new(filters: Filters, rate: f64) -> Self {
+        // the resampler changes speed and pitch by the pitch factor,
+        // the stretch makes up for the rest of the speed
+        let tempo = filters.speed / filters.pitch;
+        Self {
+            equalizer: filters.equalizer.filters(rate),
+            stretch: ((tempo - 1.0).abs() > 1e-3).then(|| Stretch::new(rate, tempo)),
+            resampler: ((filters.pitch - 1.0).abs() > 1e-3)
+                .then(|| Resampler::new(rate, filters.pitch)),
+            normalizer: filters.normalize.then(|| Normalizer::new(rate)),
+        }
+    }
+
+    fn process(&mut self, mut frames: Vec<Frame>) -> Vec<Frame> {
+        for frame in &mut frames {
+            for filter in &mut self.equalizer {
+                *frame = filter.process(*frame);
+            }
+        }
+        if let Some(stretch) = &mut self.stretch {
+            let mut out = Vec::with_capacity(frames.len() * 2);
+            stretch.process(&frames, &mut out);
+            frames = out;
+        }
+        if let Some(resampler) = &mut self.resampler {
+            let mut out = Vec::with_capacity(frames.len() * 2);
+            resampler.process(&frames, &mut out);
+            frames = out;
+        }
+        if let Some(normalizer) = &mut self.normalizer {
+            for frame in &mut frames {
+                *frame = normalizer.process(*frame);
+            }
+        }
+        frames
+    }
+}
+
+/// Called with the integrated loudness of a track in LUFS, once it was played to the end.
+pub(crate) type LoudnessCallback = Arc<dyn Fn(f64) + Send + Sync>;
+
+/// Gain in dB that brings a track of `loudness` LUFS to the loudness all tracks play at.
+pub(crate) fn normalization_gain(loudness: f64) -> f64 {
+    // turning tracks up much more makes them clip
+    (dsp::TARGET_LUFS - loudness).clamp(-20.0, 6.0)
+}
+
+/// Creates the input of the track of `metadata` that is played with the filters of `guild_id`.
+///
+/// `on_loudness` is called if the track is played to the end.
+pub(crate) fn input(
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    metadata: &Metadata,
+    on_loudness: Option<LoudnessCallback>,
+) -> Input {
+    Input::Lazy(Box::new(FilteredInput {
+        src: metadata.src.clone(),
+        url: metadata.url.clone(),
+        start: metadata.section.start,
+        state: State::clone(state),
+        guild_id,
+        on_loudness,
+    }))
+}
+
+/// Looks up the filters of a guild whenever the track is started, or restarted to
+/// change them.
+struct FilteredInput {
+    src: YoutubeDl<'static>,
+    url: String,
+    /// Where the section of the track that is played starts.
+    start: Duration,
+    state: State,
+    guild_id: Id<GuildMarker>,
+    on_loudness: Option<LoudnessCallback>,
+}
+
+impl FilteredInput {
+    fn wrap(
+        &self,
+        stream: AudioStream<Box<dyn MediaSource>>,
+        skip_silence: bool,
+        segments: &[Segment],
+    ) -> AudioStream<Box<dyn MediaSource>> {
+        let filters = self
+            .state
+            .guild_settings
+            .get(&self.guild_id)
+            .map(|settings| settings.filters)
+            .unwrap_or_default();
+        AudioStream {
+            input: Box::new(FilteredSource::new(
+                stream,
+                filters,
+                self.on_loudness.clone(),
+                skip_silence.then(Silence::new),
+                Segments::new(self.start, segments),
+            )),
+            hint: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Compose for FilteredInput {
+    fn create(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        // what to skip is only looked up when creating asynchronously, like yt-dlp does
+        let stream = self.src.create()?;
+        Ok(self.wrap(stream, false, &[]))
+    }
+
+    async fn create_async(
+        &mut self,
+    ) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        let skip = db::guild::skip_settings(&self.state.pool, self.guild_id.to_string())
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("could not get the skip settings: {e:?}");
+                Default::default()
+            });
+        let segments = match skip.segments {
+            true => load_segments(&self.state, &self.url).await,
+            false => Vec::new(),
+        };
+        let stream = self.src.create_async().await?;
+        Ok(self.wrap(stream, skip.silence, &segments))
+    }
+
+    fn should_create_async(&self) -> bool {
+        self.src.should_create_async()
+    }
+
+    async fn aux_metadata(&mut self) -> Result<AuxMetadata, AudioStreamError> {
+        self.src.aux_metadata().await
+    }
+}
+
+enum Decoding {
+    /// The stream is probed when it is first read, where blocking is fine.
+    Pending(AudioStream<Box<dyn MediaSource>>),
+    Running {
+        parsed: Box<Parsed>,
+        chain: Option<Box<Chain>>,
+    },
+    Finished,
+}
+
+/// Decodes a stream and passes the filtered samples on in songbird's raw format.
+///
+/// Only seeking forward is supported, so songbird recreates the stream to seek back.
+struct FilteredSource {
+    decoding: Decoding,
+    filters: Filters,
+    /// Bytes that are ready to be read.
+    buffer: Vec<u8>,
+    read: usize,
+    /// Position in the output, in bytes.
+    position: u64,
+    /// Measures the unfiltered track, once its sample rate is known.
+    meter: Option<LoudnessMeter>,
+    on_loudness: Option<LoudnessCallback>,
+    silence: Option<Silence>,
+    segments: Segments,
+    /// Sample rate of the output, once the header with it was written.
+    rate: Option<u32>,
+    /// Time in the track of the last decoded packet, in seconds.
+    time: f64,
+}
+
+/// Bytes of a stereo frame in songbird's raw format.
+const FRAME_BYTES: u64 = 2 * std::mem::size_of::<f32>() as u64;
+
+/// Seeks further ahead than this, in seconds, seek in the track instead of decoding up to
+/// the target.
+const SKIP_DECODING_SECS: f64 = 10.0;
+
+impl FilteredSource {
+    fn new(
+        stream: AudioStream<Box<dyn MediaSource>>,
+        filters: Filters,
+        on_loudness: Option<LoudnessCallback>,
+        silence: Option<Silence>,
+        segments: Segments,
+    ) -> Self {
+        Self {
+            decoding: Decoding::Pending(stream),
+            filters,
+            buffer: Vec::new(),
+            read: 0,
+            position: 0,
+            meter: None,
+            on_loudness,
+            silence,
+            segments,
+            rate: None,
+            time: 0.0,
+        }
+    }
+
+    /// Seeks in the track itself to the output byte `target`, which is much faster than
+    /// decoding up to it from far away. Returns whether that worked.
+    fn skip_ahead(&mut self, target: u64) -> bool {
+        let (Some(rate), Decoding::Running { parsed, chain }) = (self.rate, &mut self.decoding)
+        else {
+            return false;
+        };
+        let ahead = (target - self.position) as f64 / FRAME_BYTES as f64 / f64::from(rate);
+        if ahead < SKIP_DECODING_SECS {
+            return false;
+        }
+        // the output is faster or slower than the track by the speed
+        let time = self.time + ahead * self.filters.speed;
+        let to = SeekTo::Time {
+            time: Time::from(time),
+            track_id: Some(parsed.track_id),
+        };
+        if let Err(e) = parsed.format.seek(SeekMode::Accurate, to) {
+            tracing::debug!("could not seek in the track, decoding up to the target: {e}");
+            return false;
+        }
+        parsed.decoder.reset();
+        // the filters start over, so they do not mix in the samples from before
+        *chain = Some(Box::new(Chain::new(self.filters, f64::from(rate))));
+        // a track that was not played in full is not measured
+        self.meter = None;
+        self.buffer.clear();
+        self.read = 0;
+        self.position = target;
+        self.time = time;
+        true
+    }
+
+    fn finish(&mut self) {
+        let loudness = self.meter.take().and_then(|meter| meter.integrated());
+        if let (Some(loudness), Some(on_loudness)) = (loudness, self.on_loudness.take()) {
+            on_loudness(loudness);
+        }
+    }
+
+    /// Decodes the next packet into the buffer, returns `false` at the end of the stream.
+    fn fill(&mut self) -> io::Result<bool> {
+        self.buffer.clear();
+        self.read = 0;
+        loop {
+            match std::mem::replace(&mut self.decoding, Decoding::Finished) {
+                Decoding::Pending(stream) => {
+                    let parsed =
+                        match LiveInput::Raw(stream).promote(get_codec_registry(), get_probe()) {
+                            Ok(LiveInput::Parsed(parsed)) => parsed,
+                            Ok(_) => unreachable!("promoting parses the stream"),
+                            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                        };
+                    self.decoding = Decoding::Running {
+                        parsed: Box::new(parsed),
+                        chain: None,
+                    };
+                }
+                Decoding::Running { mut parsed, chain } => {
+                    let packet = match parsed.format.next_packet() {
+                        Ok(packet) => packet,
+                        Err(SymphoniaError::IoError(e))
+                            if e.kind() == io::ErrorKind::UnexpectedEof =>
+                        {
+                            self.finish();
+                            return Ok(false);
+                        }
+                        Err(SymphoniaError::ResetRequired) => {
+                            parsed.decoder.reset();
+                            self.decoding = Decoding::Running { parsed, chain };
+                            continue;
+                        }
+                        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                    };
+                    if packet.track_id() != parsed.track_id {
+                        self.decoding = Decoding::Running { parsed, chain };
+                        continue;
+                    }
+                    if let Some(time_base) = parsed.decoder.codec_params().time_base {
+                        let time = time_base.calc_time(packet.ts());
+                        self.time = time.seconds as f64 + time.frac;
+                        match self.segments.check(self.time) {
+                            Skip::Keep => {}
+                            Skip::Drop => {
+                                self.decoding = Decoding::Running { parsed, chain };
+                                continue;
+                            }
+                            Skip::SeekTo(end) => {
+                                let to = SeekTo::Time {
+                                    time: Time::from(end),
+                                    track_id: Some(parsed.track_id),
+                                };
+                                match parsed.format.seek(SeekMode::Accurate, to) {
+                                    Ok(_) => parsed.decoder.reset(),
+                                    Err(e) => tracing::debug!("could not seek past a segment: {e}"),
+                                }
+                                self.decoding = Decoding::Running { parsed, chain };
+                                continue;
+                            }
+                        }
+                    }
+                    let decoded = match parsed.decoder.decode(&packet) {
+                        Ok(decoded) => decoded,
+                        // skip broken packets like songbird does
+                        Err(SymphoniaError::DecodeError(e)) => {
+                            tracing::debug!("could not decode packet: {e}");
+                            self.decoding = Decoding::Running { parsed, chain };
+                            continue;
+                        }
+                        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                    };
+                    let spec = *decoded.spec();
+                    let channels = spec.channels.count();
+                    let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+                    samples.copy_interleaved_ref(decoded);
+                    let frames: Vec<Frame> = samples
+                        .samples()
+                        .chunks_exact(channels)
+                        .map(|frame| match frame {
+                            [mono] => [*mono, *mono],
+                            [left, right, ..] => [*left, *right],
+                            [] => unreachable!("chunks are not empty"),
+                        })
+                        .collect();
+
+                    let mut chain = match chain {
+                        Some(chain) => chain,
+                        None => {
+                            // songbird's raw format starts with the sample rate and channels
+                            self.buffer.extend_from_slice(b"SbirdRaw");
+                            self.buffer.extend_from_slice(&spec.rate.to_le_bytes());
+                            self.buffer.extend_from_slice(&2u32.to_le_bytes());
+                            self.rate = Some(spec.rate);
+                            if self.on_loudness.is_some() {
+                                self.meter = Some(LoudnessMeter::new(f64::from(spec.rate)));
+                            }
+                            Box::new(Chain::new(self.filters, f64::from(spec.rate)))
+                        }
+                    };
+                    if let Some(meter) = &mut self.meter {
+                        for &frame in &frames {
+                            meter.process(frame);
+                        }
+                    }
+                    let frames = match &mut self.silence {
+                        Some(silence) => silence.process(frames),
+                        None => frames,
+                    };
+                    for frame in chain.process(frames) {
+                        for sample in frame {
+                            self.buffer.extend_from_slice(&sample.to_le_bytes());
+                        }
+                    }
+                    self.decoding = Decoding::Running {
+                        parsed,
+                        chain: Some(chain),
+                    };
+                    if !self.buffer.is_empty() {
+                        return Ok(true);
+                    }
+                }
+                Decoding::Finished => return Ok(false),
+            }
+        }
+    }
+}
+
+impl Read for FilteredSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.read == self.buffer.len() && !self.fill()? {
+            return Ok(0);
+        }
+        let count = buf.len().min(self.buffer.len() - self.read);
+        buf[..count].copy_from_slice(&self.buffer[self.read..self.read + count]);
+        self.read += count;
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Seek for FilteredSource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(target) => target,
+            SeekFrom::Current(delta) => self.position.saturating_add_signed(delta),
+            SeekFrom::End(_) => return Err(io::ErrorKind::Unsupported.into()),
+        };
+        if target < self.position {
+            return Err(io::ErrorKind::Unsupported.into());
+        }
+        if self.skip_ahead(target) {
+            return Ok(self.position);
+        }
+        // the samples up to the target are filtered anyway, to keep the filters in their state
+        let mut discard = [0; 4096];
+        while self.position < target {
+            let count = (target - self.position).min(discard.len() as u64) as usize;
+            if self.read(&mut discard[..count])? == 0 {
+                break;
+            }
+        }
+        Ok(self.position)
+    }
+}
+
+impl MediaSource for FilteredSource {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters() {
+        assert!((Filters::pitch_from_semitones(12) - 2.0).abs() < 1e-9);
+        assert!((Filters::pitch_from_semitones(-12) - 0.5).abs() < 1e-9);
+        let filters = Filters {
+            pitch: Filters::pitch_from_semitones(3),
+            ..Filters::new()
+        };
+        assert!((filters.semitones() - 3.0).abs() < 1e-9);
+
+        assert_eq!(
+            Filters::new().convert_position(Filters::nightcore(), Duration::from_secs(80)),
+            Duration::from_secs(100)
+        );
+
+        let filters = Filters {
+            equalizer: Equalizer::Vocals,
+            normalize: true,
+            ..Filters::vaporwave()
+        };
+        assert_eq!(Filters::from(FilterSettings::from(filters)), filters);
+    }
+
+    #[test]
+    fn test_chain_speed() {
+        let frames = vec![[0.25; 2]; 48_000];
+        for filters in [
+            Filters::new(),
+            Filters::nightcore(),
+            Filters {
+                speed: 0.5,
+                ..Filters::new()
+            },
+            Filters {
+                pitch: 0.5,
+                ..Filters::new()
+            },
+        ] {
+            let mut chain = Chain::new(filters, 48_000.0);
+            let out: usize = frames
+                .chunks(960)
+                .map(|chunk| chain.process(chunk.to_vec()).len())
+                .sum();
+            let ratio = out as f64 / (48_000.0 / filters.speed);
+            // the stretch holds back about a window
+            assert!((0.94..1.01).contains(&ratio), "{filters:?}: {out} frames");
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use super::dsp::Frame;
+use crate::db;
+use crate::state::State;
+use crate::ytdlp::Segment;
+use std::time::Duration;
+
+/// Samples quieter than this, about -50 dBFS, are silence.
+const SILENCE: f32 = 0.003;
+
+/// Drops the silence at the start and the end of a track.
+///
+/// Silence within the track is played as it was, which is only known once the track goes on.
+pub(super) struct Silence {
+    started: bool,
+    /// Silent frames since the last sound.
+    pending: usize,
+}
+
+impl Silence {
+    pub(super) fn new() -> Self {
+        Self {
+            started: false,
+            pending: 0,
+        }
+    }
+
+    pub(super) fn process(&mut self, frames: Vec<Frame>) -> Vec<Frame> {
+        let mut output = Vec::with_capacity(frames.len());
+        for frame in frames {
+            if frame.iter().all(|sample| sample.abs() < SILENCE) {
+                if self.started {
+                    self.pending += 1;
+                }
+                continue;
+            }
+            self.started = true;
+            output.resize(output.len() + std::mem::take(&mut self.pending), [0.0; 2]);
+            output.push(frame);
+        }
+        output
+    }
+}
+
+/// What to do with a packet of a track.
+#[derive(Debug, PartialEq)]
+pub(super) enum Skip {
+    Keep,
+    Drop,
+    /// Seek to the time in seconds, and drop the packet if that fails.
+    SeekTo(f64),
+}
+
+/// Segments of a track that are skipped, and the part before the start of its section.
+pub(super) struct Segments {
+    /// Start and end in seconds, ordered by the start.
+    ranges: Vec<(f64, f64)>,
+    next: usize,
+    /// Whether the next segment was sought past already.
+    sought: bool,
+}
+
+impl Segments {
+    pub(super) fn new(start: Duration, segments: &[Segment]) -> Self {
+        let mut ranges: Vec<_> = segments
+            .iter()
+            .map(|segment| (segment.start_time, segment.end_time))
+            .collect();
+        if !start.is_zero() {
+            ranges.push((0.0, start.as_secs_f64()));
+        }
+        ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            ranges,
+            next: 0,
+            sought: false,
+        }
+    }
+
+    /// Decides about the packet at `time` in seconds.
+    ///
+    /// Packets in a segment are sought past once, as seeking may stop a bit before the
+    /// end of the segment.
+    pub(super) fn check(&mut self, time: f64) -> Skip {
+        while let Some(&(_, end)) = self.ranges.get(self.next) {
+            if time < end {
+                break;
+            }
+            self.next += 1;
+            self.sought = false;
+        }
+        match self.ranges.get(self.next) {
+            Some(&(start, end)) if time >= start => {
+                if std::mem::replace(&mut self.sought, true) {
+                    Skip::Drop
+                } else {
+                    Skip::SeekTo(end)
+                }
+            }
+            _ => Skip::Keep,
+        }
+    }
+}
+
+/// Segments of the track at `url` that are not music, from the cache or from yt-dlp.
+pub(crate) async fn load_segments(state: &State, url: &str) -> Vec<Segment> {
+    match db::track::cached_segments(&state.pool, url.to_string()).await {
+        Ok(Some(json)) => match serde_json::from_str(&json) {
+            Ok(segments) => return segments,
+            Err(e) => tracing::warn!("could not parse the cached segments of {url}: {e:?}"),
+        },
+        Ok(None) => {}
+        Err(e) => tracing::warn!("could not get the cached segments of {url}: {e:?}"),
+    }
+
+    let segments = match state.ytdlp.segments(url.to_string()).await {
+        Ok(segments) => segments,
+        Err(e) => {
+            tracing::debug!("could not get the segments of {url}: {e:?}");
+            return Vec::new();
+        }
+    };
+    let cached = match serde_json::to_string(&segments) {
+        Ok(json) => db::track::cache_segments(&state.pool, url.to_string(), json)
+            .await
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = cached {
+        tracing::warn!("could not cache the segments of {url}: {e}");
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_silence() {
+        let mut silence = Silence::new();
+        let quiet = [0.001, -0.001];
+        let loud = [0.5, -0.5];
+        assert!(silence.process(vec![quiet; 100]).is_empty());
+        assert_eq!(silence.process(vec![loud; 10]), vec![loud; 10]);
+        // silence within the track is kept, as digital silence
+        assert!(silence.process(vec![quiet; 5]).is_empty());
+        let output = silence.process(vec![loud, quiet]);
+        assert_eq!(output.len(), 6);
+        assert_eq!(output[..5], [[0.0; 2]; 5]);
+        assert_eq!(output[5], loud);
+        // the trailing silence is never played
+        assert!(silence.process(vec![quiet; 100]).is_empty());
+    }
+
+    #[test]
+    fn test_segments() {
+        let segment = |start_time, end_time| Segment {
+            start_time,
+            end_time,
+            category: "sponsor".to_string(),
+        };
+        let mut segments = Segments::new(
+            Duration::from_secs(3),
+            &[segment(30.0, 40.0), segment(1.0, 5.0)],
+        );
+        assert_eq!(segments.check(0.0), Skip::SeekTo(3.0));
+        assert_eq!(segments.check(2.9), Skip::Drop);
+        // overlapping segments are sought past one after the other
+        assert_eq!(segments.check(3.0), Skip::SeekTo(5.0));
+        assert_eq!(segments.check(4.9), Skip::Drop);
+        assert_eq!(segments.check(5.0), Skip::Keep);
+        assert_eq!(segments.check(29.0), Skip::Keep);
+        assert_eq!(segments.check(31.0), Skip::SeekTo(40.0));
+        assert_eq!(segments.check(39.0), Skip::Drop);
+        assert_eq!(segments.check(41.0), Skip::Keep);
+    }
+}
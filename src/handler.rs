@@ -1,47 +1,39 @@
-use crate::commands::queue::{build_action_row, build_queue_embeds, TRACKS_PER_PAGE};
-use crate::commands::{
-    delete, join, leave, leave_if_alone, loop_queue, pause, play, queue, resume, skip, stop,
-};
-use crate::interaction_commands::InteractionCommand;
+use crate::cleanup::disable_components;
+use crate::commands::queue::{build_action_row, build_queue_embeds};
+use crate::commands::{leave_if_alone, like, registry, run_handler, Registry, LIKE_PREFIX};
+use crate::db;
+use crate::i18n::Locale;
 use crate::state::State;
-use crate::utils::spawn;
+use crate::utils::{created_at, spawn, unix_now};
 use anyhow::Context;
 use std::sync::Arc;
 use twilight_gateway::Event;
 use twilight_model::application::interaction::message_component::MessageComponentInteractionData;
 use twilight_model::application::interaction::InteractionData;
+use twilight_model::channel::Message;
 use twilight_model::gateway::payload::incoming::InteractionCreate;
 use twilight_model::http::interaction::{InteractionResponse, InteractionResponseType};
 use twilight_util::builder::InteractionResponseDataBuilder;
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub(crate) struct Handler {
     state: State,
+    registry: Arc<Registry>,
 }
 
 impl Handler {
     pub(crate) fn new(state: State) -> Self {
-        Self { state }
+        Self {
+            state,
+            registry: Arc::new(registry()),
+        }
     }
     pub(crate) async fn act(&self, event: Event) -> anyhow::Result<()> {
-        self.handle_messages(&event).await?;
         self.handle_voice_state_update(&event).await?;
         self.handle_interaction(&event).await?;
         Ok(())
     }
 
-    async fn handle_messages(&self, event: &Event) -> anyhow::Result<()> {
-        match event {
-            Event::MessageCreate(message) if message.content.starts_with('!') => {
-                if message.content.contains("!delete") {
-                    spawn(delete(message.0.clone(), Arc::clone(&self.state)));
-                }
-                Ok(())
-            }
-            _ => Ok(()),
-        }
-    }
-
     async fn handle_voice_state_update(&self, event: &Event) -> anyhow::Result<()> {
         match event {
             Event::VoiceStateUpdate(update) => {
@@ -56,8 +48,8 @@ impl Handler {
     async fn handle_interaction(&self, event: &Event) -> anyhow::Result<()> {
         match event {
             Event::InteractionCreate(interaction) => match &interaction.data {
-                Some(InteractionData::ApplicationCommand(command)) => {
-                    self.handle_application_command(command.clone().into(), interaction.clone())
+                Some(InteractionData::ApplicationCommand(_)) => {
+                    self.handle_application_command(interaction.clone())
                 }
                 Some(InteractionData::MessageComponent(data)) => {
                     self.handle_message_component(data, interaction.clone())
@@ -71,26 +63,12 @@ impl Handler {
 
     fn handle_application_command(
         &self,
-        command: InteractionCommand,
         interaction: Box<InteractionCreate>,
     ) -> anyhow::Result<()> {
-        {
-            match command {
-                InteractionCommand::Play(query) => {
-                    spawn(play(interaction, Arc::clone(&self.state), query))
-                }
-                InteractionCommand::Stop => spawn(stop(interaction, Arc::clone(&self.state))),
-                InteractionCommand::Pause => spawn(pause(interaction, Arc::clone(&self.state))),
-                InteractionCommand::Skip => spawn(skip(interaction, Arc::clone(&self.state))),
-                InteractionCommand::Loop => spawn(loop_queue(interaction, Arc::clone(&self.state))),
-                InteractionCommand::Resume => spawn(resume(interaction, Arc::clone(&self.state))),
-                InteractionCommand::Leave => spawn(leave(interaction, Arc::clone(&self.state))),
-                InteractionCommand::Join => spawn(join(interaction, Arc::clone(&self.state))),
-                InteractionCommand::Queue => spawn(queue(interaction, Arc::clone(&self.state))),
-                _ => {}
-            }
-            Ok(())
-        }
+        let registry = Arc::clone(&self.registry);
+        let state = Arc::clone(&self.state);
+        spawn(async move { registry.dispatch(interaction, state).await });
+        Ok(())
     }
 
     async fn handle_message_component(
@@ -98,6 +76,14 @@ impl Handler {
         data: &MessageComponentInteractionData,
         interaction: Box<InteractionCreate>,
     ) -> anyhow::Result<()> {
+        if let Some(target) = data.custom_id.strip_prefix(LIKE_PREFIX) {
+            let state = Arc::clone(&self.state);
+            let target = target.to_string();
+            spawn(async move {
+                run_handler(&interaction, &state, like(&interaction, &state, &target)).await
+            });
+            return Ok(());
+        }
         if !data.custom_id.starts_with("page:") {
             return Ok(());
         }
@@ -107,16 +93,31 @@ impl Handler {
             .parse::<usize>()
             .unwrap_or(0);
 
+        if let Some(message) = &interaction.message {
+            let age = unix_now().saturating_sub(created_at(message.id));
+            if age > self.state.config.queue.pagination_timeout() {
+                return self.disable_pagination(message, &interaction).await;
+            }
+        }
+
         if let Some(guild_id) = interaction.guild_id {
+            let locale = Locale::for_interaction(&interaction, &self.state).await;
             let mut queue = Vec::new();
             if let Some(call_lock) = self.state.songbird.get(guild_id) {
                 let call = call_lock.lock().await;
                 queue = call.queue().current_queue();
             }
-            let n_pages = queue.len().div_ceil(TRACKS_PER_PAGE);
-            let page = page.min(n_pages - 1).max(0);
-            let embeds = build_queue_embeds(&queue, page).await;
-            let action_row = build_action_row(page, n_pages);
+            let tracks_per_page = self.state.config.queue.tracks_per_page;
+            let n_pages = queue.len().div_ceil(tracks_per_page);
+            let page = page.min(n_pages - 1);
+            let filters = self
+                .state
+                .guild_settings
+                .get(&guild_id)
+                .map(|settings| settings.filters)
+                .unwrap_or_default();
+            let embeds = build_queue_embeds(&queue, page, tracks_per_page, filters, locale).await;
+            let action_row = build_action_row(page, n_pages, locale);
 
             let interaction_response_data = InteractionResponseDataBuilder::new()
                 .embeds(embeds)
@@ -136,4 +137,26 @@ impl Handler {
             Ok(())
         }
     }
+
+    /// Answers a click on a stale queue by disabling its buttons.
+    async fn disable_pagination(
+        &self,
+        message: &Message,
+        interaction: &InteractionCreate,
+    ) -> anyhow::Result<()> {
+        let interaction_response_data = InteractionResponseDataBuilder::new()
+            .components(disable_components(message.components.clone()))
+            .build();
+        let response = InteractionResponse {
+            kind: InteractionResponseType::UpdateMessage,
+            data: Some(interaction_response_data),
+        };
+        self.state
+            .http
+            .interaction(interaction.application_id)
+            .create_response(interaction.id, &interaction.token, &response)
+            .await?;
+        db::message::disable_bot_message(&self.state.pool, message.id.to_string()).await?;
+        Ok(())
+    }
 }
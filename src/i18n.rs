@@ -0,0 +1,262 @@
+use crate::db;
+use crate::state::State;
+use std::{collections::HashMap, fmt, sync::OnceLock};
+use twilight_model::{
+    application::command::{Command, CommandOption},
+    gateway::payload::incoming::InteractionCreate,
+};
+
+/// Languages the bot can reply in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum Locale {
+    #[default]
+    En,
+    De,
+}
+
+type Catalog = HashMap<String, String>;
+
+impl Locale {
+    pub(crate) const ALL: [Self; 2] = [Self::En, Self::De];
+
+    /// Code stored in the database and offered in `/language`.
+    pub(crate) fn code(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::De => "de",
+        }
+    }
+
+    /// Parses our own codes as well as Discord locales like `en-US`.
+    pub(crate) fn from_code(code: &str) -> Option<Self> {
+        match code.split('-').next()? {
+            "en" => Some(Self::En),
+            "de" => Some(Self::De),
+            _ => None,
+        }
+    }
+
+    /// Discord locales that command names and descriptions are translated for.
+    fn discord_locales(self) -> &'static [&'static str] {
+        match self {
+            Self::En => &["en-US", "en-GB"],
+            Self::De => &["de"],
+        }
+    }
+
+    fn catalog(self) -> &'static Catalog {
+        static EN: OnceLock<Catalog> = OnceLock::new();
+        static DE: OnceLock<Catalog> = OnceLock::new();
+        let (catalog, source) = match self {
+            Self::En => (&EN, include_str!("../locales/en.toml")),
+            Self::De => (&DE, include_str!("../locales/de.toml")),
+        };
+        catalog.get_or_init(|| {
+            let table = toml::from_str(source).expect("message catalogs are valid toml");
+            let mut catalog = Catalog::new();
+            flatten("", table, &mut catalog);
+            catalog
+        })
+    }
+
+    /// Looks up `key`, falling back to English and then to the key itself.
+    pub(crate) fn text(self, key: &str) -> &str {
+        self.catalog()
+            .get(key)
+            .or_else(|| Self::En.catalog().get(key))
+            .map_or(key, String::as_str)
+    }
+
+    /// Looks up `key` and fills in its `{placeholders}`.
+    pub(crate) fn format(self, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+        args.iter()
+            .fold(self.text(key).to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{name}}}"), &value.to_string())
+            })
+    }
+
+    /// Language to reply to `interaction` in.
+    ///
+    /// A language set with `/language` wins over the server's language, which
+    /// wins over the language of the user.
+    pub(crate) async fn for_interaction(interaction: &InteractionCreate, state: &State) -> Self {
+        let mut configured = None;
+        if let Some(guild_id) = interaction.guild_id {
+            match db::guild::guild_locale(&state.pool, guild_id.to_string()).await {
+                Ok(locale) => configured = locale,
+                Err(e) => tracing::warn!(?e, "could not load locale of guild {guild_id}"),
+            }
+        }
+        Self::choose(
+            configured.as_deref(),
+            interaction.guild_locale.as_deref(),
+            interaction.locale.as_deref(),
+        )
+    }
+
+    fn choose(configured: Option<&str>, guild: Option<&str>, user: Option<&str>) -> Self {
+        [configured, guild, user]
+            .into_iter()
+            .flatten()
+            .find_map(Self::from_code)
+            .unwrap_or_default()
+    }
+}
+
+fn flatten(prefix: &str, table: toml::Table, catalog: &mut Catalog) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            toml::Value::String(text) => {
+                catalog.insert(key, text);
+            }
+            toml::Value::Table(table) => flatten(&key, table, catalog),
+            value => panic!("{key} in a message catalog is not a string: {value}"),
+        }
+    }
+}
+
+/// Adds the translations of the catalogs to the name and description of
+/// `command` and its options.
+pub(crate) fn localize_command(command: &mut Command) {
+    let prefix = format!("commands.{}", command.name);
+    command.name_localizations = translations(&format!("{prefix}.name"));
+    command.description_localizations = translations(&format!("{prefix}.description"));
+    for option in &mut command.options {
+        localize_option(&prefix, option);
+    }
+}
+
+fn localize_option(prefix: &str, option: &mut CommandOption) {
+    let prefix = format!("{prefix}.options.{}", option.name);
+    option.name_localizations = translations(&format!("{prefix}.name"));
+    option.description_localizations = translations(&format!("{prefix}.description"));
+    for option in option.options.iter_mut().flatten() {
+        localize_option(&prefix, option);
+    }
+}
+
+/// Translations of `key` by Discord locale, if there are any.
+fn translations(key: &str) -> Option<HashMap<String, String>> {
+    let translations: HashMap<_, _> = Locale::ALL
+        .into_iter()
+        .filter_map(|locale| Some((locale, locale.catalog().get(key)?)))
+        .flat_map(|(locale, text)| {
+            locale
+                .discord_locales()
+                .iter()
+                .map(|discord_locale| (discord_locale.to_string(), text.clone()))
+        })
+        .collect();
+    (!translations.is_empty()).then_some(translations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::get_chat_commands;
+    use std::collections::BTreeSet;
+
+    fn message_keys(locale: Locale) -> BTreeSet<&'static str> {
+        locale
+            .catalog()
+            .keys()
+            .map(String::as_str)
+            .filter(|key| !key.starts_with("commands."))
+            .collect()
+    }
+
+    fn placeholders(text: &str) -> BTreeSet<&str> {
+        text.split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}'))
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    #[test]
+    fn test_all_keys_in_every_locale() {
+        let english = message_keys(Locale::En);
+        for locale in Locale::ALL {
+            let keys = message_keys(locale);
+            let missing: Vec<_> = english.difference(&keys).collect();
+            let unknown: Vec<_> = keys.difference(&english).collect();
+            assert!(missing.is_empty(), "{locale:?} lacks {missing:?}");
+            assert!(unknown.is_empty(), "{locale:?} has unknown {unknown:?}");
+            for key in keys {
+                assert_eq!(
+                    placeholders(locale.text(key)),
+                    placeholders(Locale::En.text(key)),
+                    "placeholders of {key} in {locale:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_all_commands_in_every_locale() {
+        fn check(locale: Locale, prefix: &str, options: &[CommandOption]) {
+            for option in options {
+                let prefix = format!("{prefix}.options.{}", option.name);
+                for field in ["name", "description"] {
+                    let key = format!("{prefix}.{field}");
+                    assert!(
+                        locale.catalog().contains_key(&key),
+                        "{locale:?} lacks {key}"
+                    );
+                }
+                check(
+                    locale,
+                    &prefix,
+                    option.options.as_deref().unwrap_or_default(),
+                );
+            }
+        }
+
+        for locale in Locale::ALL.into_iter().filter(|&l| l != Locale::default()) {
+            for command in get_chat_commands() {
+                let prefix = format!("commands.{}", command.name);
+                for field in ["name", "description"] {
+                    let key = format!("{prefix}.{field}");
+                    assert!(
+                        locale.catalog().contains_key(&key),
+                        "{locale:?} lacks {key}"
+                    );
+                }
+                check(locale, &prefix, &command.options);
+            }
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            Locale::De.format("play.added_tracks", &[("count", &3)]),
+            "3 Titel zur Warteschlange hinzugefügt."
+        );
+        assert_eq!(Locale::En.text("no.such.key"), "no.such.key");
+    }
+
+    #[test]
+    fn test_choose() {
+        assert_eq!(Locale::choose(None, None, None), Locale::En);
+        assert_eq!(Locale::choose(None, None, Some("de")), Locale::De);
+        assert_eq!(Locale::choose(None, Some("en-GB"), Some("de")), Locale::En);
+        assert_eq!(Locale::choose(Some("de"), Some("en-US"), None), Locale::De);
+        assert_eq!(Locale::choose(None, Some("ja"), Some("de")), Locale::De);
+    }
+
+    #[test]
+    fn test_localize_command() {
+        let commands = get_chat_commands();
+        let play = commands.iter().find(|c| c.name == "play").unwrap();
+        let names = play.name_localizations.as_ref().unwrap();
+        assert_eq!(names["de"], "abspielen");
+        let options = play.options[0].name_localizations.as_ref().unwrap();
+        assert_eq!(options["de"], "suche");
+    }
+}
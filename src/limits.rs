@@ -0,0 +1,270 @@
+use crate::commands::CommandError;
+use crate::db::{self, guild::QueueLimits};
+use crate::metadata::Metadata;
+use crate::section::Section;
+use crate::state::State;
+use crate::utils::parse_duration;
+use crate::ytdlp::YouTubeTrack;
+use std::collections::VecDeque;
+use std::time::Duration;
+use twilight_model::id::{
+    marker::{GuildMarker, UserMarker},
+    Id,
+};
+
+/// The tracks that a member may add, and how many were left out.
+#[derive(Debug)]
+pub(crate) struct Admitted<T = YouTubeTrack> {
+    pub(crate) tracks: Vec<T>,
+    pub(crate) left_out: usize,
+}
+
+/// Keeps the `tracks` that fit the `limits` of a guild, for a member who has `queued`
+/// tracks in its queue already. A track plays for its `duration`.
+///
+/// Tracks that are too long are left out, and playlists are cut to what the member can add.
+/// Fails if none of the tracks can be added.
+pub(crate) fn admit<T>(
+    limits: QueueLimits,
+    tracks: Vec<T>,
+    queued: usize,
+    duration: impl Fn(&T) -> Option<Duration>,
+) -> Result<Admitted<T>, CommandError> {
+    let count = tracks.len();
+    let mut tracks = tracks;
+    if let Some(max) = limits.track_duration_secs {
+        let max = Duration::from_secs(max as u64);
+        // live streams have no duration
+        tracks.retain(|track| duration(track).is_none_or(|d| d <= max));
+        if tracks.is_empty() && count > 0 {
+            return Err(CommandError::TrackTooLong(max));
+        }
+    }
+    if let Some(max) = limits.playlist_size {
+        tracks.truncate(max as usize);
+    }
+    if let Some(max) = limits.tracks_per_user {
+        let room = (max as usize).saturating_sub(queued);
+        if room == 0 {
+            return Err(CommandError::QueueLimitReached(max));
+        }
+        tracks.truncate(room);
+    }
+    let left_out = count - tracks.len();
+    Ok(Admitted { tracks, left_out })
+}
+
+/// Like [`admit`] for tracks resolved with yt-dlp, of which only `section` plays if there
+/// is a single one.
+pub(crate) fn admit_resolved(
+    limits: QueueLimits,
+    tracks: Vec<YouTubeTrack>,
+    queued: usize,
+    section: Section,
+) -> Result<Admitted, CommandError> {
+    let section = match tracks.len() {
+        1 => section,
+        _ => Section::default(),
+    };
+    admit(limits, tracks, queued, |track| {
+        section.duration(parse_duration(&track.duration_string))
+    })
+}
+
+/// Tracks that `user_id` added to the queue of `guild_id`, including the one playing.
+pub(crate) async fn queued_by(
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    user_id: Id<UserMarker>,
+) -> usize {
+    let Some(call_lock) = state.songbird.get(guild_id) else {
+        return 0;
+    };
+    let queue = call_lock.lock().await.queue().current_queue();
+    queue
+        .iter()
+        .filter(|handle| handle.data::<Metadata>().requester == Some(user_id))
+        .count()
+}
+
+/// Order in which tracks added by `requesters` play, taking turns between the requesters.
+///
+/// Requesters take turns in the order they first appear, except that whoever added the
+/// track that plays, `current`, goes last.
+fn fair_order<T: PartialEq>(current: Option<&T>, requesters: &[T]) -> Vec<usize> {
+    let mut turns: Vec<(&T, Vec<usize>)> = Vec::new();
+    for (index, requester) in requesters.iter().enumerate() {
+        match turns.iter_mut().find(|(r, _)| *r == requester) {
+            Some((_, indices)) => indices.push(index),
+            None => turns.push((requester, vec![index])),
+        }
+    }
+    if let Some(position) = turns.iter().position(|(r, _)| Some(*r) == current) {
+        let turn = turns.remove(position);
+        turns.push(turn);
+    }
+    let rounds = turns.iter().map(|(_, indices)| indices.len()).max();
+    (0..rounds.unwrap_or_default())
+        .flat_map(|round| {
+            turns
+                .iter()
+                .filter_map(move |(_, indices)| indices.get(round).copied())
+        })
+        .collect()
+}
+
+/// Reorders the upcoming tracks of `guild_id` to take turns between the members who added
+/// them, if the guild has the fair queue on.
+pub(crate) async fn reorder(state: &State, guild_id: Id<GuildMarker>) {
+    match db::guild::guild_fair_queue(&state.pool, guild_id.to_string()).await {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            tracing::warn!(?e, "could not get the fair queue setting of {guild_id}");
+            return;
+        }
+    }
+    let Some(call_lock) = state.songbird.get(guild_id) else {
+        return;
+    };
+    let call = call_lock.lock().await;
+    call.queue()
+        .modify_queue(|queue| take_turns(queue, |track| track.data::<Metadata>().requester));
+}
+
+/// Reorders the tracks after the next one in `queue` to take turns between their
+/// requesters.
+///
+/// The track that plays and the next one keep their places, because crossfade may have
+/// started the next one already. Whoever added the next one goes last.
+fn take_turns<T, R: PartialEq>(queue: &mut VecDeque<T>, requester: impl Fn(&T) -> R) {
+    if queue.len() < 4 {
+        return;
+    }
+    let next = requester(&queue[1]);
+    let requesters: Vec<_> = queue.iter().skip(2).map(&requester).collect();
+    let order = fair_order(Some(&next), &requesters);
+    let mut upcoming: Vec<_> = queue.drain(2..).map(Some).collect();
+    for index in order {
+        if let Some(track) = upcoming[index].take() {
+            queue.push_back(track);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ytdlp::tests::fake_ytdlp;
+
+    async fn playlist() -> Vec<YouTubeTrack> {
+        fake_ytdlp()
+            .get_tracks(
+                "https://www.youtube.com/playlist?list=PLFxxhcEeloYa1OlnWD6UgxlVQKJH5i_0p"
+                    .to_string(),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_admit() {
+        let admitted = admit_resolved(
+            QueueLimits::default(),
+            playlist().await,
+            100,
+            Section::default(),
+        )
+        .unwrap();
+        assert_eq!(admitted.tracks.len(), 3);
+        assert_eq!(admitted.left_out, 0);
+
+        let limits = QueueLimits {
+            tracks_per_user: Some(5),
+            playlist_size: Some(2),
+            track_duration_secs: None,
+        };
+        let admitted = admit_resolved(limits, playlist().await, 0, Section::default()).unwrap();
+        assert_eq!(admitted.tracks.len(), 2);
+        assert_eq!(admitted.left_out, 1);
+        let admitted = admit_resolved(limits, playlist().await, 4, Section::default()).unwrap();
+        assert_eq!(admitted.tracks.len(), 1);
+        assert!(matches!(
+            admit_resolved(limits, playlist().await, 5, Section::default()),
+            Err(CommandError::QueueLimitReached(5))
+        ));
+
+        let limits = QueueLimits {
+            track_duration_secs: Some(60),
+            ..Default::default()
+        };
+        assert!(matches!(
+            admit_resolved(limits, playlist().await, 0, Section::default()),
+            Err(CommandError::TrackTooLong(_))
+        ));
+        let limits = QueueLimits {
+            track_duration_secs: Some(600),
+            ..Default::default()
+        };
+        let admitted = admit_resolved(limits, playlist().await, 0, Section::default()).unwrap();
+        assert_eq!(admitted.tracks[0].title, "Computer Love");
+        assert_eq!(admitted.left_out, 1);
+        assert!(admit_resolved(limits, Vec::new(), 0, Section::default())
+            .unwrap()
+            .tracks
+            .is_empty());
+
+        // only the section of a single track counts
+        let limits = QueueLimits {
+            track_duration_secs: Some(60),
+            ..Default::default()
+        };
+        let track = || async {
+            let mut track = playlist().await.remove(0);
+            track.duration_string = "10:00".to_string();
+            vec![track]
+        };
+        assert!(matches!(
+            admit_resolved(limits, track().await, 0, Section::default()),
+            Err(CommandError::TrackTooLong(_))
+        ));
+        let section = Section {
+            start: Duration::from_secs(90),
+            end: Some(Duration::from_secs(120)),
+        };
+        let admitted = admit_resolved(limits, track().await, 0, section).unwrap();
+        assert_eq!(admitted.tracks.len(), 1);
+        let section = Section {
+            start: Duration::from_secs(570),
+            end: None,
+        };
+        assert!(admit_resolved(limits, track().await, 0, section).is_ok());
+    }
+
+    #[test]
+    fn test_fair_order() {
+        let (a, b, c) = (1, 2, 3);
+        assert_eq!(fair_order(None, &[a, a, a, b, b, c]), [0, 3, 5, 1, 4, 2]);
+        // whoever added the current track waits for the others
+        assert_eq!(fair_order(Some(&a), &[a, a, b, c]), [2, 3, 0, 1]);
+        assert_eq!(fair_order(Some(&c), &[a, b]), [0, 1]);
+        assert!(fair_order::<i32>(None, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_take_turns() {
+        let mut queue: VecDeque<_> = [(1, "playing"), (1, "crossfading"), (1, "a"), (2, "b")]
+            .into_iter()
+            .collect();
+        take_turns(&mut queue, |(requester, _)| *requester);
+        let titles: Vec<_> = queue.iter().map(|(_, title)| *title).collect();
+        // the track that crossfade started stays next, and its requester waits
+        assert_eq!(titles, ["playing", "crossfading", "b", "a"]);
+
+        let mut queue: VecDeque<_> = [(1, "playing"), (2, "next"), (3, "c")]
+            .into_iter()
+            .collect();
+        take_turns(&mut queue, |(requester, _)| *requester);
+        assert_eq!(queue[1].1, "next");
+    }
+}
@@ -0,0 +1,149 @@
+use regex::Regex;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Timestamps like `[01:23.45]` in front of a line.
+static TIMESTAMP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[(\d+):(\d{1,2})(?:[.:](\d{1,3}))?\]").expect("valid regex"));
+
+/// Timestamps of single words like `<01:23.45>`, which are not shown.
+static WORD_TIMESTAMP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<\d+:\d{1,2}(?:[.:]\d{1,3})?>").expect("valid regex"));
+
+/// Lyrics with the time at which each line is sung, read from an LRC file.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SyncedLyrics {
+    /// Lines ordered by their time in the track.
+    pub(crate) lines: Vec<(Duration, String)>,
+}
+
+impl SyncedLyrics {
+    /// Parses the LRC format, `None` if `text` has no timed lines.
+    ///
+    /// A line may have several timestamps, like a chorus that is sung more than once.
+    /// Tags like `[ar:Kraftwerk]` are skipped, except for `[offset:…]` in milliseconds.
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let mut offset = 0;
+        let mut lines = Vec::new();
+        for line in text.lines() {
+            let mut rest = line.trim();
+            let mut times = Vec::new();
+            while let Some(captures) = TIMESTAMP.captures(rest) {
+                let number = |index| {
+                    captures
+                        .get(index)
+                        .map_or(0, |m| m.as_str().parse().unwrap_or_default())
+                };
+                let fraction = captures.get(3).map_or(0, |m| {
+                    // hundredths are the most common, but some files use tenths or millis
+                    let digits = m.as_str();
+                    digits.parse::<u64>().unwrap_or_default() * 10_u64.pow(3 - digits.len() as u32)
+                });
+                times.push((number(1) * 60 + number(2)) * 1000 + fraction);
+                rest = &rest[captures[0].len()..];
+            }
+            if times.is_empty() {
+                if let Some(value) = rest
+                    .strip_prefix("[offset:")
+                    .and_then(|tag| tag.strip_suffix(']'))
+                {
+                    offset = value.trim().parse::<i64>().unwrap_or_default();
+                }
+                continue;
+            }
+            let text = WORD_TIMESTAMP.replace_all(rest, "");
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            for millis in times {
+                // a positive offset shows the lyrics earlier
+                let millis = (millis as i64 - offset).max(0) as u64;
+                lines.push((Duration::from_millis(millis), text.clone()));
+            }
+        }
+        if lines.is_empty() {
+            return None;
+        }
+        lines.sort_by_key(|(time, _)| *time);
+        Some(Self { lines })
+    }
+
+    /// Index of the line that is sung at `time`, `None` before the first one.
+    pub(crate) fn line_at(&self, time: Duration) -> Option<usize> {
+        self.lines
+            .partition_point(|(start, _)| *start <= time)
+            .checked_sub(1)
+    }
+
+    /// Time until the line after the one at `time` starts, `None` after the last one.
+    pub(crate) fn until_next(&self, time: Duration) -> Option<Duration> {
+        let next = self.lines.partition_point(|(start, _)| *start <= time);
+        self.lines.get(next).map(|(start, _)| *start - time)
+    }
+
+    /// The lyrics without timestamps.
+    pub(crate) fn text(&self) -> String {
+        let lines: Vec<_> = self.lines.iter().map(|(_, line)| line.as_str()).collect();
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: &str = "[ar:Kraftwerk]
+[ti:The Model]
+[offset:+500]
+
+[00:12.50]She's a model and she's looking good
+[00:16.3]I'd like to take her home, that's understood
+[00:20.120][00:44.00] <00:20.120>She <00:20.500>plays <00:21.000>hard to get
+[00:30]
+";
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_parse() {
+        let lyrics = SyncedLyrics::parse(MODEL).unwrap();
+        assert_eq!(
+            lyrics.lines,
+            [
+                (
+                    ms(12000),
+                    "She's a model and she's looking good".to_string()
+                ),
+                (
+                    ms(15800),
+                    "I'd like to take her home, that's understood".to_string()
+                ),
+                (ms(19620), "She plays hard to get".to_string()),
+                (ms(29500), String::new()),
+                (ms(43500), "She plays hard to get".to_string()),
+            ]
+        );
+        assert_eq!(SyncedLyrics::parse("She's a model\n[ar:Kraftwerk]"), None);
+        assert_eq!(SyncedLyrics::parse(""), None);
+    }
+
+    #[test]
+    fn test_line_at() {
+        let lyrics = SyncedLyrics::parse(MODEL).unwrap();
+        assert_eq!(lyrics.line_at(ms(1000)), None);
+        assert_eq!(lyrics.line_at(ms(12000)), Some(0));
+        assert_eq!(lyrics.line_at(ms(20000)), Some(2));
+        assert_eq!(lyrics.line_at(ms(100000)), Some(4));
+        assert_eq!(lyrics.until_next(ms(1000)), Some(ms(11000)));
+        assert_eq!(lyrics.until_next(ms(15000)), Some(ms(800)));
+        assert_eq!(lyrics.until_next(ms(100000)), None);
+    }
+
+    #[test]
+    fn test_text() {
+        let lyrics =
+            SyncedLyrics::parse("[00:01.00]She's a model\n[00:02.00]and she's looking good")
+                .unwrap();
+        assert_eq!(lyrics.text(), "She's a model\nand she's looking good");
+    }
+}
@@ -0,0 +1,486 @@
+pub(crate) mod lrc;
+
+use crate::metadata::Metadata;
+use async_trait::async_trait;
+use lrc::SyncedLyrics;
+use regex::Regex;
+use serde::Deserialize;
+use std::error::Error;
+use std::io::Cursor;
+use std::sync::LazyLock;
+use std::time::Duration;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::Hint;
+use url::Url;
+
+/// Files that are downloaded at most to read their tags.
+const MAX_FILE_SIZE: u64 = 50 * 1024 * 1024;
+
+/// Extensions of audio files that may have lyrics in their tags.
+const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "flac", "ogg", "opus", "m4a", "wav", "aac"];
+
+/// Parts of titles like `(Official Video)` or `[HD]` that are not part of the song's name.
+static DECORATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\s*[(\[][^)\]]*\b(official|video|audio|lyrics?|visuali[sz]er|hd|4k|mv)\b[^)\]]*[)\]]",
+    )
+    .expect("valid regex")
+});
+
+pub(crate) type LyricsResult = Result<Option<Lyrics>, Box<dyn Error + Send + Sync + 'static>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Lyrics {
+    pub(crate) text: String,
+    /// Where the lyrics were found, shown below them.
+    pub(crate) source: String,
+    /// When each line is sung, if known.
+    pub(crate) synced: Option<SyncedLyrics>,
+}
+
+impl Lyrics {
+    /// Lyrics from `text`, which are synced if it is in the LRC format.
+    pub(crate) fn new(text: String, source: &str) -> Self {
+        let synced = SyncedLyrics::parse(&text);
+        Self {
+            text: synced.as_ref().map_or(text, SyncedLyrics::text),
+            source: source.to_string(),
+            synced,
+        }
+    }
+}
+
+/// What lyrics are looked up for.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LyricsQuery {
+    pub(crate) title: String,
+    pub(crate) artist: Option<String>,
+    pub(crate) duration: Option<Duration>,
+    pub(crate) url: String,
+}
+
+impl LyricsQuery {
+    /// Builds the query for a track, or `None` if its title is unknown.
+    ///
+    /// Titles of music videos like `Kraftwerk - The Model (Official Video)` are split into
+    /// artist and title, otherwise the channel is taken as the artist.
+    pub(crate) fn new(metadata: &Metadata) -> Option<Self> {
+        let title = DECORATION.replace_all(metadata.title.as_deref()?, "");
+        let (artist, title) = match title.split_once(" - ") {
+            Some((artist, title)) => (Some(artist.trim().to_string()), title.trim()),
+            None => (metadata.channel.as_deref().map(clean_channel), title.trim()),
+        };
+        if title.is_empty() {
+            return None;
+        }
+        Some(Self {
+            title: title.to_string(),
+            artist: artist.filter(|artist| !artist.is_empty()),
+            // lyrics belong to the whole track rather than its section
+            duration: metadata
+                .section
+                .is_full()
+                .then_some(metadata.duration)
+                .flatten(),
+            url: metadata.url.clone(),
+        })
+    }
+}
+
+/// Strips what YouTube and labels add to the names of artist channels.
+fn clean_channel(channel: &str) -> String {
+    let channel = channel.trim();
+    let channel = channel.strip_suffix(" - Topic").unwrap_or(channel);
+    let channel = channel.strip_suffix("VEVO").unwrap_or(channel);
+    channel.trim().to_string()
+}
+
+/// Somewhere lyrics can be looked up.
+#[async_trait]
+pub(crate) trait LyricsProvider: Send + Sync {
+    /// Looks up the lyrics for `query`, `None` if the provider has none.
+    async fn lyrics(&self, query: &LyricsQuery) -> LyricsResult;
+}
+
+/// The providers that are asked for lyrics one after the other.
+pub(crate) struct LyricsProviders(Vec<Box<dyn LyricsProvider>>);
+
+impl std::fmt::Debug for LyricsProviders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "LyricsProviders({})", self.0.len())
+    }
+}
+
+impl LyricsProviders {
+    /// Reads the tags of audio files first, then searches LRCLIB.
+    pub(crate) fn new(client: reqwest::Client) -> Self {
+        Self(vec![
+            Box::new(Embedded::new(client.clone())),
+            Box::new(LrcLib::new(client)),
+        ])
+    }
+
+    /// Lyrics of the first provider that has some.
+    pub(crate) async fn find(&self, query: &LyricsQuery) -> Option<Lyrics> {
+        for provider in &self.0 {
+            match provider.lyrics(query).await {
+                Ok(Some(lyrics)) => return Some(lyrics),
+                Ok(None) => {}
+                Err(e) => tracing::warn!(?e, "could not look up lyrics for {}", query.url),
+            }
+        }
+        None
+    }
+}
+
+/// Reads the lyrics of audio files, like attachments on Discord, from an LRC file next to
+/// them or from their tags.
+pub(crate) struct Embedded {
+    client: reqwest::Client,
+}
+
+impl Embedded {
+    pub(crate) fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    /// Reads the file at `url`, `None` if it does not exist or is too large.
+    async fn read(
+        &self,
+        url: &Url,
+    ) -> Result<Option<Vec<u8>>, Box<dyn Error + Send + Sync + 'static>> {
+        if url.scheme() == "file" {
+            let path = url.to_file_path().map_err(|_| "invalid file url")?;
+            let size = match tokio::fs::metadata(&path).await {
+                Ok(metadata) => metadata.len(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            if size > MAX_FILE_SIZE {
+                return Ok(None);
+            }
+            return Ok(Some(tokio::fs::read(path).await?));
+        }
+        let mut response = self.client.get(url.clone()).send().await?;
+        if !response.status().is_success()
+            || response.content_length().unwrap_or_default() > MAX_FILE_SIZE
+        {
+            return Ok(None);
+        }
+        // the length is missing or wrong for some servers, so it is checked while reading
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if (bytes.len() + chunk.len()) as u64 > MAX_FILE_SIZE {
+                return Ok(None);
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(Some(bytes))
+    }
+}
+
+/// URL of the LRC file next to the audio file at `url`, like `model.lrc` for `model.mp3`.
+fn sidecar_url(url: &Url) -> Option<Url> {
+    let (stem, _) = url.path().rsplit_once('.')?;
+    let mut sidecar = url.clone();
+    sidecar.set_path(&format!("{stem}.lrc"));
+    sidecar.set_query(None);
+    Some(sidecar)
+}
+
+/// Extension of the audio file at `url`, `None` for other pages like YouTube videos.
+fn audio_extension(url: &Url) -> Option<String> {
+    let (_, extension) = url.path().rsplit_once('.')?;
+    let extension = extension.to_lowercase();
+    AUDIO_EXTENSIONS
+        .contains(&extension.as_str())
+        .then_some(extension)
+}
+
+/// Lyrics in the tags of an audio file.
+fn embedded_lyrics(bytes: Vec<u8>, extension: &str) -> Option<String> {
+    let mut hint = Hint::new();
+    hint.with_extension(extension);
+    let stream = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+    let lyrics = |revision: &MetadataRevision| {
+        revision
+            .tags()
+            .iter()
+            .find(|tag| tag.std_key == Some(StandardTagKey::Lyrics))
+            .map(|tag| tag.value.to_string())
+    };
+    // ID3 tags in front of the stream are read by the probe, others by the format
+    probed
+        .metadata
+        .get()
+        .and_then(|metadata| metadata.current().and_then(lyrics))
+        .or_else(|| probed.format.metadata().current().and_then(lyrics))
+        .filter(|text| !text.trim().is_empty())
+}
+
+#[async_trait]
+impl LyricsProvider for Embedded {
+    async fn lyrics(&self, query: &LyricsQuery) -> LyricsResult {
+        let Ok(url) = Url::parse(&query.url) else {
+            return Ok(None);
+        };
+        let Some(extension) = audio_extension(&url) else {
+            return Ok(None);
+        };
+        if let Some(sidecar) = sidecar_url(&url) {
+            if let Some(bytes) = self.read(&sidecar).await? {
+                let text = String::from_utf8_lossy(&bytes).into_owned();
+                if !text.trim().is_empty() {
+                    return Ok(Some(Lyrics::new(text, "LRC")));
+                }
+            }
+        }
+        let Some(bytes) = self.read(&url).await? else {
+            return Ok(None);
+        };
+        let text = tokio::task::spawn_blocking(move || embedded_lyrics(bytes, &extension)).await?;
+        Ok(text.map(|text| Lyrics::new(text, "tags")))
+    }
+}
+
+/// Searches [LRCLIB](https://lrclib.net), an open database of lyrics.
+pub(crate) struct LrcLib {
+    client: reqwest::Client,
+}
+
+impl LrcLib {
+    const SEARCH_URL: &'static str = "https://lrclib.net/api/search";
+
+    pub(crate) fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LrcLibTrack {
+    duration: Option<f64>,
+    plain_lyrics: Option<String>,
+    synced_lyrics: Option<String>,
+}
+
+/// Lyrics of the search result whose duration is the closest to `duration`.
+fn parse_search(
+    json: &str,
+    duration: Option<Duration>,
+) -> Result<Option<Lyrics>, serde_json::Error> {
+    let tracks: Vec<LrcLibTrack> = serde_json::from_str(json)?;
+    let distance = |track: &LrcLibTrack| match (track.duration, duration) {
+        (Some(a), Some(b)) => (a - b.as_secs_f64()).abs(),
+        _ => f64::MAX,
+    };
+    Ok(tracks
+        .into_iter()
+        .filter(|track| {
+            track
+                .plain_lyrics
+                .as_ref()
+                .is_some_and(|lyrics| !lyrics.trim().is_empty())
+        })
+        // the first of equally close results, as LRCLIB ranks them
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        .and_then(|track| {
+            Some(Lyrics {
+                text: track.plain_lyrics?,
+                source: "LRCLIB".to_string(),
+                synced: track.synced_lyrics.as_deref().and_then(SyncedLyrics::parse),
+            })
+        }))
+}
+
+#[async_trait]
+impl LyricsProvider for LrcLib {
+    async fn lyrics(&self, query: &LyricsQuery) -> LyricsResult {
+        let mut params = vec![("track_name", query.title.as_str())];
+        if let Some(artist) = &query.artist {
+            params.push(("artist_name", artist));
+        }
+        let json = self
+            .client
+            .get(Self::SEARCH_URL)
+            .query(&params)
+            .header(
+                reqwest::header::USER_AGENT,
+                concat!("ohrwurm/", env!("CARGO_PKG_VERSION")),
+            )
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        Ok(parse_search(&json, query.duration)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::section::Section;
+    use crate::ytdlp::tests::fake_ytdlp;
+
+    fn metadata(title: &str, channel: &str) -> Metadata {
+        let url = "https://www.youtube.com/watch?v=34CZjsEI1yU".to_string();
+        Metadata {
+            title: Some(title.to_string()),
+            channel: Some(channel.to_string()),
+            duration: Some(Duration::from_secs(220)),
+            src: fake_ytdlp().source(reqwest::Client::new(), url.clone()),
+            url,
+            autoplay: false,
+            requester: None,
+            gain: 1.0,
+            section: Section::default(),
+            chapters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_query() {
+        let query = LyricsQuery::new(&metadata(
+            "Kraftwerk - The Model (Official Video) [HD]",
+            "KraftwerkVEVO",
+        ))
+        .unwrap();
+        assert_eq!(query.title, "The Model");
+        assert_eq!(query.artist.as_deref(), Some("Kraftwerk"));
+        assert_eq!(query.duration, Some(Duration::from_secs(220)));
+        let query = LyricsQuery::new(&metadata(
+            "Computer Love (feat. Nobody)",
+            "Kraftwerk - Topic",
+        ))
+        .unwrap();
+        assert_eq!(query.title, "Computer Love (feat. Nobody)");
+        assert_eq!(query.artist.as_deref(), Some("Kraftwerk"));
+        assert_eq!(
+            LyricsQuery::new(&metadata("(Official Audio)", "Kraftwerk")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_audio_extension() {
+        let url = |url| Url::parse(url).unwrap();
+        assert_eq!(
+            audio_extension(&url(
+                "https://cdn.discordapp.com/attachments/1/2/model.MP3?ex=1"
+            )),
+            Some("mp3".to_string())
+        );
+        assert_eq!(
+            audio_extension(&url("file:///music/model.flac")),
+            Some("flac".to_string())
+        );
+        assert_eq!(
+            audio_extension(&url("https://www.youtube.com/watch?v=34CZjsEI1yU")),
+            None
+        );
+        assert_eq!(embedded_lyrics(b"no audio".to_vec(), "mp3"), None);
+        assert_eq!(
+            sidecar_url(&url("https://example.com/music/model.mp3?token=1")),
+            Some(url("https://example.com/music/model.lrc"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sidecar() {
+        let dir = std::env::temp_dir().join(format!("ohrwurm-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir(&dir).await.unwrap();
+        tokio::fs::write(dir.join("model.mp3"), b"no audio")
+            .await
+            .unwrap();
+        let mut query = LyricsQuery::new(&metadata("The Model", "Kraftwerk")).unwrap();
+        query.url = Url::from_file_path(dir.join("model.mp3")).unwrap().into();
+        let embedded = Embedded::new(reqwest::Client::new());
+        assert_eq!(embedded.lyrics(&query).await.unwrap(), None);
+
+        tokio::fs::write(dir.join("model.lrc"), "[00:12.50]She's a model")
+            .await
+            .unwrap();
+        let lyrics = embedded.lyrics(&query).await.unwrap().unwrap();
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+        assert_eq!(lyrics.text, "She's a model");
+        assert_eq!(lyrics.source, "LRC");
+        assert!(lyrics.synced.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_read_too_large() {
+        let path = std::env::temp_dir().join(format!("ohrwurm-{}.mp3", uuid::Uuid::new_v4()));
+        let file = tokio::fs::File::create(&path).await.unwrap();
+        file.set_len(MAX_FILE_SIZE + 1).await.unwrap();
+        let embedded = Embedded::new(reqwest::Client::new());
+        let url = Url::from_file_path(&path).unwrap();
+        let read = embedded.read(&url).await.unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+        assert_eq!(read, None);
+    }
+
+    #[test]
+    fn test_parse_search() {
+        let json = r#"[
+            {"id": 1, "trackName": "The Model", "duration": 180.0, "plainLyrics": "Short edit"},
+            {"id": 2, "trackName": "The Model", "duration": 222.0, "plainLyrics": "She's a model"},
+            {"id": 3, "trackName": "The Model", "duration": 220.0, "plainLyrics": null,
+             "instrumental": true}
+        ]"#;
+        let json = json.replace(
+            r#""plainLyrics": "She's a model""#,
+            r#""plainLyrics": "She's a model", "syncedLyrics": "[00:12.50] She's a model""#,
+        );
+        let lyrics = parse_search(&json, Some(Duration::from_secs(220)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(lyrics.text, "She's a model");
+        assert_eq!(
+            lyrics.synced.unwrap().lines,
+            [(Duration::from_millis(12500), "She's a model".to_string())]
+        );
+        let lyrics = parse_search(&json, None).unwrap().unwrap();
+        assert_eq!(lyrics.text, "Short edit");
+        assert_eq!(lyrics.synced, None);
+        assert_eq!(parse_search("[]", None).unwrap(), None);
+        assert!(parse_search("{}", None).is_err());
+    }
+
+    struct Mock(LyricsResult);
+
+    #[async_trait]
+    impl LyricsProvider for Mock {
+        async fn lyrics(&self, _query: &LyricsQuery) -> LyricsResult {
+            match &self.0 {
+                Ok(lyrics) => Ok(lyrics.clone()),
+                Err(e) => Err(e.to_string().into()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find() {
+        let query = LyricsQuery::new(&metadata("Kraftwerk - The Model", "Kraftwerk")).unwrap();
+        let lyrics = Lyrics::new("She's a model".to_string(), "mock");
+        let providers = LyricsProviders(vec![
+            Box::new(Mock(Err("offline".into()))),
+            Box::new(Mock(Ok(None))),
+            Box::new(Mock(Ok(Some(lyrics.clone())))),
+            Box::new(Mock(Err("never asked".into()))),
+        ]);
+        assert_eq!(providers.find(&query).await, Some(lyrics));
+        let providers = LyricsProviders(vec![Box::new(Mock(Ok(None)))]);
+        assert_eq!(providers.find(&query).await, None);
+    }
+}
@@ -1,26 +1,39 @@
 mod handler;
 use handler::Handler;
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+mod blocklist;
+mod cleanup;
 mod colors;
 mod commands;
+mod config;
 mod db;
-mod interaction_commands;
+mod filter;
+mod i18n;
+mod limits;
+mod lyrics;
 mod metadata;
+mod playlist_file;
+mod resolve;
+mod section;
 mod signal;
 mod state;
 mod utils;
+mod ytdlp;
 
-use crate::commands::get_chat_commands;
+use crate::commands::{get_chat_commands, register_commands};
+use config::Config;
 use dotenv::dotenv;
+use lyrics::LyricsProviders;
 use songbird::{shards::TwilightMap, Songbird};
 use state::StateRef;
 use std::{env, error::Error, str::FromStr, sync::Arc, time::Duration};
-use tracing::{debug, info};
+use tracing::{debug, error, info};
 use twilight_cache_inmemory::InMemoryCache;
 use twilight_gateway::{Event, EventTypeFlags, Intents, Shard, StreamExt as _};
 use twilight_http::Client as HttpClient;
 use twilight_model::id::Id;
 use twilight_standby::Standby;
+use ytdlp::YtDlp;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
@@ -33,41 +46,40 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
     info!("Starting up...");
 
     let (shards, state) = {
-        let db = env::var("DATABASE_URL").map_err(|_| "DATABASE_URL is not set")?;
-        let options = SqliteConnectOptions::from_str(&db)
+        let config = Config::load().map_err(|e| format!("invalid configuration: {e}"))?;
+
+        let options = SqliteConnectOptions::from_str(&config.database.url)
             .expect("could not create options")
             .create_if_missing(true);
         let pool = SqlitePoolOptions::new()
-            .max_connections(5)
+            .max_connections(config.database.max_connections)
             .connect_with(options)
             .await?;
         sqlx::migrate!().run(&pool).await?;
 
-        let token = env::var("DISCORD_TOKEN").map_err(|_| "DISCORD_TOKEN is not set")?;
-        let app_id = env::var("DISCORD_APP_ID")
-            .map_err(|_| "DISCORD_APP_ID is not set")?
-            .parse()?;
-
+        let token = config.discord.token.clone();
         let http = HttpClient::new(token.clone());
         let user_id = http.current_user().await?.model().await?.id;
-        let application_id = Id::new(app_id);
+        let application_id = Id::new(config.discord.app_id);
         let interaction_client = http.interaction(application_id);
 
-        interaction_client
-            .set_global_commands(&get_chat_commands())
-            .await?;
-        // let commands = interaction_client.global_commands().await?.models().await?;
-        // debug!("Global commands: {:?}", commands);
-
-        let intents = Intents::GUILDS
-            | Intents::GUILD_MESSAGES
-            | Intents::GUILD_VOICE_STATES
-            | Intents::MESSAGE_CONTENT;
-        let config = twilight_gateway::Config::new(token.clone(), intents);
+        let clear_commands = env::args().any(|arg| arg == "--clear-commands");
+        register_commands(
+            &interaction_client,
+            &config,
+            &get_chat_commands(),
+            clear_commands,
+        )
+        .await?;
+
+        let intents = Intents::GUILDS | Intents::GUILD_VOICE_STATES;
+        let gateway_config = twilight_gateway::Config::new(token.clone(), intents);
         let shards: Vec<Shard> =
-            twilight_gateway::create_recommended(&http, config, |_, builder| builder.build())
-                .await?
-                .collect();
+            twilight_gateway::create_recommended(&http, gateway_config, |_, builder| {
+                builder.build()
+            })
+            .await?
+            .collect();
         let senders = TwilightMap::new(
             shards
                 .iter()
@@ -77,10 +89,18 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
         let songbird = Songbird::twilight(Arc::new(senders), user_id);
         let cache = InMemoryCache::new();
         let client = reqwest::ClientBuilder::new()
-            .connect_timeout(Duration::from_secs(10))
-            .timeout(Duration::from_secs(3600))
+            .connect_timeout(Duration::from_secs(config.http.connect_timeout_secs))
+            .timeout(Duration::from_secs(config.http.timeout_secs))
             .build()
             .expect("could not build http client");
+        let ytdlp = YtDlp::new(config.ytdlp.path.clone().leak())
+            .args(config.ytdlp.args.clone())
+            .timeout(Duration::from_secs(config.ytdlp.timeout_secs))
+            .max_concurrent(config.ytdlp.max_concurrent);
+        match ytdlp.version().await {
+            Ok(version) => info!("Using yt-dlp {version}"),
+            Err(e) => error!("Could not run yt-dlp: {e}"),
+        }
 
         (
             shards,
@@ -91,13 +111,18 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
                 standby: Standby::new(),
                 guild_settings: Default::default(),
                 pool,
+                lyrics: LyricsProviders::new(client.clone()),
                 client,
+                ytdlp,
+                config,
             }),
         )
     };
 
     info!("Ready to receive events");
 
+    tokio::spawn(cleanup::run(Arc::clone(&state)));
+
     let handler = Handler::new(Arc::clone(&state));
     // let mut stop_rx = signal_handler();
     let mut set = tokio::task::JoinSet::new();
@@ -1,10 +1,24 @@
+use crate::section::Section;
+use crate::ytdlp::Chapter;
 use songbird::input::YoutubeDl;
 use std::time::Duration;
+use twilight_model::id::{marker::UserMarker, Id};
 
 #[derive(Clone)]
 pub(crate) struct Metadata {
     pub(crate) title: Option<String>,
+    /// Channel that uploaded the track, often its artist.
+    pub(crate) channel: Option<String>,
+    /// How long the track plays, which is only its section if it is cut.
     pub(crate) duration: Option<Duration>,
     pub(crate) url: String,
     pub(crate) src: YoutubeDl<'static>,
+    /// Whether autoplay picked the track, rather than someone requesting it.
+    pub(crate) autoplay: bool,
+    /// Member who added the track, `None` for tracks that autoplay picked.
+    pub(crate) requester: Option<Id<UserMarker>>,
+    /// Linear gain that plays the track at the normal loudness, 1.0 until it is measured.
+    pub(crate) gain: f32,
+    pub(crate) section: Section,
+    pub(crate) chapters: Vec<Chapter>,
 }
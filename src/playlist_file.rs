@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, time::Duration};
+
+/// A track in an exported file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Entry {
+    pub(crate) url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) channel: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) duration_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) thumbnail: Option<String>,
+}
+
+impl Entry {
+    pub(crate) fn new(url: String, title: Option<String>, duration: Option<Duration>) -> Self {
+        Self {
+            url,
+            title,
+            channel: None,
+            duration_secs: duration.map(|duration| duration.as_secs()),
+            thumbnail: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    tracks: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Format {
+    Json,
+    /// Extended M3U, with the title and duration of every track.
+    M3u,
+}
+
+impl Format {
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::M3u => "m3u",
+        }
+    }
+
+    /// Guesses the format of a file from its name, and else from its content.
+    pub(crate) fn detect(filename: &str, content: &str) -> Self {
+        let filename = filename.to_lowercase();
+        if filename.ends_with(".json") {
+            Self::Json
+        } else if filename.ends_with(".m3u") || filename.ends_with(".m3u8") {
+            Self::M3u
+        } else if content.trim_start().starts_with('{') {
+            Self::Json
+        } else {
+            Self::M3u
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum ParseError {
+    Json(serde_json::Error),
+    Empty,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(e) => write!(f, "invalid JSON: {e}"),
+            Self::Empty => f.write_str("no tracks in file"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Writes `entries` in `format`, under the playlist name `name` if there is one.
+pub(crate) fn write(name: Option<&str>, entries: &[Entry], format: Format) -> String {
+    match format {
+        Format::Json => {
+            let file = JsonFile {
+                name: name.map(str::to_string),
+                tracks: entries.to_vec(),
+            };
+            serde_json::to_string_pretty(&file).expect("entries are serializable")
+        }
+        Format::M3u => {
+            let mut s = String::from("#EXTM3U\n");
+            if let Some(name) = name {
+                s.push_str(&format!("#PLAYLIST:{}\n", single_line(name)));
+            }
+            for entry in entries {
+                let duration = entry.duration_secs.map_or(-1, |secs| secs as i64);
+                let title = entry.title.as_deref().unwrap_or(&entry.url);
+                s.push_str(&format!("#EXTINF:{duration},{}\n", single_line(title)));
+                s.push_str(&single_line(&entry.url));
+                s.push('\n');
+            }
+            s
+        }
+    }
+}
+
+fn single_line(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
+
+/// Reads the entries of a file in `format`.
+pub(crate) fn parse(content: &str, format: Format) -> Result<Vec<Entry>, ParseError> {
+    let entries = match format {
+        Format::Json => {
+            serde_json::from_str::<JsonFile>(content)
+                .map_err(ParseError::Json)?
+                .tracks
+        }
+        Format::M3u => parse_m3u(content),
+    };
+    if entries.is_empty() {
+        return Err(ParseError::Empty);
+    }
+    Ok(entries)
+}
+
+fn parse_m3u(content: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    // title and duration of the next url, from its #EXTINF line
+    let mut info = None;
+    for line in content.lines().map(str::trim) {
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            let (duration, title) = extinf.split_once(',').unwrap_or((extinf, ""));
+            let duration = duration
+                .split_whitespace()
+                .next()
+                .and_then(|duration| duration.parse::<u64>().ok());
+            let title = Some(title.trim().to_string()).filter(|title| !title.is_empty());
+            info = Some((title, duration));
+        } else if !line.is_empty() && !line.starts_with('#') {
+            let (title, duration_secs) = info.take().unwrap_or_default();
+            entries.push(Entry {
+                url: line.to_string(),
+                title,
+                channel: None,
+                duration_secs,
+                thumbnail: None,
+            });
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            Entry {
+                url: "https://www.youtube.com/watch?v=34CZjsEI1yU".to_string(),
+                title: Some("Kraftwerk - The Model".to_string()),
+                channel: Some("Kraftwerk".to_string()),
+                duration_secs: Some(222),
+                thumbnail: Some("https://i.ytimg.com/vi/34CZjsEI1yU/hq720.jpg".to_string()),
+            },
+            Entry::new("https://example.com/live".to_string(), None, None),
+        ]
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let file = write(Some("mix"), &entries(), Format::Json);
+        assert_eq!(parse(&file, Format::Json).unwrap(), entries());
+    }
+
+    #[test]
+    fn test_m3u_round_trip() {
+        let file = write(Some("mix"), &entries(), Format::M3u);
+        assert_eq!(
+            file,
+            "#EXTM3U\n#PLAYLIST:mix\n\
+             #EXTINF:222,Kraftwerk - The Model\nhttps://www.youtube.com/watch?v=34CZjsEI1yU\n\
+             #EXTINF:-1,https://example.com/live\nhttps://example.com/live\n"
+        );
+        let parsed = parse(&file, Format::M3u).unwrap();
+        assert_eq!(parsed[0].title.as_deref(), Some("Kraftwerk - The Model"));
+        assert_eq!(parsed[0].duration_secs, Some(222));
+        assert_eq!(parsed[1].url, "https://example.com/live");
+        assert_eq!(parsed[1].duration_secs, None);
+    }
+
+    #[test]
+    fn test_parse_plain_m3u() {
+        let parsed = parse("# comment\n\nhttps://a\r\nhttps://b\n", Format::M3u).unwrap();
+        assert_eq!(
+            parsed,
+            [
+                Entry::new("https://a".to_string(), None, None),
+                Entry::new("https://b".to_string(), None, None),
+            ]
+        );
+        assert!(matches!(
+            parse("#EXTM3U\n", Format::M3u),
+            Err(ParseError::Empty)
+        ));
+        assert!(matches!(parse("{", Format::Json), Err(ParseError::Json(_))));
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Format::detect("queue.JSON", ""), Format::Json);
+        assert_eq!(Format::detect("queue.m3u8", "{"), Format::M3u);
+        assert_eq!(
+            Format::detect("queue.txt", "  {\"tracks\": []}"),
+            Format::Json
+        );
+        assert_eq!(Format::detect("queue", "https://a"), Format::M3u);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::i18n::Locale;
+use std::{error::Error, fmt, io};
+
+/// Reasons why a query could not be resolved into playable tracks.
+#[derive(Debug)]
+pub(crate) enum ResolveError {
+    NotFound,
+    PremiumOnly,
+    AgeRestricted,
+    GeoBlocked,
+    BotCheck,
+    Private,
+    YtDlpMissing,
+    Timeout,
+    Parse(String),
+    Failed,
+}
+
+impl ResolveError {
+    /// Classifies an error message printed by yt-dlp.
+    ///
+    /// Returns `None` if the message does not match any known failure.
+    pub(crate) fn from_stderr(stderr: &str) -> Option<Self> {
+        let contains_any = |needles: &[&str]| needles.iter().any(|n| stderr.contains(n));
+
+        // order matters: e.g. geo-blocked videos are also reported as "Video unavailable"
+        if contains_any(&["could not find executable"]) {
+            Some(Self::YtDlpMissing)
+        } else if contains_any(&[
+            "Sign in to confirm you’re not a bot",
+            "Sign in to confirm you're not a bot",
+        ]) {
+            Some(Self::BotCheck)
+        } else if contains_any(&["only available to Music Premium members"]) {
+            Some(Self::PremiumOnly)
+        } else if contains_any(&["Sign in to confirm your age", "age-restricted"]) {
+            Some(Self::AgeRestricted)
+        } else if contains_any(&[
+            "not made this video available in your country",
+            "not available in your country",
+            "geo restriction",
+        ]) {
+            Some(Self::GeoBlocked)
+        } else if contains_any(&["Private video", "This playlist is private"]) {
+            Some(Self::Private)
+        } else if contains_any(&["timed out"]) {
+            Some(Self::Timeout)
+        } else if contains_any(&[
+            "The playlist does not exist",
+            "Video unavailable",
+            "This video is unavailable",
+            "This video has been removed",
+            "Unsupported URL",
+            "HTTP Error 404",
+            "Incomplete YouTube ID",
+        ]) {
+            Some(Self::NotFound)
+        } else {
+            None
+        }
+    }
+
+    /// Maps a failure to spawn yt-dlp.
+    pub(crate) fn from_io(e: &io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => Self::YtDlpMissing,
+            io::ErrorKind::TimedOut => Self::Timeout,
+            _ => Self::Parse(e.to_string()),
+        }
+    }
+}
+
+impl ResolveError {
+    /// Whether looking up any other track would fail the same way.
+    pub(crate) fn is_fatal(&self) -> bool {
+        matches!(self, Self::BotCheck | Self::YtDlpMissing)
+    }
+
+    /// Explains the failure to a user in `locale`.
+    pub(crate) fn message(&self, locale: Locale) -> String {
+        let key = match self {
+            Self::NotFound => "resolve.not_found",
+            Self::PremiumOnly => "resolve.premium_only",
+            Self::AgeRestricted => "resolve.age_restricted",
+            Self::GeoBlocked => "resolve.geo_blocked",
+            Self::BotCheck => "resolve.bot_check",
+            Self::Private => "resolve.private",
+            Self::YtDlpMissing => "resolve.ytdlp_missing",
+            Self::Timeout => "resolve.timeout",
+            Self::Parse(detail) => return locale.format("resolve.parse", &[("detail", detail)]),
+            Self::Failed => "resolve.failed",
+        };
+        locale.text(key).to_string()
+    }
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(Locale::En))
+    }
+}
+
+impl Error for ResolveError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(fixture: &str) -> Option<ResolveError> {
+        ResolveError::from_stderr(fixture)
+    }
+
+    #[test]
+    fn test_classify_fixtures() {
+        assert!(matches!(
+            classify(include_str!("../tests/fixtures/yt-dlp/premium.stderr")),
+            Some(ResolveError::PremiumOnly)
+        ));
+        assert!(matches!(
+            classify(include_str!(
+                "../tests/fixtures/yt-dlp/playlist_missing.stderr"
+            )),
+            Some(ResolveError::NotFound)
+        ));
+        assert!(matches!(
+            classify(include_str!("../tests/fixtures/yt-dlp/unavailable.stderr")),
+            Some(ResolveError::NotFound)
+        ));
+        assert!(matches!(
+            classify(include_str!(
+                "../tests/fixtures/yt-dlp/age_restricted.stderr"
+            )),
+            Some(ResolveError::AgeRestricted)
+        ));
+        assert!(matches!(
+            classify(include_str!("../tests/fixtures/yt-dlp/geo_blocked.stderr")),
+            Some(ResolveError::GeoBlocked)
+        ));
+        assert!(matches!(
+            classify(include_str!("../tests/fixtures/yt-dlp/bot_check.stderr")),
+            Some(ResolveError::BotCheck)
+        ));
+        assert!(matches!(
+            classify(include_str!("../tests/fixtures/yt-dlp/private.stderr")),
+            Some(ResolveError::Private)
+        ));
+        assert!(matches!(
+            classify(include_str!("../tests/fixtures/yt-dlp/timeout.stderr")),
+            Some(ResolveError::Timeout)
+        ));
+    }
+
+    #[test]
+    fn test_classify_unknown() {
+        assert!(classify("WARNING: [youtube] Falling back to generic n function search").is_none());
+        assert!(classify("").is_none());
+    }
+
+    #[test]
+    fn test_missing_binary() {
+        let e = io::Error::from(io::ErrorKind::NotFound);
+        assert!(matches!(
+            ResolveError::from_io(&e),
+            ResolveError::YtDlpMissing
+        ));
+        assert!(matches!(
+            classify("failed to create audio: could not find executable 'yt-dlp' on path"),
+            Some(ResolveError::YtDlpMissing)
+        ));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::state::State;
+use crate::utils::{format_duration, parse_timestamp};
+use async_trait::async_trait;
+use songbird::tracks::TrackHandle;
+use songbird::{Event, EventContext, EventHandler};
+use std::time::Duration;
+use twilight_model::id::{marker::GuildMarker, Id};
+use url::Url;
+
+/// Part of a track that is played, e.g. of a long mix.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Section {
+    pub(crate) start: Duration,
+    pub(crate) end: Option<Duration>,
+}
+
+impl Section {
+    /// Reads the section from `t`, `start` and `end` in the query or the fragment of `url`,
+    /// like `https://www.youtube.com/watch?v=34CZjsEI1yU&t=1m30s`.
+    pub(crate) fn from_url(url: &str) -> Self {
+        let Ok(url) = Url::parse(url) else {
+            return Self::default();
+        };
+        let fragment = url.fragment().unwrap_or_default();
+        let mut section = Self::default();
+        for (key, value) in url
+            .query_pairs()
+            .chain(url::form_urlencoded::parse(fragment.as_bytes()))
+        {
+            match key.as_ref() {
+                "t" | "start" => section.start = parse_timestamp(&value).unwrap_or_default(),
+                "end" => section.end = parse_timestamp(&value),
+                _ => {}
+            }
+        }
+        if section.end.is_some_and(|end| end <= section.start) {
+            section.end = None;
+        }
+        section
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.start.is_zero() && self.end.is_none()
+    }
+
+    /// How long the section of a track of `duration` plays.
+    pub(crate) fn duration(&self, duration: Option<Duration>) -> Option<Duration> {
+        let end = match (self.end, duration) {
+            (Some(end), Some(duration)) => end.min(duration),
+            (end, duration) => end.or(duration)?,
+        };
+        Some(end.saturating_sub(self.start))
+    }
+
+    /// Formats the section like `01:30–05:00`, leaving out the end if it is not cut.
+    pub(crate) fn range(&self) -> String {
+        let end = self.end.map(format_duration).unwrap_or_default();
+        format!("{}–{end}", format_duration(self.start))
+    }
+}
+
+/// Stops the track of `handle` at the end of its `section`.
+pub(crate) fn schedule_end(
+    handle: &TrackHandle,
+    state: &State,
+    guild_id: Id<GuildMarker>,
+    section: Section,
+) {
+    let Some(length) = section.duration(None) else {
+        return;
+    };
+    let speed = state
+        .guild_settings
+        .get(&guild_id)
+        .map_or(1.0, |settings| settings.filters.speed);
+    if let Err(e) = handle.add_event(Event::Delayed(length.div_f64(speed)), SectionEndNotifier) {
+        tracing::debug!(?e, "could not schedule the end of a section");
+    }
+}
+
+struct SectionEndNotifier;
+
+#[async_trait]
+impl EventHandler for SectionEndNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(track_list) = ctx else {
+            return None;
+        };
+        let (_, handle) = track_list.first()?;
+        // the queue goes on with the next track
+        if let Err(e) = handle.stop() {
+            tracing::debug!(?e, "could not stop a track at the end of its section");
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_url() {
+        let section = Section::from_url("https://www.youtube.com/watch?v=34CZjsEI1yU&t=90");
+        assert_eq!(section.start, Duration::from_secs(90));
+        assert_eq!(section.end, None);
+        let section = Section::from_url("https://youtu.be/34CZjsEI1yU?start=1m30s&end=300");
+        assert_eq!(section.start, Duration::from_secs(90));
+        assert_eq!(section.end, Some(Duration::from_secs(300)));
+        let section = Section::from_url("https://www.youtube.com/watch?v=34CZjsEI1yU#t=2m");
+        assert_eq!(section.start, Duration::from_secs(120));
+        // an end before the start is left out
+        let section = Section::from_url("https://youtu.be/34CZjsEI1yU?t=90&end=60");
+        assert_eq!(section.end, None);
+        assert!(Section::from_url("https://www.youtube.com/watch?v=34CZjsEI1yU").is_full());
+        assert!(Section::from_url("kraftwerk the model").is_full());
+    }
+
+    #[test]
+    fn test_duration() {
+        let section = Section {
+            start: Duration::from_secs(90),
+            end: Some(Duration::from_secs(300)),
+        };
+        let minutes = |m: u64| Some(Duration::from_secs(m * 60));
+        assert_eq!(
+            section.duration(minutes(10)),
+            Some(Duration::from_secs(210))
+        );
+        assert_eq!(section.duration(minutes(4)), Some(Duration::from_secs(150)));
+        assert_eq!(section.duration(None), Some(Duration::from_secs(210)));
+        assert_eq!(section.range(), "01:30–05:00");
+        let section = Section {
+            end: None,
+            ..section
+        };
+        assert_eq!(
+            section.duration(minutes(10)),
+            Some(Duration::from_secs(510))
+        );
+        assert_eq!(section.duration(None), None);
+        assert_eq!(section.range(), "01:30–");
+    }
+}
@@ -1,5 +1,10 @@
+use crate::config::Config;
+use crate::filter::Filters;
+use crate::lyrics::LyricsProviders;
+use crate::ytdlp::YtDlp;
 use dashmap::DashMap;
 use songbird::Songbird;
+use std::collections::VecDeque;
 use std::sync::Arc;
 use twilight_cache_inmemory::InMemoryCache;
 use twilight_http::Client as HttpClient;
@@ -11,11 +16,22 @@ pub(crate) type State = Arc<StateRef>;
 #[derive(Debug)]
 pub(crate) struct Settings {
     pub(crate) loop_queue: bool,
+    /// URLs of the tracks autoplay picked last, newest last.
+    pub(crate) autoplayed: VecDeque<String>,
+    /// Filters that tracks are played with, from when they start.
+    pub(crate) filters: Filters,
+    /// Volume that all tracks are played at, 1.0 being their normal volume.
+    pub(crate) volume: f32,
 }
 
 impl Settings {
     pub(crate) fn new() -> Self {
-        Self { loop_queue: false }
+        Self {
+            loop_queue: false,
+            autoplayed: VecDeque::new(),
+            filters: Filters::new(),
+            volume: 1.0,
+        }
     }
 }
 
@@ -34,4 +50,7 @@ pub(crate) struct StateRef {
     pub(crate) guild_settings: DashMap<Id<GuildMarker>, Settings>,
     pub(crate) pool: sqlx::SqlitePool,
     pub(crate) client: reqwest::Client,
+    pub(crate) ytdlp: YtDlp,
+    pub(crate) lyrics: LyricsProviders,
+    pub(crate) config: Config,
 }
@@ -1,5 +1,10 @@
 use futures::Future;
 use std::error::Error;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use twilight_model::id::Id;
+
+/// Milliseconds between the Unix epoch and the first Discord snowflake.
+pub(crate) const DISCORD_EPOCH_MS: u64 = 1_420_070_400_000;
 
 pub(crate) fn spawn(
     fut: impl Future<Output = Result<(), Box<dyn Error + Send + Sync + 'static>>> + Send + 'static,
@@ -10,3 +15,94 @@ pub(crate) fn spawn(
         }
     });
 }
+
+/// Time since the Unix epoch.
+pub(crate) fn unix_now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Time since the Unix epoch at which the object with `id` was created.
+pub(crate) fn created_at<T>(id: Id<T>) -> Duration {
+    Duration::from_millis((id.get() >> 22) + DISCORD_EPOCH_MS)
+}
+
+/// Formats `duration` like `01:02:03`, leaving out the hours if there are none.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let res = duration.as_secs();
+    let hours = res / (60 * 60);
+    let res = res - hours * 60 * 60;
+    let minutes = res / 60;
+    let res = res - minutes * 60;
+    let seconds = res;
+    let mut s = String::new();
+    if hours > 0 {
+        s.push_str(format!("{hours:02}:").as_str());
+    }
+    s.push_str(format!("{minutes:02}:{seconds:02}").as_str());
+    s
+}
+
+/// Parses a duration like `3:42` or `1:02:03`, as yt-dlp writes them.
+pub(crate) fn parse_duration(s: &str) -> Option<Duration> {
+    let mut secs = 0;
+    for part in s.trim().split(':') {
+        secs = secs * 60 + part.parse::<u64>().ok()?;
+    }
+    Some(Duration::from_secs(secs))
+}
+
+/// Parses a timestamp like `90`, `1m30s` or `1:30`, as they appear in YouTube URLs.
+pub(crate) fn parse_timestamp(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    if s.contains(':') || s.bytes().all(|b| b.is_ascii_digit()) {
+        return parse_duration(s);
+    }
+    let mut secs = 0;
+    let mut rest = s;
+    for (unit, factor) in [('h', 60 * 60), ('m', 60), ('s', 1)] {
+        if let Some((value, tail)) = rest.split_once(unit) {
+            secs += value.parse::<u64>().ok()? * factor;
+            rest = tail;
+        }
+    }
+    rest.is_empty().then(|| Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use twilight_model::id::marker::MessageMarker;
+
+    #[test]
+    fn test_created_at() {
+        // https://discord.com/developers/docs/reference#snowflakes
+        let id: Id<MessageMarker> = Id::new(175928847299117063);
+        assert_eq!(created_at(id).as_millis(), 1462015105796);
+    }
+
+    #[test]
+    fn test_durations() {
+        assert_eq!(parse_duration("3:42"), Some(Duration::from_secs(222)));
+        assert_eq!(parse_duration("1:02:03"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_duration("42"), Some(Duration::from_secs(42)));
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("live"), None);
+        assert_eq!(format_duration(Duration::from_secs(222)), "03:42");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "01:02:03");
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_timestamp("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_timestamp("1m30s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_timestamp("1h2m3s"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_timestamp("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_timestamp("1:30"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_timestamp(""), None);
+        assert_eq!(parse_timestamp("1h30"), None);
+        assert_eq!(parse_timestamp("soon"), None);
+    }
+}
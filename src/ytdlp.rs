@@ -0,0 +1,454 @@
+use crate::resolve::ResolveError;
+use serde::{Deserialize, Serialize};
+use songbird::input::{AuxMetadata, YoutubeDl};
+use std::{
+    io::{BufRead, BufReader},
+    process::Output,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{process::Command, sync::Semaphore, time};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct YouTubeTrack {
+    pub(crate) url: Option<String>,
+    pub(crate) original_url: Option<String>,
+    pub(crate) title: String,
+    pub(crate) channel: String,
+    pub(crate) playlist: Option<String>,
+    pub(crate) playlist_id: Option<String>,
+    pub(crate) duration_string: String,
+    pub(crate) thumbnail: Option<String>,
+    /// Segments marked on SponsorBlock, only fetched with `--sponsorblock-mark`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) sponsorblock_chapters: Vec<Segment>,
+    /// Chapters of a single video, empty for the entries of playlists.
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub(crate) chapters: Vec<Chapter>,
+}
+
+/// Chapter of a video, like a track of an album.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Chapter {
+    pub(crate) start_time: f64,
+    pub(crate) end_time: f64,
+    pub(crate) title: String,
+}
+
+/// Reads `null`, which yt-dlp writes for videos without chapters, as an empty list.
+fn nullable<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(Option::<Vec<T>>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Part of a video that SponsorBlock users marked, e.g. as a sponsor message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Segment {
+    pub(crate) start_time: f64,
+    pub(crate) end_time: f64,
+    pub(crate) category: String,
+}
+
+impl Segment {
+    /// Categories of segments that are not part of the music.
+    const NON_MUSIC: [&'static str; 8] = [
+        "sponsor",
+        "selfpromo",
+        "interaction",
+        "intro",
+        "outro",
+        "preview",
+        "music_offtopic",
+        "filler",
+    ];
+
+    pub(crate) fn is_music(&self) -> bool {
+        !Self::NON_MUSIC.contains(&self.category.as_str())
+    }
+}
+
+/// Invokes the yt-dlp executable.
+#[derive(Debug, Clone)]
+pub(crate) struct YtDlp {
+    program: &'static str,
+    args: Vec<String>,
+    timeout: Duration,
+    permits: Arc<Semaphore>,
+}
+
+impl YtDlp {
+    pub(crate) fn new(program: &'static str) -> Self {
+        Self {
+            program,
+            args: Vec::new(),
+            timeout: Duration::from_secs(60),
+            permits: Arc::new(Semaphore::new(4)),
+        }
+    }
+
+    /// Sets additional arguments passed to every invocation, e.g. `--cookies`.
+    pub(crate) fn args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Sets the time after which a yt-dlp process is killed.
+    pub(crate) fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how many yt-dlp processes may run at the same time across all guilds.
+    pub(crate) fn max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.permits = Arc::new(Semaphore::new(max_concurrent.max(1)));
+        self
+    }
+
+    /// Creates a lazy songbird source for `url` that uses the configured executable.
+    pub(crate) fn source(&self, client: reqwest::Client, url: String) -> YoutubeDl<'static> {
+        YoutubeDl::new_ytdl_like(self.program, client, url).user_args(self.args.clone())
+    }
+
+    /// Returns the version of the configured executable.
+    pub(crate) async fn version(&self) -> Result<String, ResolveError> {
+        let output = self.run(&["--version"]).await?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    pub(crate) async fn get_tracks(&self, url: String) -> Result<Vec<YouTubeTrack>, ResolveError> {
+        let output = self.run(&[&url, "--flat-playlist", "-j"]).await?;
+
+        tracing::info!(
+            "yt-dlp output: {:?}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+
+        let tracks = parse_tracks(&output.stdout, &output.stderr)?;
+        tracing::info!("tracks: {:?}", tracks);
+        Ok(tracks)
+    }
+
+    /// Fetches the segments of the video at `url` that are not music, ordered by their start.
+    pub(crate) async fn segments(&self, url: String) -> Result<Vec<Segment>, ResolveError> {
+        let output = self
+            .run(&[&url, "--no-playlist", "-j", "--sponsorblock-mark", "all"])
+            .await?;
+        let track = parse_tracks(&output.stdout, &output.stderr)?
+            .into_iter()
+            .next()
+            .ok_or(ResolveError::NotFound)?;
+        let mut segments: Vec<_> = track
+            .sponsorblock_chapters
+            .into_iter()
+            .filter(|segment| !segment.is_music() && segment.end_time > segment.start_time)
+            .collect();
+        segments.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+        Ok(segments)
+    }
+
+    /// Fetches the metadata of the video at `url` with the same limits as any other
+    /// invocation, which songbird's own lookup would not kill after the timeout.
+    pub(crate) async fn aux_metadata(&self, url: &str) -> Result<AuxMetadata, ResolveError> {
+        let output = self.run(&["-j", url, "--no-playlist"]).await?;
+        parse_metadata(&output.stdout, &output.stderr)
+    }
+
+    async fn run(&self, args: &[&str]) -> Result<Output, ResolveError> {
+        let _permit = self.permits.acquire().await.expect("semaphore closed");
+        let output = Command::new(self.program)
+            .args(args)
+            .args(&self.args)
+            .kill_on_drop(true)
+            .output();
+        match time::timeout(self.timeout, output).await {
+            Ok(output) => output.map_err(|e| ResolveError::from_io(&e)),
+            Err(_) => {
+                tracing::warn!("yt-dlp did not finish within {:?}", self.timeout);
+                Err(ResolveError::Timeout)
+            }
+        }
+    }
+}
+
+/// Metadata of a video as printed by `yt-dlp -j`, the fields that songbird reads.
+#[derive(Debug, Deserialize)]
+struct VideoInfo {
+    title: Option<String>,
+    track: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    uploader: Option<String>,
+    channel: Option<String>,
+    release_date: Option<String>,
+    upload_date: Option<String>,
+    duration: Option<f64>,
+    thumbnail: Option<String>,
+    webpage_url: Option<String>,
+}
+
+impl From<VideoInfo> for AuxMetadata {
+    fn from(info: VideoInfo) -> Self {
+        Self {
+            track: info.track,
+            artist: info.artist.or(info.uploader),
+            album: info.album,
+            date: info.release_date.or(info.upload_date),
+            channels: Some(2),
+            channel: info.channel,
+            duration: info.duration.map(Duration::from_secs_f64),
+            source_url: info.webpage_url,
+            title: info.title,
+            thumbnail: info.thumbnail,
+            ..Self::default()
+        }
+    }
+}
+
+fn parse_metadata(stdout: &[u8], stderr: &[u8]) -> Result<AuxMetadata, ResolveError> {
+    let line = stdout
+        .split(|&b| b == b'\n')
+        .find(|line| !line.trim_ascii().is_empty());
+    let Some(line) = line else {
+        let stderr = String::from_utf8_lossy(stderr);
+        tracing::debug!("yt-dlp metadata error: {}", stderr);
+        return Err(ResolveError::from_stderr(&stderr).unwrap_or(ResolveError::Failed));
+    };
+    let info: VideoInfo =
+        serde_json::from_slice(line).map_err(|e| ResolveError::Parse(e.to_string()))?;
+    Ok(info.into())
+}
+
+fn parse_tracks(stdout: &[u8], stderr: &[u8]) -> Result<Vec<YouTubeTrack>, ResolveError> {
+    let mut parse_error = None;
+    let reader = BufReader::new(stdout);
+    let tracks: Vec<YouTubeTrack> = reader
+        .lines()
+        .map_while(Result::ok)
+        .filter(|line| !line.trim().is_empty())
+        .flat_map(|line| {
+            serde_json::from_str(&line)
+                .map_err(|e| parse_error = Some(e.to_string()))
+                .ok()
+        })
+        .collect();
+    tracing::info!("yt-dlp tracks: {:?}", tracks);
+
+    if !tracks.is_empty() {
+        return Ok(tracks);
+    }
+
+    let stderr = String::from_utf8_lossy(stderr);
+    if let Some(e) = ResolveError::from_stderr(&stderr) {
+        return Err(e);
+    }
+    match parse_error {
+        Some(e) => Err(ResolveError::Parse(e)),
+        None => Err(ResolveError::NotFound),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Returns a yt-dlp replaying the recorded outputs in `tests/fixtures/yt-dlp`.
+    pub(crate) fn fake_ytdlp() -> YtDlp {
+        YtDlp::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/fake-yt-dlp"
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_get_tracks() {
+        let urls = [
+            "https://www.youtube.com/playlist?list=PLFxxhcEeloYa1OlnWD6UgxlVQKJH5i_0p",
+            "https://music.youtube.com/watch?v=RO75ZzqUOJw",
+            "https://www.youtube.com/watch?v=qVHyl0P_P-M",
+            "https://www.youtube.com/watch?v=34CZjsEI1yU",
+        ];
+        for url in urls.iter() {
+            println!("url: {:?}", url);
+            let tracks = fake_ytdlp().get_tracks(url.to_string()).await.unwrap();
+            assert!(!tracks.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chapters() {
+        let tracks = fake_ytdlp()
+            .get_tracks("https://www.youtube.com/watch?v=Cn6xw9PFzD8".to_string())
+            .await
+            .unwrap();
+        let titles: Vec<_> = tracks[0]
+            .chapters
+            .iter()
+            .map(|c| c.title.as_str())
+            .collect();
+        assert_eq!(titles, ["The Robots", "Computer Love", "The Model"]);
+        assert_eq!(tracks[0].chapters[1].start_time, 372.0);
+        let tracks = fake_ytdlp()
+            .get_tracks("https://www.youtube.com/watch?v=34CZjsEI1yU".to_string())
+            .await
+            .unwrap();
+        assert!(tracks[0].chapters.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_segments() {
+        let segments = fake_ytdlp()
+            .segments("https://www.youtube.com/watch?v=Wz3lJ5Cb6oI".to_string())
+            .await
+            .unwrap();
+        let categories: Vec<_> = segments.iter().map(|s| s.category.as_str()).collect();
+        assert_eq!(categories, ["music_offtopic", "sponsor", "outro"]);
+        assert_eq!(segments[1].end_time, 130.5);
+        assert!(fake_ytdlp()
+            .segments("https://www.youtube.com/watch?v=34CZjsEI1yU".to_string())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_playlist_tracks() {
+        let url = "https://www.youtube.com/playlist?list=PLFxxhcEeloYa1OlnWD6UgxlVQKJH5i_0p";
+        let tracks = fake_ytdlp().get_tracks(url.to_string()).await.unwrap();
+        assert_eq!(tracks.len(), 3);
+        assert!(tracks
+            .iter()
+            .all(|t| t.playlist_id.as_deref() == Some("PLFxxhcEeloYa1OlnWD6UgxlVQKJH5i_0p")));
+        assert_eq!(
+            tracks[0].url.as_deref(),
+            Some("https://www.youtube.com/watch?v=RO75ZzqUOJw")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_premium_tracks() {
+        let urls = ["https://www.youtube.com/watch?v=QgMZRmxQ0Dc"];
+        for url in urls.iter() {
+            println!("url: {:?}", url);
+            let tracks = fake_ytdlp().get_tracks(url.to_string()).await;
+            assert!(matches!(tracks, Err(ResolveError::PremiumOnly)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_playlist_does_not_exist_tracks() {
+        let urls = ["https://www.youtube.com/playlist?list=PLox0oG0uy8Lc1IaIfGyrvtuRItuEyJiyG"];
+        for url in urls.iter() {
+            println!("url: {:?}", url);
+            let tracks = fake_ytdlp().get_tracks(url.to_string()).await;
+            assert!(matches!(tracks, Err(ResolveError::NotFound)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bot_check() {
+        let tracks = fake_ytdlp()
+            .get_tracks("ytsearch:bot check".to_string())
+            .await;
+        assert!(matches!(tracks, Err(ResolveError::BotCheck)));
+    }
+
+    #[tokio::test]
+    async fn test_missing_executable() {
+        let tracks = YtDlp::new("/nonexistent/yt-dlp")
+            .get_tracks("ytsearch:kraftwerk".to_string())
+            .await;
+        assert!(matches!(tracks, Err(ResolveError::YtDlpMissing)));
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let start = std::time::Instant::now();
+        let tracks = fake_ytdlp()
+            .timeout(Duration::from_millis(200))
+            .get_tracks("ytsearch:hang".to_string())
+            .await;
+        assert!(matches!(tracks, Err(ResolveError::Timeout)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_extra_args() {
+        let tracks = fake_ytdlp()
+            .args(vec!["--cookies".to_string(), "cookies.txt".to_string()])
+            .get_tracks("https://www.youtube.com/watch?v=34CZjsEI1yU".to_string())
+            .await
+            .unwrap();
+        assert_eq!(tracks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_aux_metadata() {
+        let metadata = fake_ytdlp()
+            .aux_metadata("https://www.youtube.com/watch?v=34CZjsEI1yU")
+            .await
+            .unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Kraftwerk - The Model"));
+        assert_eq!(metadata.artist.as_deref(), Some("Kraftwerk"));
+        assert_eq!(metadata.duration, Some(Duration::from_secs(222)));
+
+        let metadata = fake_ytdlp()
+            .aux_metadata("https://www.youtube.com/watch?v=QgMZRmxQ0Dc")
+            .await;
+        assert!(matches!(metadata, Err(ResolveError::PremiumOnly)));
+
+        let metadata = fake_ytdlp()
+            .timeout(Duration::from_millis(200))
+            .aux_metadata("ytsearch:hang")
+            .await;
+        assert!(matches!(metadata, Err(ResolveError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn test_version() {
+        assert_eq!(fake_ytdlp().version().await.unwrap(), "2024.08.06");
+    }
+
+    #[test]
+    fn test_parse_video() {
+        let stdout = include_bytes!("../tests/fixtures/yt-dlp/video.stdout");
+        let tracks = parse_tracks(stdout, b"").unwrap();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].title, "Kraftwerk - The Model");
+        assert_eq!(tracks[0].duration_string, "3:42");
+    }
+
+    #[test]
+    fn test_parse_playlist() {
+        let stdout = include_bytes!("../tests/fixtures/yt-dlp/playlist.stdout");
+        let tracks = parse_tracks(stdout, b"").unwrap();
+        assert_eq!(tracks.len(), 3);
+        assert_eq!(tracks[1].playlist.as_deref(), Some("Kraftwerk Essentials"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let stderr = include_bytes!("../tests/fixtures/yt-dlp/premium.stderr");
+        assert!(matches!(
+            parse_tracks(b"", stderr),
+            Err(ResolveError::PremiumOnly)
+        ));
+
+        let stdout = include_bytes!("../tests/fixtures/yt-dlp/garbage.stdout");
+        assert!(matches!(
+            parse_tracks(stdout, b""),
+            Err(ResolveError::Parse(_))
+        ));
+
+        assert!(matches!(
+            parse_tracks(b"", b""),
+            Err(ResolveError::NotFound)
+        ));
+    }
+}